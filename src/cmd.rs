@@ -17,8 +17,10 @@
 use Env;
 use LastList;
 use describe;
-use kube::{ContainerState, ConfigMapList, DeploymentList, Event, EventList, Metadata, NamespaceList,
-           NodeCondition, NodeList, Pod, PodList, ReplicaSetList, SecretList, ServiceList};
+use display::{format_age, format_ready};
+use kube::{ContainerState, ConfigMapList, DefaultStorageClass, DeploymentList, Event, EventList,
+           LogParams, Metadata, NamespaceList, NodeCondition, NodeList, Pod, PodHealth, PodList,
+           ReplicaSetList, SecretList, ServiceList};
 use output::ClickWriter;
 use table::CellSpec;
 use values::{get_val_as, val_item_count, val_str, val_u64};
@@ -247,6 +249,11 @@ fn phase_str(pod: &Pod) -> String {
     if let Some(_) = pod.metadata.deletion_timestamp {
         // Was deleted
         "Terminating".to_owned()
+    } else if let Some(ref reason) = pod.status.reason {
+        // like kubectl, a pod level reason (e.g. Evicted) says more than the phase
+        reason.clone()
+    } else if let Some(init) = pod.init_status() {
+        init
     } else if has_waiting(pod) {
         "ContainerCreating".to_owned()
     } else {
@@ -258,15 +265,8 @@ fn phase_str(pod: &Pod) -> String {
 fn ready_str(pod: &Pod) -> String {
     match pod.status.container_statuses {
         Some(ref statuses) => {
-            let mut count = 0;
-            let mut ready = 0;
-            for ref stat in statuses.iter() {
-                count += 1;
-                if stat.ready {
-                    ready += 1;
-                }
-            }
-            format!("{}/{}", ready, count)
+            let ready = statuses.iter().filter(|stat| stat.ready).count();
+            format_ready(ready as u32, statuses.len() as u32)
         }
         None => "unknown".to_owned(),
     }
@@ -288,32 +288,6 @@ fn phase_style_str(phase: &str) -> &'static str {
     }
 }
 
-fn time_since(date: DateTime<Utc>) -> String {
-    let now = Utc::now();
-    let diff = now.signed_duration_since(date);
-    if diff.num_days() > 0 {
-        format!(
-            "{}d {}h",
-            diff.num_days(),
-            (diff.num_hours() - (24 * diff.num_days()))
-        )
-    } else if diff.num_hours() > 0 {
-        format!(
-            "{}h {}m",
-            diff.num_hours(),
-            (diff.num_minutes() - (60 * diff.num_hours()))
-        )
-    } else if diff.num_minutes() > 0 {
-        format!(
-            "{}m {}s",
-            diff.num_minutes(),
-            (diff.num_seconds() - (60 * diff.num_minutes()))
-        )
-    } else {
-        format!("{}s", diff.num_seconds())
-    }
-}
-
 /// if s is longer than max_len it will be shorted and have ... added to be max_len
 fn shorten_to(s: String, max_len: usize) -> String {
     if s.len() > max_len {
@@ -354,12 +328,16 @@ fn print_podlist(
 
         {
             let ps = phase_str(&pod);
-            let ss = phase_style(&ps);
+            let ss = match pod.health() {
+                PodHealth::Healthy => phase_style(&ps),
+                PodHealth::Pending | PodHealth::Degraded(_) => "Fy",
+                PodHealth::Failed(_) => "Fr",
+            };
             specs.push(CellSpec::with_style_owned(ps, ss));
         }
 
         if let Some(ts) = pod.metadata.creation_timestamp {
-            specs.push(CellSpec::new_owned(time_since(ts)));
+            specs.push(CellSpec::new_owned(format_age(&ts)));
         } else {
             specs.push(CellSpec::new("unknown"));
         }
@@ -398,7 +376,11 @@ fn print_podlist(
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_pods = filtered.into_iter().map(|pod_spec| pod_spec.0).collect();
-    PodList { items: final_pods }
+    PodList {
+        type_meta: podlist.type_meta,
+        metadata: None,
+        items: final_pods,
+    }
 }
 
 /// Build a multi-line string of the specified keyvals
@@ -466,7 +448,7 @@ fn print_nodelist(
             specs.push(CellSpec::with_style_owned(state, state_style));
             specs.push(CellSpec::new_owned(format!(
                 "{}",
-                time_since(node.metadata.creation_timestamp.unwrap())
+                format_age(&node.metadata.creation_timestamp.unwrap())
             )));
             if labels {
                 specs.push(CellSpec::new_owned(keyval_string(&node.metadata.labels)));
@@ -483,7 +465,10 @@ fn print_nodelist(
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_nodes = filtered.into_iter().map(|node_spec| node_spec.0).collect();
-    NodeList { items: final_nodes }
+    NodeList {
+        type_meta: nodelist.type_meta,
+        items: final_nodes,
+    }
 }
 
 /// Print out the specified list of deployments in a pretty format
@@ -525,7 +510,7 @@ fn print_deployments(
         ));
         specs.push(CellSpec::new_owned(format!(
             "{}",
-            time_since(dep.metadata.creation_timestamp.unwrap())
+            format_age(&dep.metadata.creation_timestamp.unwrap())
         )));
         (dep, specs)
     });
@@ -538,7 +523,10 @@ fn print_deployments(
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_deps = filtered.into_iter().map(|dep_spec| dep_spec.0).collect();
-    DeploymentList { items: final_deps }
+    DeploymentList {
+        type_meta: deplist.type_meta,
+        items: final_deps,
+    }
 }
 
 /// Print out the specified list of deployments in a pretty format
@@ -616,7 +604,7 @@ fn print_servicelist(
         specs.push(CellSpec::new_owned(port_strs.join(",")));
         specs.push(CellSpec::new_owned(format!(
             "{}",
-            time_since(service.metadata.creation_timestamp.unwrap())
+            format_age(&service.metadata.creation_timestamp.unwrap())
         )));
 
         (service, specs)
@@ -634,6 +622,7 @@ fn print_servicelist(
         .map(|service_spec| service_spec.0)
         .collect();
     ServiceList {
+        type_meta: servlist.type_meta,
         items: final_services,
     }
 }
@@ -650,7 +639,7 @@ fn print_namespaces(nslist: &NamespaceList, regex: Option<Regex>, writer: &mut C
         specs.push(CellSpec::with_style(ps, phase_style_str(ps)));
         specs.push(CellSpec::new_owned(format!(
             "{}",
-            time_since(ns.metadata.creation_timestamp.unwrap())
+            format_age(&ns.metadata.creation_timestamp.unwrap())
         )));
         (ns, specs)
     });
@@ -852,7 +841,7 @@ command!(
             urlstr.push_str(node);
         }
 
-        let pl: Option<PodList> = env.run_on_kluster(|k| k.get(urlstr.as_str()));
+        let pl: Option<PodList> = env.run_on_kluster(|k| k.get_list(urlstr.as_str()));
 
         match pl {
             Some(l) => {
@@ -947,33 +936,27 @@ command!(
         let cont = matches.value_of("container").unwrap(); // required so unwrap safe
         match (&env.current_object_namespace, env.current_pod()) {
             (&Some(ref ns), Some(ref pod)) => {
-                let mut url = format!(
-                    "/api/v1/namespaces/{}/pods/{}/log?container={}",
-                    ns, pod, cont
-                );
-                if matches.is_present("follow") {
-                    url.push_str("&follow=true");
-                }
-                if matches.is_present("previous") {
-                    url.push_str("&previous=true");
-                }
+                let mut params = LogParams::default();
+                params.container = Some(cont.to_owned());
+                params.follow = matches.is_present("follow");
+                params.previous = matches.is_present("previous");
                 if matches.is_present("tail") {
-                    url.push_str(
-                        format!("&tailLines={}", matches.value_of("tail").unwrap()).as_str(),
-                    );
+                    // validated already
+                    params.tail_lines = Some(matches.value_of("tail").unwrap().parse().unwrap());
                 }
                 if matches.is_present("since") {
                      // all unwraps already validated
                     let dur = parse_duration(matches.value_of("since").unwrap()).unwrap();
-                    url.push_str(format!("&sinceSeconds={}", dur.as_secs()).as_str());
+                    params.since_seconds = Some(dur.as_secs() as i64);
                 }
                 if matches.is_present("sinceTime") {
                     let specified = DateTime::parse_from_rfc3339(
                         matches.value_of("sinceTime").unwrap(),
                     ).unwrap();
                     let dur = Utc::now().signed_duration_since(specified.with_timezone(&Utc));
-                    url.push_str(format!("&sinceSeconds={}", dur.num_seconds()).as_str());
+                    params.since_seconds = Some(dur.num_seconds());
                 }
+                let url = params.path(ns, pod);
                 let logs_reader =
                     env.run_on_kluster(|k| k.get_read(url.as_str(), Some(Duration::new(1, 0))));
                 if let Some(lreader) = logs_reader {
@@ -1332,13 +1315,18 @@ command!(
           .takes_value(false)
           .conflicts_with("grace")
           .conflicts_with("now")
+    ).arg(Arg::with_name("dry-run")
+          .long("dry-run")
+          .help("Have the server check the delete and report what it would do, without actually \
+                 deleting anything")
+          .takes_value(false)
     ),
     vec!["delete"],
     noop_complete,
     |matches, env, writer| {
         if let Some(ref ns) = env.current_object_namespace {
             let mut no_delete_opts = false;
-            if let Some(mut url) = match env.current_object {
+            if let Some(url) = match env.current_object {
                 ::KObj::Pod { ref name, .. } => {
                     clickwrite!(writer, "Delete pod {} [y/N]? ", name);
                     Some(format!("/api/v1/namespaces/{}/pods/{}", ns, name))
@@ -1430,10 +1418,17 @@ command!(
                             }
                             Some(delete_body.to_string())
                         };
-                        let result = env.run_on_kluster(|k| k.delete(url.as_str(),
-                                                                     delete_body));
+                        let dry_run = matches.is_present("dry-run");
+                        let result = env.run_on_kluster(|k| if dry_run {
+                            k.delete_dry_run(url.as_str(), delete_body)
+                        } else {
+                            k.delete(url.as_str(), delete_body)
+                        });
                         if let Some(x) = result {
-                            if x.status.is_success() {
+                            if x.status.is_success() && dry_run {
+                                clickwrite!(writer, "Delete would succeed (dry run, nothing was \
+                                                     deleted)\n");
+                            } else if x.status.is_success() {
                                 clickwrite!(writer, "Deleted\n");
                             } else {
                                 clickwrite!(writer, "Failed to delete: {:?}", x.get_ref());
@@ -1461,15 +1456,32 @@ fn containers_string(pod: &Pod) -> String {
             buf.push_str(format!("Name:\t{}\n", cont.name).as_str());
             buf.push_str(format!("  Image:\t{}\n", cont.image).as_str());
             buf.push_str(format!("  State:\t{}\n", cont.state).as_str());
+            if let Some(last) = cont.last_termination_summary() {
+                buf.push_str(format!("  Last Run:\t{}\n", last).as_str());
+            }
             buf.push_str(format!("  Ready:\t{}\n", cont.ready).as_str());
 
             // find the spec for this container
             let mut spec_it = pod.spec.containers.iter().filter(|cs| cs.name == cont.name);
             if let Some(spec) = spec_it.next() {
+                if let Some(ref probe) = spec.liveness_probe {
+                    buf.push_str(format!("  Liveness:\t{}\n", probe).as_str());
+                }
+                if let Some(ref probe) = spec.readiness_probe {
+                    buf.push_str(format!("  Readiness:\t{}\n", probe).as_str());
+                }
+                if let Some(ref probe) = spec.startup_probe {
+                    buf.push_str(format!("  Startup:\t{}\n", probe).as_str());
+                }
                 if let Some(ref vols) = spec.volume_mounts {
                     buf.push_str("  Volumes:\n");
                     for vol in vols.iter() {
                         buf.push_str(format!("   {}\n", vol.name).as_str());
+                        if let Some(source) = pod.spec.volume_for_mount(vol) {
+                            buf.push_str(
+                                format!("    Source:\t{}\n", source.source_summary()).as_str(),
+                            );
+                        }
                         buf.push_str(format!("    Path:\t{}\n", vol.mount_path).as_str());
                         buf.push_str(
                             format!(
@@ -1485,12 +1497,28 @@ fn containers_string(pod: &Pod) -> String {
                 } else {
                     buf.push_str("  No Volumes\n");
                 }
+                if let Some(ref env) = spec.env {
+                    buf.push_str("  Environment:\n");
+                    for var in env.iter() {
+                        buf.push_str(
+                            format!("   {}:\t{}\n", var.name, var.value_summary()).as_str(),
+                        );
+                    }
+                }
             }
             buf.push('\n');
         }
     } else {
         buf.push_str("<No Containers>\n");
     }
+    if let Some(ref stats) = pod.status.ephemeral_container_statuses {
+        for cont in stats.iter() {
+            buf.push_str(format!("Name:\t{} (ephemeral)\n", cont.name).as_str());
+            buf.push_str(format!("  Image:\t{}\n", cont.image).as_str());
+            buf.push_str(format!("  State:\t{}\n", cont.state).as_str());
+            buf.push('\n');
+        }
+    }
     buf
 }
 
@@ -1539,7 +1567,7 @@ command!(
         if let Some(ref pod) = env.current_pod() {
             let url = format!("/api/v1/namespaces/{}/events?fieldSelector=involvedObject.name={},involvedObject.namespace={}",
                                    ns,pod,ns);
-            let oel: Option<EventList> = env.run_on_kluster(|k| k.get(url.as_str()));
+            let oel: Option<EventList> = env.run_on_kluster(|k| k.get_list(url.as_str()));
             if let Some(el) = oel {
                 if el.items.len() > 0 {
                     for e in el.items.iter() {
@@ -1588,7 +1616,7 @@ command!(
         };
 
         let url = "/api/v1/nodes";
-        let nl: Option<NodeList> = env.run_on_kluster(|k| k.get(url));
+        let nl: Option<NodeList> = env.run_on_kluster(|k| k.get_list(url));
         match nl {
             Some(n) => {
                 let final_list = print_nodelist(n, matches.is_present("labels"), regex, writer);
@@ -1632,7 +1660,7 @@ command!(
         } else {
             "/api/v1/services".to_owned()
         };
-        let sl: Option<ServiceList> = env.run_on_kluster(|k| k.get(url.as_str()));
+        let sl: Option<ServiceList> = env.run_on_kluster(|k| k.get_list(url.as_str()));
         if let Some(s) = sl {
             let filtered = print_servicelist(s, regex, matches.is_present("labels"), writer);
             env.set_lastlist(LastList::ServiceList(filtered));
@@ -1736,7 +1764,7 @@ command!(
             urlstr.push_str(label_selector);
         }
 
-        let dl: Option<DeploymentList> = env.run_on_kluster(|k| k.get(urlstr.as_str()));
+        let dl: Option<DeploymentList> = env.run_on_kluster(|k| k.get_list(urlstr.as_str()));
         match dl {
             Some(d) => {
                 let final_list = print_deployments(d, matches.is_present("labels"), regex, writer);
@@ -1783,7 +1811,10 @@ fn print_replicasets(
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_rss = filtered.into_iter().map(|rs_spec| rs_spec.0).collect();
-    ReplicaSetList { items: final_rss }
+    ReplicaSetList {
+        type_meta: list.type_meta,
+        items: final_rss,
+    }
 }
 
 command!(
@@ -1820,7 +1851,7 @@ command!(
             "/apis/extensions/v1beta1/replicasets".to_owned()
         };
 
-        let rsl: Option<ReplicaSetList> = env.run_on_kluster(|k| k.get(urlstr.as_str()));
+        let rsl: Option<ReplicaSetList> = env.run_on_kluster(|k| k.get_list(urlstr.as_str()));
 
         match rsl {
             Some(l) => {
@@ -1850,7 +1881,7 @@ fn print_configmaps(
         specs.push(CellSpec::new_owned(format!("{}", data_count)));
         specs.push(CellSpec::new_owned(format!(
             "{}",
-            time_since(metadata.creation_timestamp.unwrap())
+            format_age(&metadata.creation_timestamp.unwrap())
         )));
         (cm, specs)
     });
@@ -1863,7 +1894,10 @@ fn print_configmaps(
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_rss = filtered.into_iter().map(|cm_spec| cm_spec.0).collect();
-    ConfigMapList { items: final_rss }
+    ConfigMapList {
+        type_meta: list.type_meta,
+        items: final_rss,
+    }
 }
 
 command!(
@@ -1900,7 +1934,7 @@ command!(
             "/api/v1/configmaps".to_owned()
         };
 
-        let cml: Option<ConfigMapList> = env.run_on_kluster(|k| k.get(urlstr.as_str()));
+        let cml: Option<ConfigMapList> = env.run_on_kluster(|k| k.get_list(urlstr.as_str()));
 
         match cml {
             Some(l) => {
@@ -1934,7 +1968,7 @@ fn print_secrets(list: SecretList, regex: Option<Regex>, writer: &mut ClickWrite
         )));
         specs.push(CellSpec::new_owned(format!(
             "{}",
-            time_since(metadata.creation_timestamp.unwrap())
+            format_age(&metadata.creation_timestamp.unwrap())
         )));
         (rs, specs)
     });
@@ -1947,7 +1981,10 @@ fn print_secrets(list: SecretList, regex: Option<Regex>, writer: &mut ClickWrite
     ::table::print_table(&mut table, &filtered, writer);
 
     let final_rss = filtered.into_iter().map(|rs_spec| rs_spec.0).collect();
-    SecretList { items: final_rss }
+    SecretList {
+        type_meta: list.type_meta,
+        items: final_rss,
+    }
 }
 
 command!(
@@ -1984,7 +2021,7 @@ command!(
             "/api/v1/secrets".to_owned()
         };
 
-        let sl: Option<SecretList> = env.run_on_kluster(|k| k.get(urlstr.as_str()));
+        let sl: Option<SecretList> = env.run_on_kluster(|k| k.get_list(urlstr.as_str()));
 
         match sl {
             Some(l) => {
@@ -2020,7 +2057,7 @@ command!(
             }
         };
 
-        let nl: Option<NamespaceList> = env.run_on_kluster(|k| k.get("/api/v1/namespaces"));
+        let nl: Option<NamespaceList> = env.run_on_kluster(|k| k.get_list("/api/v1/namespaces"));
 
         if let Some(l) = nl {
             print_namespaces(&l, regex, writer);
@@ -2028,6 +2065,38 @@ command!(
     }
 );
 
+command!(
+    StorageClassCmd,
+    "storageclass",
+    "Print the storage class used by claims that don't name one",
+    identity,
+    vec!["storageclass", "sc"],
+    noop_complete,
+    |_, env, writer| {
+        let default = env.run_on_kluster(|k| k.default_storage_class());
+        match default {
+            Some(DefaultStorageClass::One(sc)) => {
+                clickwrite!(writer, "{} (provisioner: {})\n", sc.metadata.name, sc.provisioner);
+            }
+            Some(DefaultStorageClass::None) => {
+                clickwrite!(
+                    writer,
+                    "No default storage class, claims without a class won't be provisioned\n"
+                );
+            }
+            Some(DefaultStorageClass::Multiple(names)) => {
+                clickwrite!(
+                    writer,
+                    "{} Multiple default storage classes: {}\n",
+                    Yellow.paint("Warning:"),
+                    names.join(", ")
+                );
+            }
+            None => {}
+        }
+    }
+);
+
 command!(
     UtcCmd,
     "utc",
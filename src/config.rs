@@ -14,13 +14,19 @@
 
 //! Handle reading .kube/config files
 
+use chrono::{DateTime, Duration, Utc};
+use ring::digest;
+use serde_json;
 use serde_yaml;
 
 use std::collections::HashMap;
 use std::env;
 use std::error::Error;
-use std::fs::File;
-use std::io::{self, BufReader, Read};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, stderr, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 use error::{KubeErrNo, KubeError};
 use kube::{Kluster, KlusterAuth};
@@ -29,6 +35,8 @@ use certs::{get_cert, get_cert_from_pem, get_key_from_str, get_private_key};
 /// Kubernetes cluster config
 #[derive(Debug, Deserialize)]
 struct IConfig {
+    #[serde(rename = "current-context")]
+    current_context: Option<String>,
     clusters: Vec<ICluster>,
     contexts: Vec<IContext>,
     users: Vec<IUser>,
@@ -104,6 +112,36 @@ pub struct UserConf {
 
     pub username: Option<String>,
     pub password: Option<String>,
+
+    pub exec: Option<ExecConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExecEnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+/// A credential plugin: a command that prints an ExecCredential with a token for the cluster
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExecConfig {
+    pub command: String,
+    pub args: Option<Vec<String>>,
+    pub env: Option<Vec<ExecEnvVar>>,
+    #[serde(rename = "apiVersion")]
+    pub api_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ExecCredentialStatus {
+    token: Option<String>,
+    #[serde(rename = "expirationTimestamp")]
+    expiration_timestamp: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ExecCredential {
+    status: Option<ExecCredentialStatus>,
 }
 
 impl IConfig {
@@ -121,11 +159,82 @@ impl IConfig {
 #[derive(Debug)]
 pub struct Config {
     pub source_file: String,
+    pub current_context: Option<String>,
     pub clusters: HashMap<String, ClusterConf>,
     pub contexts: HashMap<String, ContextConf>,
     pub users: HashMap<String, UserConf>,
 }
 
+/// Something wrong with a kubernetes config that would stop a context from working
+#[derive(Debug)]
+pub enum ConfigProblem {
+    NoCurrentContext,
+    UnknownCurrentContext(String),
+    /// A context references a cluster that isn't defined (or that couldn't be loaded)
+    MissingCluster { context: String, cluster: String },
+    MissingUser { context: String, user: String },
+    /// A user's client certificate or key file can't be read
+    UnreadableFile {
+        context: String,
+        user: String,
+        path: String,
+        error: String,
+    },
+}
+
+impl ConfigProblem {
+    /// The context this problem affects, if it's specific to one
+    pub fn context(&self) -> Option<&str> {
+        match *self {
+            ConfigProblem::NoCurrentContext => None,
+            ConfigProblem::UnknownCurrentContext(ref context)
+            | ConfigProblem::MissingCluster { ref context, .. }
+            | ConfigProblem::MissingUser { ref context, .. }
+            | ConfigProblem::UnreadableFile { ref context, .. } => Some(context.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigProblem::NoCurrentContext => write!(f, "No current-context is set"),
+            ConfigProblem::UnknownCurrentContext(ref context) => write!(
+                f,
+                "current-context is set to \"{}\", but no such context exists",
+                context
+            ),
+            ConfigProblem::MissingCluster {
+                ref context,
+                ref cluster,
+            } => write!(
+                f,
+                "Context \"{}\" uses cluster \"{}\", which is not defined or could not be \
+                 loaded",
+                context, cluster
+            ),
+            ConfigProblem::MissingUser {
+                ref context,
+                ref user,
+            } => write!(
+                f,
+                "Context \"{}\" uses user \"{}\", which is not defined",
+                context, user
+            ),
+            ConfigProblem::UnreadableFile {
+                ref context,
+                ref user,
+                ref path,
+                ref error,
+            } => write!(
+                f,
+                "Context \"{}\": can't read {} for user \"{}\": {}",
+                context, path, user, error
+            ),
+        }
+    }
+}
+
 // some utility functions
 fn get_full_path(path: String) -> Result<String, KubeError> {
     if path.is_empty() {
@@ -213,10 +322,189 @@ fn auth_from_data(
     }
 }
 
+/// Where to cache the credential from an exec plugin.  The file name is a hash of the command,
+/// its arguments and environment, and the cluster, so different plugins (or the same plugin for
+/// different clusters) don't share credentials.
+fn exec_cache_path(exec: &ExecConfig, server: &str) -> Option<PathBuf> {
+    let mut key = String::new();
+    key.push_str(exec.command.as_str());
+    for arg in exec.args.iter().flat_map(|a| a.iter()) {
+        key.push('\0');
+        key.push_str(arg.as_str());
+    }
+    for var in exec.env.iter().flat_map(|e| e.iter()) {
+        key.push('\0');
+        key.push_str(format!("{}={}", var.name, var.value).as_str());
+    }
+    key.push('\0');
+    key.push_str(server);
+    let hash = digest::digest(&digest::SHA256, key.as_bytes());
+    let hex: String = hash.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    env::home_dir().map(|mut path| {
+        path.push(".kube");
+        path.push("cache");
+        path.push("click-exec");
+        path.push(format!("{}.json", hex));
+        path
+    })
+}
+
+/// Return the cached token, if there is one and it isn't going to expire in the next minute
+fn cached_exec_token(path: &Path) -> Option<String> {
+    let f = File::open(path).ok()?;
+    let cred: ExecCredential = serde_json::from_reader(f).ok()?;
+    let status = cred.status?;
+    match status.expiration_timestamp {
+        Some(expires) if expires > Utc::now() + Duration::minutes(1) => status.token,
+        _ => None,
+    }
+}
+
+fn save_exec_credential(path: &Path, cred: &ExecCredential) -> Result<(), KubeError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut opts = OpenOptions::new();
+    opts.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        // the token is a secret
+        opts.mode(0o600);
+    }
+    let mut file = opts.open(path)?;
+    file.write_all(serde_json::to_string(cred)?.as_bytes())?;
+    Ok(())
+}
+
+/// Get a token by running an exec credential plugin, or from the cache of its last output if
+/// that hasn't expired, so the plugin (which may prompt the user) isn't run on every connect.
+/// Credentials without an expiration are never cached.
+fn auth_from_exec(
+    exec: &ExecConfig,
+    server: &str,
+    context: &str,
+) -> Result<KlusterAuth, KubeError> {
+    auth_from_exec_cached(exec, exec_cache_path(exec, server), context)
+}
+
+/// auth_from_exec, caching the credential at cache_path (if there is one)
+fn auth_from_exec_cached(
+    exec: &ExecConfig,
+    cache_path: Option<PathBuf>,
+    context: &str,
+) -> Result<KlusterAuth, KubeError> {
+    if let Some(token) = cache_path.as_ref().and_then(|p| cached_exec_token(p)) {
+        return Ok(KlusterAuth::with_token(token.as_str()));
+    }
+
+    let api_version = exec.api_version
+        .clone()
+        .unwrap_or_else(|| "client.authentication.k8s.io/v1beta1".to_owned());
+    let exec_info = json!({
+        "apiVersion": api_version,
+        "kind": "ExecCredential",
+        "spec": { "interactive": true },
+    });
+    let mut cmd = Command::new(exec.command.as_str());
+    cmd.args(exec.args.iter().flat_map(|a| a.iter()))
+        .env("KUBERNETES_EXEC_INFO", exec_info.to_string())
+        // let the plugin prompt the user if it needs to
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit());
+    for var in exec.env.iter().flat_map(|e| e.iter()) {
+        cmd.env(var.name.as_str(), var.value.as_str());
+    }
+    let output = cmd.output().map_err(|e| {
+        KubeError::ConfigFileError(format!(
+            "Could not run credential plugin '{}' for {}: {}",
+            exec.command, context, e
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(KubeError::ConfigFileError(format!(
+            "Credential plugin '{}' for {} failed: {}",
+            exec.command, context, output.status
+        )));
+    }
+    let cred: ExecCredential = serde_json::from_slice(output.stdout.as_slice())?;
+    let token = match cred.status {
+        Some(ExecCredentialStatus {
+            token: Some(ref token),
+            ..
+        }) => token.clone(),
+        _ => {
+            return Err(KubeError::ConfigFileError(format!(
+                "Credential plugin '{}' for {} didn't return a token",
+                exec.command, context
+            )))
+        }
+    };
+    let expires = cred.status.as_ref().and_then(|s| s.expiration_timestamp);
+    if let (Some(path), Some(_)) = (cache_path, expires) {
+        if let Err(e) = save_exec_credential(path.as_path(), &cred) {
+            // not fatal, we'll just run the plugin again next time
+            writeln!(stderr(), "[WARNING] Couldn't cache credential for {}: {}", context, e)
+                .unwrap_or(());
+        }
+    }
+    Ok(KlusterAuth::with_token(token.as_str()))
+}
+
 impl Config {
-    pub fn from_file(path: &str) -> Result<Config, KubeError> {
-        let iconf = IConfig::from_file(path)?;
+    /// Load and merge several config files, as kubectl does with the files in $KUBECONFIG.  When
+    /// files define a cluster, context or user with the same name the first one wins, and the
+    /// current-context comes from the first file that sets one.  Files that don't exist are
+    /// skipped, but at least one must load.
+    pub fn merge(files: &[PathBuf]) -> Result<Config, KubeError> {
+        let mut merged: Option<IConfig> = None;
+        let mut loaded = Vec::new();
+        for file in files.iter() {
+            if !file.exists() {
+                continue;
+            }
+            let path = file.to_string_lossy().into_owned();
+            let iconf = IConfig::from_file(path.as_str())?;
+            loaded.push(path);
+            merged = Some(match merged {
+                None => iconf,
+                Some(mut m) => {
+                    if m.current_context.is_none() {
+                        m.current_context = iconf.current_context;
+                    }
+                    for cluster in iconf.clusters.into_iter() {
+                        if !m.clusters.iter().any(|c| c.name == cluster.name) {
+                            m.clusters.push(cluster);
+                        }
+                    }
+                    for context in iconf.contexts.into_iter() {
+                        if !m.contexts.iter().any(|c| c.name == context.name) {
+                            m.contexts.push(context);
+                        }
+                    }
+                    for user in iconf.users.into_iter() {
+                        if !m.users.iter().any(|u| u.name == user.name) {
+                            m.users.push(user);
+                        }
+                    }
+                    m
+                }
+            });
+        }
+        match merged {
+            Some(iconf) => Config::from_iconfig(iconf, loaded.join(":").as_str()),
+            None => Err(KubeError::ConfigFileError(format!(
+                "None of the config files exist: {}",
+                files
+                    .iter()
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .collect::<Vec<String>>()
+                    .join(":")
+            ))),
+        }
+    }
 
+    fn from_iconfig(iconf: IConfig, path: &str) -> Result<Config, KubeError> {
         // copy over clusters
         let mut cluster_map = HashMap::new();
         for cluster in iconf.clusters.into_iter() {
@@ -308,12 +596,86 @@ impl Config {
 
         Ok(Config {
             source_file: path.to_owned(),
+            current_context: iconf.current_context,
             clusters: cluster_map,
             contexts: context_map,
             users: user_map,
         })
     }
 
+    /// Check the config for problems that would stop contexts from working, so they can be
+    /// reported clearly up front rather than as a failure when connecting
+    pub fn validate(&self) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        match self.current_context {
+            Some(ref current) => {
+                if !self.contexts.contains_key(current) {
+                    problems.push(ConfigProblem::UnknownCurrentContext(current.clone()));
+                }
+            }
+            None => problems.push(ConfigProblem::NoCurrentContext),
+        }
+
+        let mut names: Vec<&String> = self.contexts.keys().collect();
+        names.sort();
+        for name in names.into_iter() {
+            let ctx = &self.contexts[name];
+            if !self.clusters.contains_key(&ctx.cluster) {
+                problems.push(ConfigProblem::MissingCluster {
+                    context: name.clone(),
+                    cluster: ctx.cluster.clone(),
+                });
+            }
+            match self.users.get(&ctx.user) {
+                Some(user) => {
+                    for path in user.client_cert.iter().chain(user.client_key.iter()) {
+                        let readable = get_full_path(path.clone())
+                            .and_then(|full| File::open(full).map_err(KubeError::from));
+                        if let Err(e) = readable {
+                            problems.push(ConfigProblem::UnreadableFile {
+                                context: name.clone(),
+                                user: ctx.user.clone(),
+                                path: path.clone(),
+                                error: format!("{}", e),
+                            });
+                        }
+                    }
+                }
+                None => problems.push(ConfigProblem::MissingUser {
+                    context: name.clone(),
+                    user: ctx.user.clone(),
+                }),
+            }
+        }
+        problems
+    }
+
+    /// The namespace set in the current context, if there is one
+    pub fn current_namespace(&self) -> Option<&str> {
+        self.current_context
+            .as_ref()
+            .and_then(|c| self.contexts.get(c))
+            .and_then(|ctx| ctx.namespace.as_ref())
+            .map(|ns| ns.as_str())
+    }
+
+    /// Set (or clear, if namespace is None) the namespace of the current context.  This only
+    /// changes the loaded config, not the file it came from.  Returns false if there is no valid
+    /// current context to update.
+    pub fn set_current_namespace(&mut self, namespace: Option<&str>) -> bool {
+        let contexts = &mut self.contexts;
+        match self.current_context
+            .as_ref()
+            .and_then(|c| contexts.get_mut(c))
+        {
+            Some(ctx) => {
+                ctx.namespace = namespace.map(|ns| ns.to_owned());
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn cluster_for_context(&self, context: &str) -> Result<Kluster, KubeError> {
         self.contexts
             .get(context)
@@ -340,11 +702,13 @@ impl Config {
                                     (&user.client_cert_data, &user.client_key_data)
                                 {
                                     auth_from_data(client_cert_data, key_data, context)
+                                } else if let Some(ref exec) = user.exec {
+                                    auth_from_exec(exec, cluster.server.as_str(), context)
                                 } else {
                                     Err(KubeError::ConfigFileError(format!(
                                         "Invalid context {}.  Each user must have either a token, \
-                                         a username AND password, or a client-certificate AND \
-                                         a client-key.", context)))
+                                         a username AND password, a client-certificate AND \
+                                         a client-key, or an exec credential plugin.", context)))
                                 }?;
                                 Kluster::new(
                                     context,
@@ -399,3 +763,284 @@ impl ClickConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempdir::TempDir;
+
+    /// Write yaml to a config file in a new temporary directory
+    fn write_config(dir: &TempDir, name: &str, yaml: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        File::create(&path)
+            .unwrap()
+            .write_all(yaml.as_bytes())
+            .unwrap();
+        path
+    }
+
+    fn load(yaml: &str) -> (TempDir, Config) {
+        let dir = TempDir::new("click-config").unwrap();
+        let path = write_config(&dir, "config", yaml);
+        let config = Config::merge(&[path]).unwrap();
+        (dir, config)
+    }
+
+    #[test]
+    fn validate_dangling_cluster() {
+        let (_dir, config) = load(
+            r#"
+current-context: dev
+clusters:
+- name: dev-cluster
+  cluster:
+    server: https://dev.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+contexts:
+- name: dev
+  context: {cluster: dev-cluster, user: dev-user}
+- name: old
+  context: {cluster: deleted-cluster, user: dev-user}
+users:
+- name: dev-user
+  user: {token: abc}
+"#,
+        );
+        let problems = config.validate();
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].context(), Some("old"));
+        assert_eq!(
+            problems[0].to_string(),
+            "Context \"old\" uses cluster \"deleted-cluster\", which is not defined or could \
+             not be loaded"
+        );
+    }
+
+    #[test]
+    fn validate_missing_cert_file() {
+        let (dir, config) = load(
+            r#"
+current-context: prod
+clusters:
+- name: prod-cluster
+  cluster:
+    server: https://prod.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+contexts:
+- name: prod
+  context: {cluster: prod-cluster, user: admin}
+users:
+- name: admin
+  user:
+    client-certificate: /nonexistent/click-test/admin.crt
+    client-key: /nonexistent/click-test/admin.key
+"#,
+        );
+        let problems = config.validate();
+        let paths: Vec<&str> = problems
+            .iter()
+            .map(|p| match *p {
+                ConfigProblem::UnreadableFile {
+                    ref context,
+                    ref path,
+                    ..
+                } => {
+                    assert_eq!(context, "prod");
+                    path.as_str()
+                }
+                ref other => panic!("unexpected problem: {}", other),
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["/nonexistent/click-test/admin.crt", "/nonexistent/click-test/admin.key"]
+        );
+
+        // the current context has to exist too
+        let ok = write_config(
+            &dir,
+            "ok",
+            r#"
+current-context: prod
+clusters: []
+contexts: []
+users: []
+"#,
+        );
+        let config = Config::merge(&[ok]).unwrap();
+        match config.validate().as_slice() {
+            [ConfigProblem::UnknownCurrentContext(ref ctx)] => assert_eq!(ctx, "prod"),
+            other => panic!("unexpected problems: {:?}", other),
+        }
+    }
+
+    fn exec_config(command: &str, args: &[&str]) -> ExecConfig {
+        ExecConfig {
+            command: command.to_owned(),
+            args: Some(args.iter().map(|a| (*a).to_owned()).collect()),
+            env: None,
+            api_version: None,
+        }
+    }
+
+    fn credential_json(token: &str, expires: DateTime<Utc>) -> String {
+        json!({
+            "kind": "ExecCredential",
+            "status": {"token": token, "expirationTimestamp": expires.to_rfc3339()},
+        }).to_string()
+    }
+
+    fn token(auth: KlusterAuth) -> String {
+        match auth {
+            KlusterAuth::Token(token) => token,
+            _ => panic!("not a token"),
+        }
+    }
+
+    #[test]
+    fn cached_exec_credential_is_reused() {
+        let dir = TempDir::new("click-exec").unwrap();
+        let cache = dir.path().join("cache.json");
+        File::create(&cache)
+            .unwrap()
+            .write_all(credential_json("cached", Utc::now() + Duration::hours(1)).as_bytes())
+            .unwrap();
+        // running this would fail, so getting a token means the cache was used
+        let exec = exec_config("/nonexistent/click-test-plugin", &[]);
+        let auth = auth_from_exec_cached(&exec, Some(cache.clone()), "ctx").unwrap();
+        assert_eq!(token(auth), "cached");
+        assert!(auth_from_exec_cached(&exec, None, "ctx").is_err());
+    }
+
+    #[test]
+    fn expired_exec_credential_is_refreshed() {
+        let dir = TempDir::new("click-exec").unwrap();
+        let cache = dir.path().join("nested").join("cache.json");
+        fs::create_dir_all(cache.parent().unwrap()).unwrap();
+        // about to expire counts as expired
+        File::create(&cache)
+            .unwrap()
+            .write_all(credential_json("stale", Utc::now() + Duration::seconds(30)).as_bytes())
+            .unwrap();
+        let output = credential_json("fresh", Utc::now() + Duration::hours(1));
+        let exec = exec_config("sh", &["-c", format!("echo '{}'", output).as_str()]);
+        let auth = auth_from_exec_cached(&exec, Some(cache.clone()), "ctx").unwrap();
+        assert_eq!(token(auth), "fresh");
+        assert_eq!(cached_exec_token(cache.as_path()), Some("fresh".to_owned()));
+    }
+
+    #[test]
+    fn merge_two_files() {
+        let dir = TempDir::new("click-merge").unwrap();
+        // no current-context here, so it comes from the second file
+        let first = write_config(
+            &dir,
+            "first",
+            r#"
+clusters:
+- name: shared
+  cluster:
+    server: https://first.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+- name: only-first
+  cluster:
+    server: https://one.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+contexts:
+- name: dev
+  context: {cluster: shared, user: dev-user, namespace: dev}
+users:
+- name: dev-user
+  user: {token: first-token}
+"#,
+        );
+        let second = write_config(
+            &dir,
+            "second",
+            r#"
+current-context: prod
+clusters:
+- name: shared
+  cluster:
+    server: https://second.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+- name: only-second
+  cluster:
+    server: https://two.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+contexts:
+- name: dev
+  context: {cluster: only-second, user: dev-user}
+- name: prod
+  context: {cluster: only-second, user: prod-user}
+users:
+- name: dev-user
+  user: {token: second-token}
+- name: prod-user
+  user: {token: prod-token}
+"#,
+        );
+        let missing = dir.path().join("missing");
+        let config = Config::merge(&[first.clone(), missing.clone(), second.clone()]).unwrap();
+
+        assert_eq!(config.current_context, Some("prod".to_owned()));
+        assert_eq!(
+            config.source_file,
+            format!("{}:{}", first.display(), second.display())
+        );
+        let mut clusters: Vec<&str> = config.clusters.keys().map(|k| k.as_str()).collect();
+        clusters.sort();
+        assert_eq!(clusters, vec!["only-first", "only-second", "shared"]);
+        // on conflicts the first file wins
+        assert_eq!(config.clusters["shared"].server, "https://first.example.com");
+        assert_eq!(config.contexts["dev"].cluster, "shared");
+        assert_eq!(config.contexts["dev"].namespace, Some("dev".to_owned()));
+        assert_eq!(config.contexts["prod"].user, "prod-user");
+        assert_eq!(config.users["dev-user"].token, Some("first-token".to_owned()));
+        assert_eq!(config.users["prod-user"].token, Some("prod-token".to_owned()));
+
+        assert!(Config::merge(&[missing]).is_err());
+    }
+
+    #[test]
+    fn current_namespace_with_and_without() {
+        let (_dir, mut config) = load(
+            r#"
+current-context: dev
+clusters:
+- name: dev-cluster
+  cluster:
+    server: https://dev.example.com
+    certificate-authority-data: ZmFrZSBjYQ==
+contexts:
+- name: dev
+  context: {cluster: dev-cluster, user: dev-user, namespace: team-a}
+- name: bare
+  context: {cluster: dev-cluster, user: dev-user}
+users:
+- name: dev-user
+  user: {token: abc}
+"#,
+        );
+        assert_eq!(config.current_namespace(), Some("team-a"));
+        assert!(config.set_current_namespace(Some("team-b")));
+        assert_eq!(config.current_namespace(), Some("team-b"));
+        assert_eq!(config.contexts["dev"].namespace, Some("team-b".to_owned()));
+
+        config.current_context = Some("bare".to_owned());
+        assert_eq!(config.current_namespace(), None);
+        assert!(config.set_current_namespace(Some("team-c")));
+        assert_eq!(config.current_namespace(), Some("team-c"));
+        assert!(config.set_current_namespace(None));
+        assert_eq!(config.current_namespace(), None);
+        // the other context is untouched
+        assert_eq!(config.contexts["dev"].namespace, Some("team-b".to_owned()));
+
+        config.current_context = Some("missing".to_owned());
+        assert_eq!(config.current_namespace(), None);
+        assert!(!config.set_current_namespace(Some("team-a")));
+        config.current_context = None;
+        assert!(!config.set_current_namespace(Some("team-a")));
+    }
+}
@@ -17,10 +17,13 @@
 
 use values::{val_str, val_str_opt, val_u64};
 
+use kube::{NodeAffinity, Pod, Toleration};
+
 use ansi_term::Colour;
 use chrono::DateTime;
 use chrono::offset::Local;
 use chrono::offset::Utc;
+use serde_json;
 use serde_json::Value;
 
 use std::borrow::Cow;
@@ -178,6 +181,22 @@ pub fn describe_format_pod(v: Value) -> String {
                 default: "<No Phase>",
             },
         ),
+        (
+            "Priority:\t",
+            DescItem::CustomFunc {
+                path: None,
+                func: &get_priority_str,
+                default: "<unknown>",
+            },
+        ),
+        (
+            "QoS Class:\t",
+            DescItem::CustomFunc {
+                path: None,
+                func: &get_qos_str,
+                default: "<unknown>",
+            },
+        ),
         (
             "Labels:\t",
             DescItem::KeyValStr {
@@ -200,10 +219,72 @@ pub fn describe_format_pod(v: Value) -> String {
                 default: "<No Volumes>",
             },
         ),
+        (
+            "Tolerations:",
+            DescItem::CustomFunc {
+                path: Some("/spec/tolerations"),
+                func: &get_tolerations_str,
+                default: "\t<none>",
+            },
+        ),
+        (
+            "Node Affinity:",
+            DescItem::CustomFunc {
+                path: Some("/spec/affinity/nodeAffinity"),
+                func: &get_node_affinity_str,
+                default: "\t<none>",
+            },
+        ),
     ];
     describe_object(&v, fields.into_iter())
 }
 
+/// The priority class and its resolved value
+fn get_priority_str<'a>(v: &'a Value) -> Cow<'a, str> {
+    match serde_json::from_value::<Pod>(v.clone()) {
+        Ok(pod) => pod.priority_summary().into(),
+        Err(_) => "<unknown>".into(),
+    }
+}
+
+/// The QoS class, which along with priority decides which pods are evicted first
+fn get_qos_str<'a>(v: &'a Value) -> Cow<'a, str> {
+    match serde_json::from_value::<Pod>(v.clone()) {
+        Ok(pod) => pod.qos_class().to_string().into(),
+        Err(_) => "<unknown>".into(),
+    }
+}
+
+/// One toleration per line
+fn get_tolerations_str<'a>(v: &'a Value) -> Cow<'a, str> {
+    match serde_json::from_value::<Vec<Toleration>>(v.clone()) {
+        Ok(ref tolerations) if !tolerations.is_empty() => {
+            let lines: Vec<String> = tolerations.iter().map(|t| format!("\t{}", t)).collect();
+            lines.join("\n").into()
+        }
+        Ok(_) => "\t<none>".into(),
+        Err(_) => "\t<invalid tolerations>".into(),
+    }
+}
+
+fn get_node_affinity_str<'a>(v: &'a Value) -> Cow<'a, str> {
+    match serde_json::from_value::<NodeAffinity>(v.clone()) {
+        Ok(affinity) => {
+            let lines: Vec<String> = affinity
+                .summary()
+                .into_iter()
+                .map(|l| format!("\t{}", l))
+                .collect();
+            if lines.is_empty() {
+                "\t<none>".into()
+            } else {
+                lines.join("\n").into()
+            }
+        }
+        Err(_) => "\t<invalid node affinity>".into(),
+    }
+}
+
 /// Get volume info out of volume array
 fn get_volume_str<'a>(v: &'a Value) -> Cow<'a, str> {
     let mut buf = String::new();
@@ -273,7 +354,14 @@ fn pod_phase<'a>(v: &Value) -> Cow<str> {
         "Failed" => Colour::Red,
         _ => Colour::Yellow,
     };
-    colour.paint(phase_str).to_string().into()
+    let mut phase = colour.paint(phase_str).to_string();
+    match (val_str_opt("/status/reason", v), val_str_opt("/status/message", v)) {
+        (Some(reason), Some(message)) => phase.push_str(&format!(" ({}: {})", reason, message)),
+        (Some(reason), None) => phase.push_str(&format!(" ({})", reason)),
+        (None, Some(message)) => phase.push_str(&format!(" ({})", message)),
+        (None, None) => {}
+    }
+    phase.into()
 }
 
 /// Utility function for describe to print out value
@@ -567,3 +655,37 @@ pub fn describe_format_secret(v: Value) -> String {
     ];
     describe_object(&v, fields.into_iter())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_tab_per_toleration_and_affinity_line() {
+        let tolerations = json!([
+            {"key": "dedicated", "value": "etcd", "effect": "NoSchedule"},
+            {"key": "node.kubernetes.io/not-ready", "operator": "Exists", "effect": "NoExecute"},
+        ]);
+        let lines: Vec<String> = get_tolerations_str(&tolerations)
+            .lines()
+            .map(|l| l.to_owned())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.starts_with('\t') && !l.starts_with("\t\t")));
+
+        let affinity = json!({
+            "requiredDuringSchedulingIgnoredDuringExecution": {"nodeSelectorTerms": [
+                {"matchExpressions": [{"key": "zone", "operator": "In", "values": ["a"]}]},
+            ]},
+            "preferredDuringSchedulingIgnoredDuringExecution": [
+                {"weight": 10, "preference": {"matchExpressions": [
+                    {"key": "cpu-count", "operator": "Gt", "values": ["8"]},
+                ]}},
+            ],
+        });
+        let affinity = get_node_affinity_str(&affinity);
+        assert!(affinity.lines().count() >= 2);
+        assert!(affinity.lines().all(|l| l.starts_with('\t') && !l.starts_with("\t\t")));
+        assert_eq!(get_tolerations_str(&json!([])), "\t<none>");
+    }
+}
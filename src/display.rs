@@ -0,0 +1,114 @@
+// Copyright 2017 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Formatting for the common columns of resource listings, matching what kubectl prints so
+//! output can be compared side by side
+
+use chrono::{DateTime, Duration};
+use chrono::offset::Utc;
+
+/// Format a duration the way kubectl does for ages: more precision for recent things, less the
+/// older they get (like "90s", "5m12s", "3h", "5d2h", "400d", "2y10d").  Small negative
+/// durations (clock skew) are "0s".
+pub fn human_duration(d: Duration) -> String {
+    let seconds = d.num_seconds();
+    if seconds < -1 {
+        return "<invalid>".to_owned();
+    } else if seconds < 0 {
+        return "0s".to_owned();
+    } else if seconds < 60 * 2 {
+        return format!("{}s", seconds);
+    }
+    let minutes = d.num_minutes();
+    if minutes < 10 {
+        return match seconds % 60 {
+            0 => format!("{}m", minutes),
+            s => format!("{}m{}s", minutes, s),
+        };
+    } else if minutes < 60 * 3 {
+        return format!("{}m", minutes);
+    }
+    let hours = d.num_hours();
+    if hours < 8 {
+        match minutes % 60 {
+            0 => format!("{}h", hours),
+            m => format!("{}h{}m", hours, m),
+        }
+    } else if hours < 48 {
+        format!("{}h", hours)
+    } else if hours < 24 * 8 {
+        match hours % 24 {
+            0 => format!("{}d", hours / 24),
+            h => format!("{}d{}h", hours / 24, h),
+        }
+    } else if hours < 24 * 365 * 2 {
+        format!("{}d", hours / 24)
+    } else if hours < 24 * 365 * 8 {
+        match (hours / 24) % 365 {
+            0 => format!("{}y", hours / 24 / 365),
+            days => format!("{}y{}d", hours / 24 / 365, days),
+        }
+    } else {
+        format!("{}y", hours / 24 / 365)
+    }
+}
+
+/// The AGE column: how long ago ts was
+pub fn format_age(ts: &DateTime<Utc>) -> String {
+    human_duration(Utc::now().signed_duration_since(*ts))
+}
+
+/// The READY column: how many of a pod's containers (or a workload's replicas) are ready
+pub fn format_ready(ready: u32, total: u32) -> String {
+    format!("{}/{}", ready, total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_thresholds() {
+        let age = |secs: i64| human_duration(Duration::seconds(secs));
+        assert_eq!(age(-5), "<invalid>");
+        assert_eq!(age(-1), "0s");
+        assert_eq!(age(0), "0s");
+        assert_eq!(age(90), "90s");
+        assert_eq!(age(119), "119s");
+        assert_eq!(age(120), "2m");
+        assert_eq!(age(5 * 60 + 12), "5m12s");
+        assert_eq!(age(10 * 60 + 12), "10m");
+        assert_eq!(age(179 * 60), "179m");
+        assert_eq!(age(3 * 3600), "3h");
+        assert_eq!(age(3 * 3600 + 20 * 60), "3h20m");
+        assert_eq!(age(8 * 3600 + 20 * 60), "8h");
+        assert_eq!(age(47 * 3600), "47h");
+        assert_eq!(age(5 * 86400 + 2 * 3600), "5d2h");
+        assert_eq!(age(3 * 86400), "3d");
+        assert_eq!(age(400 * 86400), "400d");
+        assert_eq!(age((2 * 365 + 10) * 86400), "2y10d");
+        assert_eq!(age(3 * 365 * 86400), "3y");
+        assert_eq!(age(9 * 365 * 86400 + 86400), "9y");
+
+        let three_hours_ago = Utc::now() - Duration::hours(3);
+        assert_eq!(format_age(&three_hours_ago), "3h");
+    }
+
+    #[test]
+    fn ready_ratios() {
+        assert_eq!(format_ready(2, 3), "2/3");
+        assert_eq!(format_ready(0, 0), "0/0");
+        assert_eq!(format_ready(1, 1), "1/1");
+    }
+}
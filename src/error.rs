@@ -25,7 +25,16 @@ pub enum KubeErrNo {
     InvalidContextName,
     InvalidCluster,
     InvalidUser,
-    Unauthorized,
+    /// The server's explanation (e.g. that the token has expired), if it gave one
+    Unauthorized(Option<String>),
+    EmptyResponse,
+    Timeout,
+    /// The server is overloaded or unavailable, with the status code and its explanation (if it
+    /// gave one)
+    Unavailable(u16, Option<String>),
+    ResponseTooLarge,
+    /// A watch sent an event older than what we'd already seen, described here
+    WatchInconsistency(String),
     Unknown,
 }
 
@@ -35,10 +44,31 @@ impl fmt::Display for KubeErrNo {
             &KubeErrNo::InvalidContextName => write!(f, "Invalid Context Name"),
             &KubeErrNo::InvalidCluster => write!(f, "Invalid Cluster Name"),
             &KubeErrNo::InvalidUser => write!(f, "Invalid User Name"),
-            &KubeErrNo::Unauthorized => write!(
-                f,
-                "Not authorized to talk to cluster, check credentials in config"
-            ),
+            &KubeErrNo::Unauthorized(ref msg) => {
+                write!(
+                    f,
+                    "Not authorized to talk to cluster, check credentials in config"
+                )?;
+                match msg {
+                    &Some(ref msg) => write!(f, " (server said: {})", msg),
+                    &None => Ok(()),
+                }
+            }
+            &KubeErrNo::EmptyResponse => write!(f, "Server returned an empty response"),
+            &KubeErrNo::Timeout => write!(f, "Timed out waiting for the cluster"),
+            &KubeErrNo::Unavailable(code, ref msg) => {
+                write!(f, "Cluster is unavailable (status {})", code)?;
+                match msg {
+                    &Some(ref msg) => write!(f, " (server said: {})", msg),
+                    &None => Ok(()),
+                }
+            }
+            &KubeErrNo::ResponseTooLarge => {
+                write!(f, "Server response was larger than the maximum allowed")
+            }
+            &KubeErrNo::WatchInconsistency(ref msg) => {
+                write!(f, "Watch sent an out of order event: {}", msg)
+            }
             &KubeErrNo::Unknown => write!(f, "Unknown error talking to cluster"),
         }
     }
@@ -50,9 +80,14 @@ impl error::Error for KubeErrNo {
             &KubeErrNo::InvalidContextName => "Invalid Context Name",
             &KubeErrNo::InvalidCluster => "Invalid Cluster Name",
             &KubeErrNo::InvalidUser => "Invalid User Name",
-            &KubeErrNo::Unauthorized => {
+            &KubeErrNo::Unauthorized(_) => {
                 "Not authorized to talk to cluster, check credentials in config"
             }
+            &KubeErrNo::EmptyResponse => "Server returned an empty response",
+            &KubeErrNo::Timeout => "Timed out waiting for the cluster",
+            &KubeErrNo::Unavailable(..) => "Cluster is unavailable",
+            &KubeErrNo::ResponseTooLarge => "Server response was larger than the maximum allowed",
+            &KubeErrNo::WatchInconsistency(_) => "Watch sent an out of order event",
             &KubeErrNo::Unknown => "Unknown error talking to cluster",
         }
     }
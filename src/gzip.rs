@@ -0,0 +1,527 @@
+// Copyright 2017 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A small gzip decoder, enough to read compressed api server responses.  The inflate part
+//! follows the structure of zlib's puff.c: simple and obviously correct rather than fast, which is
+//! fine since the network is the bottleneck here.
+
+use std::cmp;
+
+use error::{KubeErrNo, KubeError};
+
+const MAX_BITS: usize = 15;
+
+// gzip header flags
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+// base lengths and extra bits for length codes 257..285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+// base offsets and extra bits for distance codes 0..29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// order in which code length code lengths are sent in a dynamic block
+const CLEN_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn corrupt() -> KubeError {
+    KubeError::ParseErr("Invalid gzip data".to_owned())
+}
+
+fn too_large() -> KubeError {
+    KubeError::Kube(KubeErrNo::ResponseTooLarge)
+}
+
+/// Reads bits least significant first, as deflate packs them
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bitbuf: u32,
+    bitcnt: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data: data,
+            pos: 0,
+            bitbuf: 0,
+            bitcnt: 0,
+        }
+    }
+
+    fn bits(&mut self, need: u32) -> Result<u32, KubeError> {
+        let mut val = self.bitbuf;
+        while self.bitcnt < need {
+            let byte = *self.data.get(self.pos).ok_or_else(corrupt)?;
+            self.pos += 1;
+            val |= u32::from(byte) << self.bitcnt;
+            self.bitcnt += 8;
+        }
+        self.bitbuf = if need == 32 { 0 } else { val >> need };
+        self.bitcnt -= need;
+        Ok(if need == 32 { val } else { val & ((1 << need) - 1) })
+    }
+
+    /// Discard any bits left in the current byte
+    fn align(&mut self) {
+        self.bitbuf = 0;
+        self.bitcnt = 0;
+    }
+}
+
+/// A canonical huffman code: the number of symbols of each length, and the symbols ordered by
+/// code
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman, KubeError> {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for len in lengths.iter() {
+            counts[*len as usize] += 1;
+        }
+        // check the code isn't over-subscribed (incomplete codes are allowed)
+        let mut left: i32 = 1;
+        for len in 1..(MAX_BITS + 1) {
+            left <<= 1;
+            left -= i32::from(counts[len]);
+            if left < 0 {
+                return Err(corrupt());
+            }
+        }
+        let mut offsets = [0u16; MAX_BITS + 1];
+        for len in 1..MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len != 0 {
+                symbols[offsets[*len as usize] as usize] = symbol as u16;
+                offsets[*len as usize] += 1;
+            }
+        }
+        Ok(Huffman {
+            counts: counts,
+            symbols: symbols,
+        })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, KubeError> {
+        let mut code: i32 = 0; // bits read so far
+        let mut first: i32 = 0; // first code of the current length
+        let mut index: i32 = 0; // index of the first code of the current length in symbols
+        for len in 1..(MAX_BITS + 1) {
+            code |= reader.bits(1)? as i32;
+            let count = i32::from(self.counts[len]);
+            if code - count < first {
+                return Ok(self.symbols[(index + (code - first)) as usize]);
+            }
+            index += count;
+            first += count;
+            first <<= 1;
+            code <<= 1;
+        }
+        Err(corrupt())
+    }
+}
+
+fn stored(reader: &mut BitReader, out: &mut Vec<u8>, max_len: usize) -> Result<(), KubeError> {
+    reader.align();
+    let pos = reader.pos;
+    if pos + 4 > reader.data.len() {
+        return Err(corrupt());
+    }
+    let len = u16::from(reader.data[pos]) | (u16::from(reader.data[pos + 1]) << 8);
+    let nlen = u16::from(reader.data[pos + 2]) | (u16::from(reader.data[pos + 3]) << 8);
+    if len != !nlen {
+        return Err(corrupt());
+    }
+    let start = pos + 4;
+    let end = start + len as usize;
+    if end > reader.data.len() {
+        return Err(corrupt());
+    }
+    if out.len() + len as usize > max_len {
+        return Err(too_large());
+    }
+    out.extend_from_slice(&reader.data[start..end]);
+    reader.pos = end;
+    Ok(())
+}
+
+fn codes(
+    reader: &mut BitReader,
+    out: &mut Vec<u8>,
+    max_len: usize,
+    lencode: &Huffman,
+    distcode: &Huffman,
+) -> Result<(), KubeError> {
+    loop {
+        if out.len() > max_len {
+            return Err(too_large());
+        }
+        let symbol = lencode.decode(reader)? as usize;
+        if symbol < 256 {
+            out.push(symbol as u8);
+        } else if symbol == 256 {
+            return Ok(());
+        } else {
+            let symbol = symbol - 257;
+            if symbol >= LENGTH_BASE.len() {
+                return Err(corrupt());
+            }
+            let len = LENGTH_BASE[symbol] as usize
+                + reader.bits(u32::from(LENGTH_EXTRA[symbol]))? as usize;
+            let symbol = distcode.decode(reader)? as usize;
+            if symbol >= DIST_BASE.len() {
+                return Err(corrupt());
+            }
+            let dist =
+                DIST_BASE[symbol] as usize + reader.bits(u32::from(DIST_EXTRA[symbol]))? as usize;
+            if dist > out.len() {
+                return Err(corrupt());
+            }
+            // copy byte by byte since the source and destination can overlap
+            let start = out.len() - dist;
+            for i in 0..len {
+                let byte = out[start + i];
+                out.push(byte);
+            }
+        }
+    }
+}
+
+fn fixed(reader: &mut BitReader, out: &mut Vec<u8>, max_len: usize) -> Result<(), KubeError> {
+    let mut lengths = [0u8; 288];
+    for (symbol, len) in lengths.iter_mut().enumerate() {
+        *len = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    let lencode = Huffman::new(&lengths)?;
+    let distcode = Huffman::new(&[5u8; 30])?;
+    codes(reader, out, max_len, &lencode, &distcode)
+}
+
+fn dynamic(reader: &mut BitReader, out: &mut Vec<u8>, max_len: usize) -> Result<(), KubeError> {
+    let nlen = reader.bits(5)? as usize + 257;
+    let ndist = reader.bits(5)? as usize + 1;
+    let ncode = reader.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err(corrupt());
+    }
+
+    let mut clengths = [0u8; 19];
+    for i in 0..ncode {
+        clengths[CLEN_ORDER[i]] = reader.bits(3)? as u8;
+    }
+    let clencode = Huffman::new(&clengths)?;
+
+    let mut lengths = vec![0u8; nlen + ndist];
+    let mut index = 0;
+    while index < nlen + ndist {
+        let symbol = clencode.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                if index == 0 {
+                    return Err(corrupt());
+                }
+                (lengths[index - 1], 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if index + repeat > nlen + ndist {
+            return Err(corrupt());
+        }
+        for _ in 0..repeat {
+            lengths[index] = value;
+            index += 1;
+        }
+    }
+    if lengths[256] == 0 {
+        // no end of block code
+        return Err(corrupt());
+    }
+
+    let lencode = Huffman::new(&lengths[..nlen])?;
+    let distcode = Huffman::new(&lengths[nlen..])?;
+    codes(reader, out, max_len, &lencode, &distcode)
+}
+
+/// Decompress raw deflate data, returning the decompressed bytes and how many input bytes were
+/// consumed.  Fails if the output would be longer than max_len.
+fn inflate(data: &[u8], max_len: usize) -> Result<(Vec<u8>, usize), KubeError> {
+    let mut reader = BitReader::new(data);
+    let mut out = Vec::with_capacity(cmp::min(data.len() * 4, max_len));
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored(&mut reader, &mut out, max_len)?,
+            1 => fixed(&mut reader, &mut out, max_len)?,
+            2 => dynamic(&mut reader, &mut out, max_len)?,
+            _ => return Err(corrupt()),
+        }
+        if last {
+            if out.len() > max_len {
+                return Err(too_large());
+            }
+            return Ok((out, reader.pos));
+        }
+    }
+}
+
+/// Skip past a zero terminated string in a gzip header
+fn skip_cstr(data: &[u8], pos: usize) -> Result<usize, KubeError> {
+    data.get(pos..)
+        .and_then(|rest| rest.iter().position(|b| *b == 0))
+        .map(|end| pos + end + 1)
+        .ok_or_else(corrupt)
+}
+
+/// The CRC-32 (as used by gzip) of data
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    !data.iter().fold(!0u32, |crc, b| {
+        table[((crc ^ u32::from(*b)) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+/// Read a little endian u32 from the four bytes at pos
+fn read_u32(data: &[u8], pos: usize) -> u32 {
+    data[pos..pos + 4]
+        .iter()
+        .rev()
+        .fold(0u32, |acc, b| (acc << 8) | u32::from(*b))
+}
+
+/// Decode gzip data (RFC 1952), checking the decompressed data against the CRC-32 and length in
+/// the trailer.  Fails with ResponseTooLarge if the decompressed data would be longer than
+/// max_len, so a small compressed response can't expand to fill memory.
+pub fn decode(data: &[u8], max_len: usize) -> Result<Vec<u8>, KubeError> {
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b || data[2] != 8 {
+        return Err(corrupt());
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        if pos + 2 > data.len() {
+            return Err(corrupt());
+        }
+        pos += 2 + (data[pos] as usize | (data[pos + 1] as usize) << 8);
+        if pos > data.len() {
+            return Err(corrupt());
+        }
+    }
+    if flags & FNAME != 0 {
+        pos = skip_cstr(data, pos)?;
+    }
+    if flags & FCOMMENT != 0 {
+        pos = skip_cstr(data, pos)?;
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+    if pos > data.len() {
+        return Err(corrupt());
+    }
+
+    let (out, used) = inflate(&data[pos..], max_len)?;
+    let trailer = pos + used;
+    if trailer + 8 > data.len() {
+        return Err(corrupt());
+    }
+    if read_u32(data, trailer) != crc32(out.as_slice())
+        || read_u32(data, trailer + 4) != out.len() as u32
+    {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    /// Wrap raw deflate data in a minimal gzip header and a trailer for original
+    fn wrap(deflated: &[u8], original: &[u8]) -> Vec<u8> {
+        let mut data = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+        data.extend_from_slice(deflated);
+        for word in [crc32(original), original.len() as u32].iter() {
+            for shift in [0, 8, 16, 24].iter() {
+                data.push((word >> shift) as u8);
+            }
+        }
+        data
+    }
+
+    fn pod_list_text() -> Vec<u8> {
+        (0..40)
+            .map(|i| format!("{{\"name\":\"pod-{}\",\"phase\":\"Running\"}},", i))
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    /// pod_list_text compressed with a single dynamic block (by zlib at level 9)
+    fn dynamic_block() -> Vec<u8> {
+        unhex(concat!(
+            "8dd4310ac2601044e1bb6c1dc19d51133d863708286a913f01b10ade5dec2c847de5c2ab3e865da3",
+            "8dd3354eb1cc97cd36ba58eee3f37b9f5fad3dda2ddeddfa9b649da84e5c27bb3ad9d7c9a14efa3a",
+            "19eae408e8082ff04d009c4038017102e304c8099413302770167016d93170167016701670167016",
+            "701670167036703670367918c0d9c0d9c0d9c0d9c0d9c0d9ff9d3f",
+        ))
+    }
+
+    fn is_corrupt<T>(res: Result<T, KubeError>) -> bool {
+        match res {
+            Err(KubeError::ParseErr(_)) => true,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn stored_block() {
+        let text = b"hello, stored block";
+        let deflated = unhex("011300ecff68656c6c6f2c2073746f72656420626c6f636b");
+        assert_eq!(decode(wrap(&deflated, text).as_slice(), 1024).unwrap(), text.to_vec());
+    }
+
+    #[test]
+    fn fixed_block() {
+        let text = br#"{"kind":"PodList","items":[]}"#;
+        let deflated = unhex("ab56cacecc4b51b2520ac84ff1c92c2e51d251ca2c49cd2d56b28a8ead0500");
+        assert_eq!(decode(wrap(&deflated, text).as_slice(), 1024).unwrap(), text.to_vec());
+    }
+
+    #[test]
+    fn dynamic_block_round_trip() {
+        let text = pod_list_text();
+        let data = wrap(&dynamic_block(), text.as_slice());
+        assert_eq!(decode(data.as_slice(), 1 << 20).unwrap(), text);
+    }
+
+    #[test]
+    fn gzip_file_with_name() {
+        // from python's gzip module, with FNAME set to pods.json
+        let data = unhex(concat!(
+            "1f8b08080000000002ff706f64732e6a736f6e00ab56ca2c49cd2d56b28a8eade5020055293b510d",
+            "000000",
+        ));
+        assert_eq!(decode(data.as_slice(), 1024).unwrap(), b"{\"items\":[]}\n".to_vec());
+    }
+
+    #[test]
+    fn truncated_input() {
+        let text = pod_list_text();
+        let data = wrap(&dynamic_block(), text.as_slice());
+        for len in 0..data.len() {
+            assert!(is_corrupt(decode(&data[..len], 1 << 20)), "truncated to {}", len);
+        }
+    }
+
+    #[test]
+    fn corruption_that_keeps_the_length() {
+        let text = b"hello, stored block";
+        let deflated = unhex("011300ecff68656c6c6f2c2073746f72656420626c6f636b");
+        let mut data = wrap(&deflated, text);
+        data[15] ^= 0x20; // change the case of a letter in the stored data
+        assert!(is_corrupt(decode(data.as_slice(), 1024)));
+    }
+
+    #[test]
+    fn max_len() {
+        let text = pod_list_text();
+        let data = wrap(&dynamic_block(), text.as_slice());
+        assert_eq!(decode(data.as_slice(), text.len()).unwrap(), text);
+        match decode(data.as_slice(), text.len() - 1) {
+            Err(KubeError::Kube(KubeErrNo::ResponseTooLarge)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let stored = unhex("011300ecff68656c6c6f2c2073746f72656420626c6f636b");
+        match decode(wrap(&stored, b"hello, stored block").as_slice(), 10) {
+            Err(KubeError::Kube(KubeErrNo::ResponseTooLarge)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn malformed_headers() {
+        let deflated = unhex("ab56cacecc4b51b2520ac84ff1c92c2e51d251ca2c49cd2d56b28a8ead0500");
+        let good = wrap(&deflated, br#"{"kind":"PodList","items":[]}"#);
+        // an XLEN that runs past the end, with FNAME and FCOMMENT to read after it
+        let mut data = good.clone();
+        data[3] = FEXTRA | FNAME | FCOMMENT;
+        data[10] = 0xff;
+        data[11] = 0xff;
+        assert!(is_corrupt(decode(data.as_slice(), 1024)));
+        // a name that never ends
+        let mut data = good[..10].to_vec();
+        data[3] = FNAME;
+        data.extend_from_slice(b"unterminated name");
+        assert!(is_corrupt(decode(data.as_slice(), 1024)));
+        // a header crc past the end
+        let mut data = good[..10].to_vec();
+        data[3] = FHCRC | FNAME;
+        data.extend_from_slice(b"aaaaaaa\0");
+        assert!(is_corrupt(decode(data.as_slice(), 1024)));
+        // not gzip at all
+        assert!(is_corrupt(decode(b"{\"items\": [], \"kind\": \"List\"}", 1024)));
+    }
+}
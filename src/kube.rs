@@ -15,31 +15,47 @@
 //! Dealing with various kubernetes api calls
 
 use ansi_term::Colour::{Green, Red, Yellow};
-use chrono::DateTime;
+use chrono::{DateTime, NaiveDateTime};
 use chrono::offset::Utc;
+use duct;
 use hyper::{Client, Url};
 use hyper::client::{Body, RequestBuilder};
 use hyper::client::request::Request;
 use hyper::client::response::Response;
-use hyper::header::{Authorization, Basic, Bearer};
+use hyper::header::{qitem, Accept, AcceptEncoding, Authorization, Basic, Bearer, ContentEncoding,
+                    ContentType, Encoding};
 use hyper::method::Method;
 use hyper::net::HttpsConnector;
 use hyper::status::StatusCode;
 use hyper_rustls::TlsClient;
+use regex::Regex;
 use serde::Deserialize;
 use serde_json;
 use serde_json::{Map, Value};
-use rustls::{Certificate, PrivateKey};
+use serde_yaml;
+use ring::rand::{SecureRandom, SystemRandom};
+use rustls::{Certificate, ClientSessionMemoryCache, PrivateKey, StoresClientSessions};
+use tempdir::TempDir;
 
+use std::cmp;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt;
-use std::io::BufReader;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use connector::ClickSslConnector;
 use error::{KubeErrNo, KubeError};
+use gzip;
+use quantity::Quantity;
+use values::FieldDiff;
 
 // Various things we can return from the kubernetes api
 
@@ -47,7 +63,12 @@ use error::{KubeErrNo, KubeError};
 
 #[derive(Debug, Deserialize)]
 pub struct OwnerReference {
+    #[serde(default)]
     pub controller: bool,
+    /// If set, a foreground delete of the owner waits for this object to be deleted first
+    #[allow(dead_code)]
+    #[serde(rename = "blockOwnerDeletion", default)]
+    pub block_owner_deletion: bool,
     pub kind: String,
     pub name: String,
     pub uid: String,
@@ -57,6 +78,7 @@ pub struct OwnerReference {
 pub struct Metadata {
     pub name: String,
     pub namespace: Option<String>,
+    pub uid: Option<String>,
     #[serde(rename = "creationTimestamp")]
     pub creation_timestamp: Option<DateTime<Utc>>,
     #[serde(rename = "deletionTimestamp")]
@@ -65,6 +87,199 @@ pub struct Metadata {
     pub annotations: Option<Map<String, Value>>,
     #[serde(rename = "ownerReferences")]
     pub owner_refs: Option<Vec<OwnerReference>>,
+    /// Things that have to happen before the object can be removed once deleted
+    pub finalizers: Option<Vec<String>>,
+    pub generation: Option<i64>,
+    #[allow(dead_code)]
+    #[serde(rename = "resourceVersion")]
+    pub resource_version: Option<String>,
+}
+
+/// Any kind of object, with only its metadata parsed
+#[derive(Debug, Deserialize)]
+pub struct PartialObject {
+    pub metadata: Metadata,
+}
+
+/// Why an object that's been deleted is still around (stuck "Terminating")
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DeletionBlockers {
+    pub deletion_timestamp: DateTime<Utc>,
+    /// The finalizers still to run.  The object goes away once this is empty.
+    pub finalizers: Vec<String>,
+    /// Owners of the object.  If one of these is being deleted in the foreground the delete may
+    /// be cascading from it.
+    pub owners: Vec<OwnerReference>,
+}
+
+/// The kind and apiVersion every response carries, flattened into the types that want them
+#[allow(dead_code)]
+#[derive(Debug, Default, Deserialize)]
+pub struct TypeMeta {
+    pub kind: Option<String>,
+    #[serde(rename = "apiVersion")]
+    pub api_version: Option<String>,
+}
+
+impl TypeMeta {
+    /// Check the response was the kind we asked for (like "PodList"), rather than something
+    /// that happened to deserialize as it.  A missing kind is accepted, since not all sources
+    /// (like lists built by hand) set it.
+    pub fn expect_kind(&self, expected: &str) -> Result<(), KubeError> {
+        match self.kind {
+            Some(ref kind) if kind != expected => Err(KubeError::ParseErr(format!(
+                "Expected a {} but got a {}",
+                expected, kind
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A list type, and the kind the server gives it
+pub trait KubeList {
+    /// Like "PodList"
+    const KIND: &'static str;
+
+    fn type_meta(&self) -> &TypeMeta;
+}
+
+macro_rules! kube_list {
+    ($list:ident) => {
+        impl KubeList for $list {
+            const KIND: &'static str = stringify!($list);
+
+            fn type_meta(&self) -> &TypeMeta {
+                &self.type_meta
+            }
+        }
+    };
+}
+
+kube_list!(PodList);
+kube_list!(EventList);
+kube_list!(NodeList);
+kube_list!(DeploymentList);
+kube_list!(ServiceList);
+kube_list!(NamespaceList);
+kube_list!(ReplicaSetList);
+kube_list!(ConfigMapList);
+kube_list!(SecretList);
+
+/// Metadata for lists of objects
+#[derive(Debug, Deserialize)]
+pub struct ListMetadata {
+    #[serde(rename = "resourceVersion")]
+    pub resource_version: Option<String>,
+    /// If set, there are more items to fetch by passing this back as the continue param
+    #[serde(rename = "continue")]
+    pub cont: Option<String>,
+}
+
+/// A condition as found in the status of many kinds of objects
+#[derive(Debug, Deserialize)]
+pub struct Condition {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub status: String,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    #[serde(rename = "lastTransitionTime")]
+    pub last_transition_time: Option<DateTime<Utc>>,
+}
+
+/// Conditions that say an object is working, in the order they're checked: nodes and pods are
+/// Ready, deployments Available, jobs Complete, and custom resource definitions Established
+const HEALTHY_CONDITIONS: &[&str] = &["Ready", "Available", "Complete", "Established"];
+
+/// Phases (for objects without conditions, like namespaces and volumes) that mean all is well
+const HEALTHY_PHASES: &[&str] = &["Running", "Succeeded", "Active", "Bound", "Available"];
+
+/// Phases that mean something is wrong, or not done yet
+const UNHEALTHY_PHASES: &[&str] = &["Pending", "Failed", "Unknown", "Lost", "Released"];
+
+/// A one line verdict on the health of any kind of object, from its conditions or phase
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct StatusSummary {
+    /// Whether the object is ready or available, None if that can't be told from its status
+    pub healthy: Option<bool>,
+    /// The condition that changed most recently
+    pub latest: Option<Condition>,
+    /// The object's phase, if it has one
+    pub phase: Option<String>,
+}
+
+impl StatusSummary {
+    /// Summarize the status of obj.  Health comes from the first of HEALTHY_CONDITIONS it has,
+    /// or a Failed condition, or failing those its phase.
+    pub fn from_value(obj: &Value) -> StatusSummary {
+        let conditions: Vec<Condition> = obj.pointer("/status/conditions")
+            .and_then(|c| serde_json::from_value(c.clone()).ok())
+            .unwrap_or_default();
+        let phase = ::values::val_str_opt("/status/phase", obj);
+        let failed = conditions
+            .iter()
+            .any(|c| c.typ == "Failed" && c.status == "True");
+        let from_conditions = HEALTHY_CONDITIONS
+            .iter()
+            .filter_map(|typ| conditions.iter().find(|c| c.typ == *typ))
+            .next()
+            .map(|c| c.status == "True");
+        let healthy = if failed {
+            Some(false)
+        } else if from_conditions.is_some() {
+            from_conditions
+        } else {
+            phase.as_ref().and_then(|p| {
+                if HEALTHY_PHASES.contains(&p.as_str()) {
+                    Some(true)
+                } else if UNHEALTHY_PHASES.contains(&p.as_str()) {
+                    Some(false)
+                } else {
+                    None
+                }
+            })
+        };
+        let latest = conditions
+            .into_iter()
+            .max_by_key(|c| c.last_transition_time);
+        StatusSummary {
+            healthy,
+            latest,
+            phase,
+        }
+    }
+}
+
+impl fmt::Display for StatusSummary {
+    /// Like "Healthy, Available=True since 2024-01-01 10:00:00 UTC (MinimumReplicasAvailable)"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.healthy {
+            Some(true) => write!(f, "{}", Green.paint("Healthy"))?,
+            Some(false) => write!(f, "{}", Red.paint("Unhealthy"))?,
+            None => write!(f, "{}", Yellow.paint("Unknown"))?,
+        }
+        if let Some(ref phase) = self.phase {
+            write!(f, ", phase {}", phase)?;
+        }
+        if let Some(ref c) = self.latest {
+            write!(f, ", {}={}", c.typ, c.status)?;
+            if let Some(time) = c.last_transition_time {
+                write!(f, " since {}", time)?;
+            }
+            match (&c.reason, &c.message) {
+                (&Some(ref reason), &Some(ref message)) => {
+                    write!(f, " ({}: {})", reason, message)?
+                }
+                (&Some(ref reason), &None) => write!(f, " ({})", reason)?,
+                (&None, &Some(ref message)) => write!(f, " ({})", message)?,
+                (&None, &None) => {}
+            }
+        }
+        Ok(())
+    }
 }
 
 // pods
@@ -140,6 +355,28 @@ impl fmt::Display for ContainerState {
     }
 }
 
+/// How a container's previous run ended
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct TerminatedState {
+    #[serde(rename = "exitCode")]
+    pub exit_code: i32,
+    pub reason: Option<String>,
+    pub message: Option<String>,
+    pub signal: Option<i32>,
+    #[serde(rename = "startedAt")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(rename = "finishedAt")]
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// The state of the previous run of a container.  This is an empty object if the container has
+/// never restarted, so unlike ContainerState it can't be an enum.
+#[derive(Debug, Deserialize)]
+pub struct LastState {
+    pub terminated: Option<TerminatedState>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ContainerStatus {
     #[serde(rename = "containerID")]
@@ -150,13 +387,109 @@ pub struct ContainerStatus {
     pub restart_count: u32,
     pub ready: bool,
     pub state: ContainerState,
+    #[serde(rename = "lastState")]
+    pub last_state: Option<LastState>,
+}
+
+fn is_oom_reason(reason: &Option<String>) -> bool {
+    reason.as_ref().map(|r| r == "OOMKilled").unwrap_or(false)
+}
+
+impl ContainerStatus {
+    /// How the container's last run ended, if it has restarted
+    pub fn last_terminated(&self) -> Option<&TerminatedState> {
+        self.last_state.as_ref().and_then(|ls| ls.terminated.as_ref())
+    }
+
+    /// Describe how the last run ended, e.g. "exited with code 137 (OOMKilled) at <time>"
+    pub fn last_termination_summary(&self) -> Option<String> {
+        self.last_terminated().map(|t| {
+            let mut summary = format!("exited with code {}", t.exit_code);
+            if let Some(ref reason) = t.reason {
+                summary.push_str(format!(" ({})", reason).as_str());
+            }
+            if let Some(finished) = t.finished_at {
+                summary.push_str(format!(" at {}", finished).as_str());
+            }
+            summary
+        })
+    }
+
+    /// Was the current or last run of the container killed for running out of memory
+    pub fn was_oom_killed(&self) -> bool {
+        match self.state {
+            ContainerState::Terminated { ref reason, .. } if is_oom_reason(reason) => true,
+            _ => self.last_terminated()
+                .map(|t| is_oom_reason(&t.reason))
+                .unwrap_or(false),
+        }
+    }
+
+    /// When the container was last killed for running out of memory, if it was and the time is
+    /// known
+    pub fn oom_killed_at(&self) -> Option<DateTime<Utc>> {
+        match self.state {
+            ContainerState::Terminated {
+                ref reason,
+                finished_at,
+                ..
+            } if is_oom_reason(reason) =>
+            {
+                finished_at
+            }
+            _ => self.last_terminated()
+                .filter(|t| is_oom_reason(&t.reason))
+                .and_then(|t| t.finished_at),
+        }
+    }
+
+    /// If the container is waiting because its image couldn't be pulled, the reason and the
+    /// message the kubelet gave for the failure (or "" if it gave none)
+    pub fn image_pull_failure(&self) -> Option<(&str, &str)> {
+        match self.state {
+            ContainerState::Waiting {
+                ref message,
+                reason: Some(ref reason),
+            } if reason == "ImagePullBackOff" || reason == "ErrImagePull" =>
+            {
+                Some((
+                    reason.as_str(),
+                    message.as_ref().map(|m| m.as_str()).unwrap_or(""),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A container whose image couldn't be pulled
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct ImagePullFailure {
+    pub namespace: String,
+    pub pod: String,
+    pub container: String,
+    pub image: String,
+    pub reason: String,
+    pub message: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct PodStatus {
     pub phase: String,
+    /// Why the pod is in this phase, if there's a pod level reason (like "Evicted")
+    pub reason: Option<String>,
+    pub message: Option<String>,
     #[serde(rename = "containerStatuses")]
     pub container_statuses: Option<Vec<ContainerStatus>>,
+    #[serde(rename = "initContainerStatuses")]
+    pub init_container_statuses: Option<Vec<ContainerStatus>>,
+    /// Debug containers added to a running pod (via `kubectl debug` for example)
+    #[serde(rename = "ephemeralContainerStatuses")]
+    pub ephemeral_container_statuses: Option<Vec<ContainerStatus>>,
+    pub conditions: Option<Vec<Condition>>,
+    #[serde(rename = "qosClass")]
+    pub qos_class: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -170,408 +503,10435 @@ pub struct VolumeMount {
     pub sub_path: Option<String>,
 }
 
+/// A reference to an object in the same namespace by name, used for config maps and secrets
 #[derive(Debug, Deserialize)]
-pub struct ContainerSpec {
-    pub name: String,
-    pub args: Option<Vec<String>>,
-    pub command: Option<Vec<String>>,
-    #[serde(rename = "volumeMounts")]
-    pub volume_mounts: Option<Vec<VolumeMount>>,
+pub struct LocalObjectReference {
+    pub name: Option<String>,
 }
 
+/// A key in a config map or secret
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
-pub struct PodSpec {
-    pub hostname: Option<String>,
-    #[serde(rename = "nodeName")]
-    pub node_name: Option<String>,
-    pub containers: Vec<ContainerSpec>,
+pub struct KeySelector {
+    pub name: Option<String>,
+    pub key: String,
+    pub optional: Option<bool>,
 }
 
+/// A field of the pod, like "metadata.name" or "status.podIP"
 #[derive(Debug, Deserialize)]
-pub struct Pod {
-    pub metadata: Metadata,
-    pub spec: PodSpec,
-    pub status: PodStatus,
+pub struct ObjectFieldSelector {
+    #[serde(rename = "fieldPath")]
+    pub field_path: String,
 }
 
+/// A container's resource request or limit, like "limits.memory"
 #[derive(Debug, Deserialize)]
-pub struct PodList {
-    pub items: Vec<Pod>,
+pub struct ResourceFieldSelector {
+    #[serde(rename = "containerName")]
+    pub container_name: Option<String>,
+    pub resource: String,
 }
 
-// Events
 #[derive(Debug, Deserialize)]
-pub struct Event {
-    pub count: u32,
-    pub message: String,
-    pub reason: String,
-    #[serde(rename = "lastTimestamp")]
-    pub last_timestamp: DateTime<Utc>,
+pub struct EnvVarSource {
+    #[serde(rename = "configMapKeyRef")]
+    pub config_map_key_ref: Option<KeySelector>,
+    #[serde(rename = "secretKeyRef")]
+    pub secret_key_ref: Option<KeySelector>,
+    #[serde(rename = "fieldRef")]
+    pub field_ref: Option<ObjectFieldSelector>,
+    #[serde(rename = "resourceFieldRef")]
+    pub resource_field_ref: Option<ResourceFieldSelector>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct EventList {
-    pub items: Vec<Event>,
+/// Where an environment variable's value comes from
+#[derive(Debug, PartialEq)]
+pub enum EnvValue<'a> {
+    Literal(&'a str),
+    /// A key of a config map, given as (config map, key)
+    ConfigMapKey(&'a str, &'a str),
+    /// A key of a secret, given as (secret, key)
+    SecretKey(&'a str, &'a str),
+    /// A field of the pod
+    Field(&'a str),
+    /// A resource of a container, given as (container, resource), where no container means the
+    /// one the variable is in
+    ResourceField(Option<&'a str>, &'a str),
 }
 
-// Nodes
 #[derive(Debug, Deserialize)]
-pub struct NodeCondition {
-    #[serde(rename = "type")]
-    pub typ: String,
-    pub status: String,
+pub struct EnvVar {
+    pub name: String,
+    pub value: Option<String>,
+    #[serde(rename = "valueFrom")]
+    pub value_from: Option<EnvVarSource>,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct NodeStatus {
-    pub conditions: Vec<NodeCondition>,
+impl EnvVar {
+    /// Where this variable's value comes from.  A variable with neither a value nor a valueFrom
+    /// is set to the empty string.
+    pub fn source(&self) -> EnvValue<'_> {
+        if let Some(ref from) = self.value_from {
+            if let Some(ref sel) = from.config_map_key_ref {
+                return EnvValue::ConfigMapKey(key_ref_name(sel), sel.key.as_str());
+            }
+            if let Some(ref sel) = from.secret_key_ref {
+                return EnvValue::SecretKey(key_ref_name(sel), sel.key.as_str());
+            }
+            if let Some(ref sel) = from.field_ref {
+                return EnvValue::Field(sel.field_path.as_str());
+            }
+            if let Some(ref sel) = from.resource_field_ref {
+                return EnvValue::ResourceField(
+                    sel.container_name.as_ref().map(|c| c.as_str()),
+                    sel.resource.as_str(),
+                );
+            }
+        }
+        EnvValue::Literal(self.value.as_ref().map(|v| v.as_str()).unwrap_or(""))
+    }
+
+    /// Describe this variable's value the way kubectl describe does: literal values are shown
+    /// as is, anything else says where it comes from.  Secret values are never shown.
+    pub fn value_summary(&self) -> String {
+        match self.source() {
+            EnvValue::Literal(value) => value.to_owned(),
+            EnvValue::ConfigMapKey(name, key) => {
+                format!("<set to the key '{}' of config map '{}'>", key, name)
+            }
+            EnvValue::SecretKey(name, key) => {
+                format!("<set to the key '{}' in secret '{}'>", key, name)
+            }
+            EnvValue::Field(path) => format!("({})", path),
+            EnvValue::ResourceField(Some(container), resource) => {
+                format!("{} of {}", resource, container)
+            }
+            EnvValue::ResourceField(None, resource) => resource.to_owned(),
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct NodeSpec {
-    pub unschedulable: Option<bool>,
+fn key_ref_name(sel: &KeySelector) -> &str {
+    sel.name.as_ref().map(|n| n.as_str()).unwrap_or("")
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Node {
-    pub metadata: Metadata,
-    pub spec: NodeSpec,
-    pub status: NodeStatus,
+pub struct EnvFromSource {
+    #[serde(rename = "configMapRef")]
+    pub config_map_ref: Option<LocalObjectReference>,
+    #[serde(rename = "secretRef")]
+    pub secret_ref: Option<LocalObjectReference>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct NodeList {
-    pub items: Vec<Node>,
+pub struct SecretVolumeSource {
+    #[serde(rename = "secretName")]
+    pub secret_name: Option<String>,
 }
 
-// Deployments
-fn replicas_none() -> u32 {
-    0
+#[derive(Debug, Deserialize)]
+pub struct VolumeProjection {
+    #[serde(rename = "configMap")]
+    pub config_map: Option<LocalObjectReference>,
+    pub secret: Option<LocalObjectReference>,
 }
 
-fn replicas_one() -> u32 {
-    1
+#[derive(Debug, Deserialize)]
+pub struct ProjectedVolumeSource {
+    pub sources: Option<Vec<VolumeProjection>>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
-pub struct DeploymentSpec {
-    #[serde(default = "replicas_one")]
-    pub replicas: u32,
+pub struct PersistentVolumeClaimVolumeSource {
+    #[serde(rename = "claimName")]
+    pub claim_name: String,
+    #[serde(rename = "readOnly")]
+    pub read_only: Option<bool>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
-pub struct DeploymentStatus {
-    #[serde(default = "replicas_none")]
-    pub replicas: u32,
-    #[serde(default = "replicas_none", rename = "availableReplicas")]
-    pub available: u32,
-    #[serde(default = "replicas_none", rename = "updatedReplicas")]
-    pub updated: u32,
+pub struct EmptyDirVolumeSource {
+    /// "" for the node's default storage, or "Memory" for a tmpfs
+    pub medium: Option<String>,
+    #[serde(rename = "sizeLimit")]
+    pub size_limit: Option<Quantity>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Deployment {
-    pub metadata: Metadata,
-    pub spec: DeploymentSpec,
-    pub status: DeploymentStatus,
+pub struct HostPathVolumeSource {
+    pub path: String,
 }
 
+/// A pod volume.  Only the common sources are parsed.
 #[derive(Debug, Deserialize)]
-pub struct DeploymentList {
-    pub items: Vec<Deployment>,
+pub struct Volume {
+    pub name: String,
+    #[serde(rename = "configMap")]
+    pub config_map: Option<LocalObjectReference>,
+    pub secret: Option<SecretVolumeSource>,
+    pub projected: Option<ProjectedVolumeSource>,
+    #[serde(rename = "persistentVolumeClaim")]
+    pub persistent_volume_claim: Option<PersistentVolumeClaimVolumeSource>,
+    #[serde(rename = "emptyDir")]
+    pub empty_dir: Option<EmptyDirVolumeSource>,
+    #[serde(rename = "hostPath")]
+    pub host_path: Option<HostPathVolumeSource>,
 }
 
-// Services
-fn tcp_str() -> String {
-    "TCP".to_owned()
+/// The kinds of config object a pod can consume
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigRefKind {
+    ConfigMap,
+    Secret,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ServicePort {
-    pub name: Option<String>,
-    #[serde(rename = "nodePort")]
-    pub node_port: Option<u32>,
-    pub port: u32,
-    #[serde(default = "tcp_str")]
-    pub protocol: String,
-    #[serde(rename = "targetPort")]
-    pub target_pod: Option<Value>,
+fn ref_is(reference: &Option<LocalObjectReference>, name: &str) -> bool {
+    match *reference {
+        Some(ref r) => r.name.as_ref().map(|n| n == name).unwrap_or(false),
+        None => false,
+    }
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ServiceSpec {
-    #[serde(rename = "clusterIP")]
-    pub cluster_ip: Option<String>,
-    #[serde(rename = "externalIPs")]
-    pub external_ips: Option<Vec<String>>,
-    pub ports: Option<Vec<ServicePort>>,
+impl Volume {
+    /// The type of this volume's source, and the name of the object (or host path) it comes
+    /// from, if it has one
+    pub fn source(&self) -> (&'static str, Option<&str>) {
+        if let Some(ref cm) = self.config_map {
+            ("ConfigMap", cm.name.as_ref().map(|n| n.as_str()))
+        } else if let Some(ref secret) = self.secret {
+            ("Secret", secret.secret_name.as_ref().map(|n| n.as_str()))
+        } else if let Some(ref pvc) = self.persistent_volume_claim {
+            ("PersistentVolumeClaim", Some(pvc.claim_name.as_str()))
+        } else if let Some(ref empty_dir) = self.empty_dir {
+            match empty_dir.medium {
+                Some(ref m) if m == "Memory" => ("EmptyDir (memory)", None),
+                _ => ("EmptyDir", None),
+            }
+        } else if let Some(ref host_path) = self.host_path {
+            ("HostPath", Some(host_path.path.as_str()))
+        } else if self.projected.is_some() {
+            ("Projected", None)
+        } else {
+            ("Other", None)
+        }
+    }
+
+    /// Describe the source, e.g. "PersistentVolumeClaim data-0"
+    pub fn source_summary(&self) -> String {
+        match self.source() {
+            (typ, Some(name)) => format!("{} {}", typ, name),
+            (typ, None) => typ.to_owned(),
+        }
+    }
+
+    /// Does this volume mount the config map or secret with the given name
+    pub fn references(&self, kind: ConfigRefKind, name: &str) -> bool {
+        let direct = match kind {
+            ConfigRefKind::ConfigMap => ref_is(&self.config_map, name),
+            ConfigRefKind::Secret => self.secret
+                .as_ref()
+                .and_then(|s| s.secret_name.as_ref())
+                .map(|n| n == name)
+                .unwrap_or(false),
+        };
+        direct || self.projected
+            .as_ref()
+            .and_then(|p| p.sources.as_ref())
+            .map(|sources| {
+                sources.iter().any(|source| match kind {
+                    ConfigRefKind::ConfigMap => ref_is(&source.config_map, name),
+                    ConfigRefKind::Secret => ref_is(&source.secret, name),
+                })
+            })
+            .unwrap_or(false)
+    }
 }
 
+/// Many fields (like ports in probes) can be a number or a name
 #[derive(Debug, Deserialize)]
-pub struct Service {
-    pub metadata: Metadata,
-    pub spec: ServiceSpec,
-    pub status: Value,
+#[serde(untagged)]
+pub enum IntOrString {
+    Int(i64),
+    Str(String),
 }
 
-#[derive(Debug, Deserialize)]
-pub struct ServiceList {
-    pub items: Vec<Service>,
+impl fmt::Display for IntOrString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntOrString::Int(i) => write!(f, "{}", i),
+            IntOrString::Str(ref s) => write!(f, "{}", s),
+        }
+    }
 }
 
-// Namespaces
 #[derive(Debug, Deserialize)]
-pub struct NamespaceStatus {
-    pub phase: String,
+pub struct HttpGetAction {
+    pub path: Option<String>,
+    pub port: IntOrString,
+    pub host: Option<String>,
+    pub scheme: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct Namespace {
-    pub metadata: Metadata,
-    pub status: NamespaceStatus,
+pub struct TcpSocketAction {
+    pub port: IntOrString,
+    pub host: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-pub struct NamespaceList {
-    pub items: Vec<Namespace>,
+pub struct ExecAction {
+    pub command: Option<Vec<String>>,
 }
 
-// ReplicaSets
+/// A liveness, readiness or startup check.  One of the handlers should be set.
 #[derive(Debug, Deserialize)]
-pub struct ReplicaSetList {
-    pub items: Vec<Value>,
+pub struct Probe {
+    #[serde(rename = "httpGet")]
+    pub http_get: Option<HttpGetAction>,
+    #[serde(rename = "tcpSocket")]
+    pub tcp_socket: Option<TcpSocketAction>,
+    pub exec: Option<ExecAction>,
+    #[serde(default, rename = "initialDelaySeconds")]
+    pub initial_delay_seconds: u32,
+    #[serde(default = "probe_timeout", rename = "timeoutSeconds")]
+    pub timeout_seconds: u32,
+    #[serde(default = "probe_period", rename = "periodSeconds")]
+    pub period_seconds: u32,
+    #[serde(default = "probe_success", rename = "successThreshold")]
+    pub success_threshold: u32,
+    #[serde(default = "probe_failure", rename = "failureThreshold")]
+    pub failure_threshold: u32,
 }
 
-// ConfigMaps
-#[derive(Debug, Deserialize)]
-pub struct ConfigMapList {
-    pub items: Vec<Value>,
+// the api server's defaults for probes
+fn probe_timeout() -> u32 {
+    1
 }
 
-// Secrets
-#[derive(Debug, Deserialize)]
-pub struct SecretList {
-    pub items: Vec<Value>,
+fn probe_period() -> u32 {
+    10
 }
 
-// Kubernetes authentication data
+fn probe_success() -> u32 {
+    1
+}
 
-// Auth is either a token, a username/password, or a cert and key
-pub enum KlusterAuth {
-    Token(String),
-    UserPass(String, String),
-    CertKey(Vec<Certificate>, PrivateKey),
+fn probe_failure() -> u32 {
+    3
 }
 
-impl KlusterAuth {
-    pub fn with_token(token: &str) -> KlusterAuth {
-        KlusterAuth::Token(token.to_owned())
+impl fmt::Display for Probe {
+    /// Format like kubectl describe, e.g. "http-get http://:8080/healthz delay=0s timeout=1s
+    /// period=10s #success=1 #failure=3"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref http) = self.http_get {
+            write!(
+                f,
+                "http-get {}://{}:{}{}",
+                http.scheme
+                    .as_ref()
+                    .map(|s| s.to_lowercase())
+                    .unwrap_or_else(|| "http".to_owned()),
+                http.host.as_ref().map(|h| h.as_str()).unwrap_or(""),
+                http.port,
+                http.path.as_ref().map(|p| p.as_str()).unwrap_or("")
+            )?;
+        } else if let Some(ref tcp) = self.tcp_socket {
+            write!(
+                f,
+                "tcp-socket {}:{}",
+                tcp.host.as_ref().map(|h| h.as_str()).unwrap_or(""),
+                tcp.port
+            )?;
+        } else if let Some(ref exec) = self.exec {
+            write!(
+                f,
+                "exec [{}]",
+                exec.command.as_ref().map(|c| c.join(" ")).unwrap_or_default()
+            )?;
+        } else {
+            write!(f, "unknown")?;
+        }
+        write!(
+            f,
+            " delay={}s timeout={}s period={}s #success={} #failure={}",
+            self.initial_delay_seconds,
+            self.timeout_seconds,
+            self.period_seconds,
+            self.success_threshold,
+            self.failure_threshold
+        )
     }
+}
 
-    pub fn with_userpass(user: &str, pass: &str) -> KlusterAuth {
-        KlusterAuth::UserPass(user.to_owned(), pass.to_owned())
-    }
-    
-    pub fn with_cert_and_key(cert: Certificate, private_key: PrivateKey) -> KlusterAuth {
-        KlusterAuth::CertKey(vec![cert], private_key)
-    }
+#[derive(Debug, Deserialize)]
+pub struct ResourceRequirements {
+    pub limits: Option<HashMap<String, Quantity>>,
+    pub requests: Option<HashMap<String, Quantity>>,
 }
 
-pub struct Kluster {
+#[derive(Debug, Deserialize)]
+pub struct ContainerSpec {
     pub name: String,
-    endpoint: Url,
-    auth: KlusterAuth,
-    cert_opt: Option<String>,
-    client: Client,
+    /// Optional in the api, though pods can't be created without one
+    pub image: Option<String>,
+    pub args: Option<Vec<String>>,
+    pub command: Option<Vec<String>>,
+    #[serde(rename = "volumeMounts")]
+    pub volume_mounts: Option<Vec<VolumeMount>>,
+    pub resources: Option<ResourceRequirements>,
+    pub env: Option<Vec<EnvVar>>,
+    #[serde(rename = "envFrom")]
+    pub env_from: Option<Vec<EnvFromSource>>,
+    #[serde(rename = "livenessProbe")]
+    pub liveness_probe: Option<Probe>,
+    #[serde(rename = "readinessProbe")]
+    pub readiness_probe: Option<Probe>,
+    #[serde(rename = "startupProbe")]
+    pub startup_probe: Option<Probe>,
 }
 
-impl Kluster {
-    fn make_tlsclient(cert_opt: &Option<String>, auth: &KlusterAuth) -> TlsClient {
-        let mut tlsclient = TlsClient::new();
-        if let &Some(ref cert_data) = cert_opt {
-            // add the cert to the root store
-            if let Some(cfg) = Arc::get_mut(&mut tlsclient.cfg) {
-                let mut br = BufReader::new(cert_data.as_bytes());
-                match cfg.root_store.add_pem_file(&mut br) {
-                    Ok(added) => {
-                        if added.1 > 0 {
-                            println!(
-                                "[WARNING] Couldn't add your server cert, connection will probably fail"
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        println!(
-                            "[WARNING] Coudln't add your server cert, connection will probably fail. Error was: {:?}",
-                            e
-                        )
-                    }
+/// How a container's limit for one resource compares to its request
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct ResourceRatio {
+    pub resource: String,
+    pub request: Option<Quantity>,
+    pub limit: Option<Quantity>,
+    /// limit / request, or None if there's no limit (or the request is zero)
+    pub ratio: Option<f64>,
+}
+
+/// The limit/request ratios for all the resources a container specifies
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct ContainerLimits {
+    pub container: String,
+    pub ratios: Vec<ResourceRatio>,
+    /// True if the container has no limits at all, so can use as much of the node as it likes
+    pub unbounded: bool,
+}
+
+impl ContainerSpec {
+    /// Does this container's environment pull from the config map or secret with the given name
+    pub fn references(&self, kind: ConfigRefKind, name: &str) -> bool {
+        let in_env = self.env
+            .as_ref()
+            .map(|env| {
+                env.iter().any(|var| match (var.source(), kind) {
+                    (EnvValue::ConfigMapKey(map, _), ConfigRefKind::ConfigMap) => map == name,
+                    (EnvValue::SecretKey(secret, _), ConfigRefKind::Secret) => secret == name,
+                    _ => false,
+                })
+            })
+            .unwrap_or(false);
+        in_env || self.env_from
+            .as_ref()
+            .map(|env_from| {
+                env_from.iter().any(|source| match kind {
+                    ConfigRefKind::ConfigMap => ref_is(&source.config_map_ref, name),
+                    ConfigRefKind::Secret => ref_is(&source.secret_ref, name),
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// The amount of resource this container requests, which defaults to its limit if it only
+    /// has a limit
+    pub fn request(&self, resource: &str) -> Option<Quantity> {
+        self.resources.as_ref().and_then(|r| {
+            r.requests
+                .as_ref()
+                .and_then(|reqs| reqs.get(resource))
+                .or_else(|| r.limits.as_ref().and_then(|lims| lims.get(resource)))
+                .cloned()
+        })
+    }
+
+    /// Compute the limit/request ratio for each resource this container requests or limits.  A
+    /// limit with no request means the request defaults to the limit, as the api server does.
+    pub fn limit_ratios(&self) -> ContainerLimits {
+        let empty = HashMap::new();
+        let (requests, limits) = match self.resources {
+            Some(ref r) => (
+                r.requests.as_ref().unwrap_or(&empty),
+                r.limits.as_ref().unwrap_or(&empty),
+            ),
+            None => (&empty, &empty),
+        };
+        let mut resources: Vec<&String> = requests.keys().chain(limits.keys()).collect();
+        resources.sort();
+        resources.dedup();
+        let ratios = resources
+            .into_iter()
+            .map(|resource| {
+                let limit = limits.get(resource).cloned();
+                let request = requests.get(resource).cloned().or(limit);
+                let ratio = match (request, limit) {
+                    (Some(req), Some(lim)) if !req.is_zero() => Some(lim.as_f64() / req.as_f64()),
+                    _ => None,
+                };
+                ResourceRatio {
+                    resource: resource.clone(),
+                    request: request,
+                    limit: limit,
+                    ratio: ratio,
                 }
+            })
+            .collect();
+        ContainerLimits {
+            container: self.name.clone(),
+            ratios: ratios,
+            unbounded: limits.is_empty(),
+        }
+    }
+}
 
-                if let &KlusterAuth::CertKey(ref cert, ref key) = auth {
-                    cfg.set_single_client_cert(cert.clone(), key.clone());
+/// Lets a pod schedule onto (or stay on) nodes with a matching taint
+#[derive(Debug, Deserialize)]
+pub struct Toleration {
+    /// None (with operator Exists) matches any key
+    pub key: Option<String>,
+    /// Equal (the default) or Exists
+    pub operator: Option<String>,
+    pub value: Option<String>,
+    /// None matches any effect
+    pub effect: Option<String>,
+    #[serde(rename = "tolerationSeconds")]
+    pub toleration_seconds: Option<i64>,
+}
+
+impl fmt::Display for Toleration {
+    /// Format like kubectl describe does, e.g. "node.kubernetes.io/not-ready:NoExecute op=Exists
+    /// for 300s"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exists = self.operator.as_ref().map(|o| o == "Exists").unwrap_or(false);
+        if let Some(ref key) = self.key {
+            write!(f, "{}", key)?;
+            if let Some(ref value) = self.value {
+                if !value.is_empty() {
+                    write!(f, "={}", value)?;
                 }
-            } else {
-                println!(
-                    "[WARNING] Failed to configure tlsclient, connection will probably fail.  Please restart click"
-                );
             }
         }
-        tlsclient
+        if let Some(ref effect) = self.effect {
+            write!(f, ":{}", effect)?;
+        }
+        if exists {
+            write!(f, " op=Exists")?;
+        }
+        if let Some(secs) = self.toleration_seconds {
+            write!(f, " for {}s", secs)?;
+        }
+        Ok(())
+    }
+}
+
+/// A node selector term matches nodes that meet all of its requirements.  Node requirements have
+/// the same shape as label selector ones, but can also use the Gt and Lt operators.
+#[derive(Debug, Deserialize)]
+pub struct NodeSelectorTerm {
+    #[serde(rename = "matchExpressions")]
+    pub match_expressions: Option<Vec<LabelSelectorRequirement>>,
+}
+
+impl fmt::Display for NodeSelectorTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exprs: Vec<String> = self.match_expressions
+            .iter()
+            .flat_map(|e| e.iter())
+            .map(|e| {
+                let values = e.values.as_ref().map(|vs| vs.join(",")).unwrap_or_default();
+                match e.operator.as_str() {
+                    "Exists" => e.key.clone(),
+                    "DoesNotExist" => format!("!{}", e.key),
+                    "Gt" => format!("{}>{}", e.key, values),
+                    "Lt" => format!("{}<{}", e.key, values),
+                    op => format!("{} {} ({})", e.key, op.to_lowercase(), values),
+                }
+            })
+            .collect();
+        write!(f, "{}", exprs.join(", "))
     }
+}
 
-    // We map ip addresses to a name in the certificate if needed, to keep hyper happy.  see
-    // comments on try_ip_to_name and in connector.rs.
-    fn make_connector(tlsclient: TlsClient, endpoint: &mut Url) -> ClickSslConnector<TlsClient> {
-        let mut dns_host: Option<String> = None;
-        let mut ip: Option<String> = None;
-        if let Some(host) = endpoint.host_str() {
-            if let Ok(addr) = IpAddr::from_str(host) {
-                dns_host = ::certs::try_ip_to_name(&addr, endpoint.port().unwrap_or(443));
-                ip = Some(host.to_owned());
+#[derive(Debug, Deserialize)]
+pub struct NodeSelector {
+    #[serde(rename = "nodeSelectorTerms")]
+    pub node_selector_terms: Vec<NodeSelectorTerm>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreferredSchedulingTerm {
+    pub weight: i32,
+    pub preference: NodeSelectorTerm,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeAffinity {
+    /// The pod can only be scheduled on a node matching one of these terms
+    #[serde(rename = "requiredDuringSchedulingIgnoredDuringExecution")]
+    pub required: Option<NodeSelector>,
+    /// The scheduler prefers nodes matching these terms, by the sum of the weights matched
+    #[serde(rename = "preferredDuringSchedulingIgnoredDuringExecution")]
+    pub preferred: Option<Vec<PreferredSchedulingTerm>>,
+}
+
+impl NodeAffinity {
+    /// One line per term, like "required: zone in (a,b)" or "preferred (weight 10): ssd"
+    pub fn summary(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(ref required) = self.required {
+            for term in required.node_selector_terms.iter() {
+                lines.push(format!("required: {}", term));
+            }
+        }
+        if let Some(ref preferred) = self.preferred {
+            for term in preferred.iter() {
+                lines.push(format!("preferred (weight {}): {}", term.weight, term.preference));
             }
-        };
-        if let (Some(host), Some(ip_addr)) = (dns_host, ip) {
-            // The cert has a matching IP and a host name, use that
-            endpoint.set_host(Some(host.as_str())).unwrap();
-            ClickSslConnector::new(tlsclient, Some((host, ip_addr)))
-        } else {
-            ClickSslConnector::new(tlsclient, None)
         }
+        lines
     }
+}
 
-    fn add_auth_header<'a>(&self, req: RequestBuilder<'a>) -> RequestBuilder<'a> {
-        match self.auth {
-            KlusterAuth::Token(ref token) => {
-                req.header(Authorization(Bearer {
-                    token: token.clone(),
-                }))
-            },
-            KlusterAuth::UserPass(ref user, ref pass) => {
-                req.header(Authorization(Basic {
-                    username: user.clone(),
-                    password: Some(pass.clone()),
-                }))
-            },
-            KlusterAuth::CertKey(..) => req
-        }
+/// Only node affinity is parsed so far, pod (anti-)affinity is ignored
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Affinity {
+    #[serde(rename = "nodeAffinity")]
+    pub node_affinity: Option<NodeAffinity>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PodSpec {
+    pub hostname: Option<String>,
+    #[serde(rename = "nodeName")]
+    pub node_name: Option<String>,
+    pub containers: Vec<ContainerSpec>,
+    #[serde(rename = "initContainers")]
+    pub init_containers: Option<Vec<ContainerSpec>>,
+    pub volumes: Option<Vec<Volume>>,
+    #[serde(rename = "serviceAccountName")]
+    pub service_account_name: Option<String>,
+    #[serde(rename = "imagePullSecrets")]
+    pub image_pull_secrets: Option<Vec<LocalObjectReference>>,
+    #[serde(rename = "priorityClassName")]
+    pub priority_class_name: Option<String>,
+    /// Resolved from the priority class when the pod is created
+    pub priority: Option<i32>,
+    #[allow(dead_code)]
+    pub tolerations: Option<Vec<Toleration>>,
+    #[allow(dead_code)]
+    pub affinity: Option<Affinity>,
+}
+
+impl PodSpec {
+    /// Find the pod volume a container's volume mount refers to
+    pub fn volume_for_mount(&self, mount: &VolumeMount) -> Option<&Volume> {
+        self.volumes
+            .as_ref()
+            .and_then(|vols| vols.iter().find(|v| v.name == mount.name))
     }
-    
-    pub fn new(
-        name: &str,
-        cert_opt: Option<String>,
-        server: &str,
-        auth: KlusterAuth,
-    ) -> Result<Kluster, KubeError> {
-        let tlsclient = Kluster::make_tlsclient(&cert_opt, &auth);
-        let mut endpoint = try!(Url::parse(server));
-        let mut client = Client::with_connector(Kluster::make_connector(tlsclient, &mut endpoint));
-        client.set_read_timeout(Some(Duration::new(20, 0)));
-        client.set_write_timeout(Some(Duration::new(20, 0)));
-        Ok(Kluster {
-            name: name.to_owned(),
-            endpoint: endpoint,
-            auth: auth,
-            cert_opt: cert_opt,
-            client: client,
-        })
+
+    /// Does this pod consume the config map or secret with the given name, either via a volume or
+    /// in the environment of any of its (init) containers
+    pub fn references(&self, kind: ConfigRefKind, name: &str) -> bool {
+        let in_volumes = self.volumes
+            .as_ref()
+            .map(|vols| vols.iter().any(|v| v.references(kind, name)))
+            .unwrap_or(false);
+        let in_init = self.init_containers
+            .as_ref()
+            .map(|cs| cs.iter().any(|c| c.references(kind, name)))
+            .unwrap_or(false);
+        in_volumes || in_init || self.containers.iter().any(|c| c.references(kind, name))
     }
 
-    fn send_req(&self, path: &str) -> Result<Response, KubeError> {
-        let url = try!(self.endpoint.join(path));
-        let req = self.client.get(url);
-        let req = self.add_auth_header(req);
-        req.send().map_err(|he| KubeError::from(he))
+    /// Does any container (including init containers) run an image matching image.  See
+    /// image_matches for what matching means.
+    pub fn uses_image(&self, image: &str, prefix: bool) -> bool {
+        self.containers
+            .iter()
+            .chain(self.init_containers.iter().flat_map(|cs| cs.iter()))
+            .filter_map(|c| c.image.as_ref())
+            .any(|i| image_matches(i.as_str(), image, prefix))
     }
+}
 
-    fn check_resp(&self, resp: Response) -> Result<Response, KubeError> {
-        if resp.status == StatusCode::Ok {
-            Ok(resp)
-        } else if resp.status == StatusCode::Unauthorized {
-            Err(KubeError::Kube(KubeErrNo::Unauthorized))
-        } else {
-            // try and read an error message out
-            let val: Value = try!(serde_json::from_reader(resp));
-            match ::values::val_str_opt("/message", &val) {
-                Some(msg) => Err(KubeError::KubeServerError(msg)),
-                None => Err(KubeError::Kube(KubeErrNo::Unknown)),
-            }
+/// Split an image reference into its repository, tag and digest, e.g. "registry:5000/app:1.0"
+/// is ("registry:5000/app", Some("1.0"), None).  A colon before the last slash is a port.
+fn split_image(image: &str) -> (&str, Option<&str>, Option<&str>) {
+    let (rest, digest) = match image.find('@') {
+        Some(at) => (&image[..at], Some(&image[at + 1..])),
+        None => (image, None),
+    };
+    let slash = rest.rfind('/').map(|s| s + 1).unwrap_or(0);
+    match rest[slash..].rfind(':') {
+        Some(colon) => (&rest[..slash + colon], Some(&rest[slash + colon + 1..]), digest),
+        None => (rest, None, digest),
+    }
+}
+
+/// Check if a container's image matches the image being searched for.  With prefix, the image
+/// just has to start with the search (e.g. "gcr.io/my-project/").  Otherwise the repositories
+/// must be the same, and if the search specifies a tag or digest that must match too
+/// (an image with no tag counts as ":latest").
+pub fn image_matches(image: &str, search: &str, prefix: bool) -> bool {
+    if prefix {
+        return image.starts_with(search);
+    }
+    let (repo, tag, digest) = split_image(image);
+    let (search_repo, search_tag, search_digest) = split_image(search);
+    if repo != search_repo {
+        return false;
+    }
+    if search_digest.is_some() {
+        return digest == search_digest;
+    }
+    match search_tag {
+        Some(search_tag) => {
+            tag.unwrap_or("latest") == search_tag && (tag.is_some() || digest.is_none())
         }
+        None => true,
     }
+}
 
-    /// Get a resource and deserialize it as a T
-    pub fn get<T>(&self, path: &str) -> Result<T, KubeError>
-    where
-        for<'de> T: Deserialize<'de>,
-    {
-        let resp = try!(self.send_req(path));
-        let resp = try!(self.check_resp(resp));
-        serde_json::from_reader(resp).map_err(|sje| KubeError::from(sje))
+#[derive(Debug, Deserialize)]
+pub struct Pod {
+    pub metadata: Metadata,
+    pub spec: PodSpec,
+    pub status: PodStatus,
+}
+
+/// What, if anything, will recreate a pod if it's deleted
+#[derive(Debug, PartialEq)]
+pub enum PodOwnership<'a> {
+    /// Managed by a controller (ReplicaSet, Job, ...), given as (kind, name)
+    Controlled(&'a str, &'a str),
+    /// A static pod run from a manifest on the node.  The api server just has a read-only mirror
+    /// of it, and the kubelet will recreate it.
+    Mirror,
+    /// Nothing manages this pod, deleting it is permanent
+    Bare,
+}
+
+/// A pod's quality of service class, which decides (along with priority) which pods the kubelet
+/// evicts first when a node runs short of resources: BestEffort, then Burstable, then Guaranteed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QosClass {
+    /// Every container has cpu and memory limits equal to its requests
+    Guaranteed,
+    Burstable,
+    /// No container has any cpu or memory requests or limits
+    BestEffort,
+}
+
+impl fmt::Display for QosClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            QosClass::Guaranteed => write!(f, "Guaranteed"),
+            QosClass::Burstable => write!(f, "Burstable"),
+            QosClass::BestEffort => write!(f, "BestEffort"),
+        }
     }
+}
 
-    /// Get a Response.  Response implements Read, so this allows for a streaming read (for things
-    /// like printing logs)
-    pub fn get_read(&self, path: &str, timeout: Option<Duration>) -> Result<Response, KubeError> {
-        if timeout.is_some() {
-            let url = try!(self.endpoint.join(path));
-            let mut req = try!(Request::with_connector(
-                Method::Get,
-                url,
-                &HttpsConnector::new(Kluster::make_tlsclient(&self.cert_opt, &self.auth))
-            ));
-            {
-                // scope for mutable borrow of req
-                let headers = req.headers_mut();
-                if let KlusterAuth::Token(ref token) = self.auth {
-                    headers.set(Authorization(Bearer {
-                        token: token.clone(),
-                    }));
-                } else if let KlusterAuth::UserPass(ref user, ref pass) = self.auth {
-                    headers.set(Authorization(Basic {
-                        username: user.clone(),
-                        password: Some(pass.clone()),
-                    }));
+/// Where the time went between a pod being created and it becoming ready.  Each phase is None
+/// if the pod hasn't got that far (or its status doesn't say).
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct StartupBreakdown {
+    /// Created until bound to a node
+    pub scheduling: Option<::chrono::Duration>,
+    /// Scheduled until its init containers finished (including pulling their images)
+    pub initializing: Option<::chrono::Duration>,
+    /// Initialized until the last of its containers started, which is mostly pulling images
+    pub starting: Option<::chrono::Duration>,
+    /// The last container starting until the pod was ready, i.e. passing readiness probes
+    pub readiness: Option<::chrono::Duration>,
+    /// Created until ready
+    pub total: Option<::chrono::Duration>,
+}
+
+/// Restarts past this many make a running pod count as degraded
+const DEGRADED_RESTARTS: u32 = 5;
+
+/// Container waiting reasons that mean it won't start without something changing
+const STUCK_WAITING_REASONS: &[&str] = &[
+    "CrashLoopBackOff",
+    "ImagePullBackOff",
+    "ErrImagePull",
+    "InvalidImageName",
+    "CreateContainerConfigError",
+    "CreateContainerError",
+];
+
+/// The overall health of a pod
+#[derive(Debug, Clone, PartialEq)]
+pub enum PodHealth {
+    /// Running with every container ready, or completed successfully
+    Healthy,
+    /// Not running yet, but not stuck either
+    Pending,
+    /// Running (or trying to) with problems, for the given reason
+    Degraded(String),
+    /// Failed or evicted, for the given reason
+    Failed(String),
+}
+
+impl Pod {
+    /// The names of the containers logs can be fetched for: the pod's containers, and any
+    /// ephemeral (debug) containers that have been added to it
+    pub fn log_container_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.spec
+            .containers
+            .iter()
+            .map(|cspec| cspec.name.clone())
+            .collect();
+        if let Some(ref ephemerals) = self.status.ephemeral_container_statuses {
+            names.extend(ephemerals.iter().map(|cs| cs.name.clone()));
+        }
+        names
+    }
+
+    /// Work out the pod's overall health from its phase, pod level reason, and the states,
+    /// readiness and restart counts of its containers
+    pub fn health(&self) -> PodHealth {
+        let status = &self.status;
+        if status.phase == "Failed" {
+            let reason = status
+                .reason
+                .as_ref()
+                .or(status.message.as_ref())
+                .cloned()
+                .unwrap_or_else(|| "Failed".to_owned());
+            return PodHealth::Failed(reason);
+        }
+        if status.phase == "Succeeded" {
+            return PodHealth::Healthy;
+        }
+        // a pod level reason on a pod that hasn't failed (like NodeLost when its node stops
+        // reporting) is a problem, but the pod may yet recover
+        if let Some(ref reason) = status.reason {
+            return PodHealth::Degraded(reason.clone());
+        }
+
+        let statuses: Vec<&ContainerStatus> = status
+            .init_container_statuses
+            .iter()
+            .chain(status.container_statuses.iter())
+            .flat_map(|s| s.iter())
+            .collect();
+        let stuck = statuses
+            .iter()
+            .filter_map(|cs| match cs.state {
+                ContainerState::Waiting {
+                    reason: Some(ref reason),
+                    ..
+                } if STUCK_WAITING_REASONS.contains(&reason.as_str()) =>
+                {
+                    Some(format!("{}: {}", cs.name, reason))
+                }
+                _ => None,
+            })
+            .next();
+        if let Some(reason) = stuck {
+            return PodHealth::Degraded(reason);
+        }
+
+        match status.phase.as_str() {
+            "Pending" => PodHealth::Pending,
+            "Running" => {
+                let containers = status.container_statuses.as_ref();
+                let total = containers.map(|cs| cs.len()).unwrap_or(0);
+                let ready = containers
+                    .map(|cs| cs.iter().filter(|c| c.ready).count())
+                    .unwrap_or(0);
+                let restarts: u32 = statuses.iter().map(|cs| cs.restart_count).sum();
+                if ready < total {
+                    PodHealth::Degraded(format!("{}/{} containers ready", ready, total))
+                } else if restarts > DEGRADED_RESTARTS {
+                    PodHealth::Degraded(format!("{} restarts", restarts))
+                } else {
+                    PodHealth::Healthy
                 }
             }
-            try!(req.set_read_timeout(timeout));
-            let next = try!(req.start());
-            let resp = try!(next.send().map_err(|he| KubeError::from(he)));
-            self.check_resp(resp)
+            phase => PodHealth::Degraded(format!("phase is {}", phase)),
+        }
+    }
+
+    /// The pod's QoS class, as reported in its status, or worked out from its containers'
+    /// resources the way the api server does if the status doesn't say
+    pub fn qos_class(&self) -> QosClass {
+        match self.status.qos_class.as_ref().map(|q| q.as_str()) {
+            Some("Guaranteed") => return QosClass::Guaranteed,
+            Some("Burstable") => return QosClass::Burstable,
+            Some("BestEffort") => return QosClass::BestEffort,
+            _ => {}
+        }
+        let containers: Vec<&ContainerSpec> = self.spec
+            .containers
+            .iter()
+            .chain(self.spec.init_containers.iter().flat_map(|cs| cs.iter()))
+            .collect();
+        let resources = ["cpu", "memory"];
+        let limit = |c: &ContainerSpec, r: &str| {
+            c.resources
+                .as_ref()
+                .and_then(|res| res.limits.as_ref())
+                .and_then(|lims| lims.get(r))
+                .cloned()
+        };
+        let any_set = containers
+            .iter()
+            .any(|c| resources.iter().any(|r| c.request(r).is_some() || limit(c, r).is_some()));
+        if !any_set {
+            return QosClass::BestEffort;
+        }
+        let guaranteed = containers.iter().all(|c| {
+            resources.iter().all(|r| match limit(c, r) {
+                Some(lim) => c.request(r) == Some(lim),
+                None => false,
+            })
+        });
+        if guaranteed {
+            QosClass::Guaranteed
         } else {
-            let resp = try!(self.send_req(path));
-            self.check_resp(resp)
+            QosClass::Burstable
         }
     }
 
-    /// Get a serde_json::Value
-    pub fn get_value(&self, path: &str) -> Result<Value, KubeError> {
-        let resp = try!(self.send_req(path));
-        let resp = try!(self.check_resp(resp));
-        serde_json::from_reader(resp).map_err(|sje| KubeError::from(sje))
+    /// When the pod's condition of type typ last became True, if it is True
+    fn condition_true_since(&self, typ: &str) -> Option<DateTime<Utc>> {
+        self.status
+            .conditions
+            .iter()
+            .flat_map(|cs| cs.iter())
+            .find(|c| c.typ == typ && c.status == "True")
+            .and_then(|c| c.last_transition_time)
     }
 
-    /// Issue an HTTP DELETE request to the specified path
-    pub fn delete(&self, path: &str, body: Option<String>) -> Result<Response, KubeError> {
-        let url = try!(self.endpoint.join(path));
-        let req = self.client.delete(url);
-        let req = match body {
-            Some(ref b) => {
-                let hyper_body = Body::BufBody(b.as_bytes(), b.len());
-                req.body(hyper_body)
-            },
-            None => req
+    /// Work out how long each stage of starting the pod took, from its creation time, when its
+    /// PodScheduled, Initialized and Ready conditions became true, and when its containers
+    /// started.  Conditions record the last time they changed, so for a pod that has since
+    /// restarted or flapped this describes the latest change, not the first start.
+    pub fn startup_breakdown(&self) -> StartupBreakdown {
+        let created = self.metadata.creation_timestamp;
+        let scheduled = self.condition_true_since("PodScheduled");
+        let initialized = self.condition_true_since("Initialized");
+        let ready = self.condition_true_since("Ready");
+        let starts: Vec<DateTime<Utc>> = self.status
+            .container_statuses
+            .iter()
+            .flat_map(|cs| cs.iter())
+            .filter_map(|cs| match cs.state {
+                ContainerState::Running { started_at } => started_at,
+                ContainerState::Terminated { started_at, .. } => started_at,
+                ContainerState::Waiting { .. } => None,
+            })
+            .collect();
+        let total_containers = self.spec.containers.len();
+        // only count containers as started once they all have
+        let started = if !starts.is_empty() && starts.len() == total_containers {
+            starts.into_iter().max()
+        } else {
+            None
         };
-        let req = self.add_auth_header(req);
-        req.send().map_err(|he| KubeError::from(he))
+        let between = |from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>| match (from, to) {
+            (Some(from), Some(to)) => Some(to.signed_duration_since(from)),
+            _ => None,
+        };
+        StartupBreakdown {
+            scheduling: between(created, scheduled),
+            initializing: between(scheduled, initialized),
+            starting: between(initialized, started),
+            readiness: between(started, ready),
+            total: between(created, ready),
+        }
     }
 
-    /// Get all namespaces in this cluster
-    pub fn namespaces_for_context(&self) -> Result<Vec<String>, KubeError> {
-        let mut vec = Vec::new();
-        let res = try!(self.get::<NamespaceList>("/api/v1/namespaces"));
-        for ns in res.items.iter() {
-            vec.push(ns.metadata.name.clone());
+    /// If the pod is still running its init containers, a status like kubectl shows for it:
+    /// "Init:1/3" while the second of three runs, or "Init:<reason>" if the current one is
+    /// stuck (like "Init:CrashLoopBackOff") or failed.  Init containers run one at a time in
+    /// order, so the first that hasn't succeeded is the one holding the pod up.  None once
+    /// they've all succeeded (or if there are none).
+    pub fn init_status(&self) -> Option<String> {
+        let statuses = self.status.init_container_statuses.as_ref()?;
+        let total = self.spec
+            .init_containers
+            .as_ref()
+            .map(|cs| cs.len())
+            .unwrap_or_else(|| statuses.len());
+        for (i, status) in statuses.iter().enumerate() {
+            let stuck = match status.state {
+                ContainerState::Terminated { exit_code: 0, .. } => continue,
+                ContainerState::Terminated {
+                    ref reason,
+                    signal,
+                    exit_code,
+                    ..
+                } => match (reason, signal) {
+                    (&Some(ref reason), _) => Some(reason.clone()),
+                    (&None, Some(signal)) if signal != 0 => Some(format!("Signal:{}", signal)),
+                    _ => Some(format!("ExitCode:{}", exit_code)),
+                },
+                ContainerState::Waiting {
+                    reason: Some(ref reason),
+                    ..
+                } if reason != "PodInitializing" => Some(reason.clone()),
+                _ => None,
+            };
+            return Some(match stuck {
+                Some(reason) => format!("Init:{}", reason),
+                None => format!("Init:{}/{}", i, total),
+            });
         }
-        Ok(vec)
+        None
+    }
+
+    /// Describe the pod's priority, e.g. "system-node-critical (2000001000)".  Pods without a
+    /// priority class get the default (0, unless there's a global default class).
+    pub fn priority_summary(&self) -> String {
+        match (&self.spec.priority_class_name, self.spec.priority) {
+            (&Some(ref class), Some(priority)) => format!("{} ({})", class, priority),
+            (&Some(ref class), None) => class.clone(),
+            (&None, Some(priority)) => priority.to_string(),
+            (&None, None) => "<default>".to_owned(),
+        }
+    }
+
+    /// True if the pod's Ready condition is True, i.e. it's passing readiness checks and will get
+    /// traffic from services
+    pub fn is_ready(&self) -> bool {
+        self.status
+            .conditions
+            .iter()
+            .flat_map(|cs| cs.iter())
+            .any(|c| c.typ == "Ready" && c.status == "True")
+    }
+
+    pub fn ownership(&self) -> PodOwnership<'_> {
+        let is_mirror = self.metadata
+            .annotations
+            .as_ref()
+            .map(|a| a.contains_key("kubernetes.io/config.mirror"))
+            .unwrap_or(false);
+        if is_mirror {
+            return PodOwnership::Mirror;
+        }
+        let controller = self.metadata
+            .owner_refs
+            .iter()
+            .flat_map(|refs| refs.iter())
+            .find(|r| r.controller);
+        match controller {
+            Some(owner) => PodOwnership::Controlled(owner.kind.as_str(), owner.name.as_str()),
+            None => PodOwnership::Bare,
+        }
+    }
+
+    /// Get limit/request ratios for each container in the pod
+    #[allow(dead_code)]
+    pub fn limit_ratios(&self) -> Vec<ContainerLimits> {
+        self.spec
+            .containers
+            .iter()
+            .map(|c| c.limit_ratios())
+            .collect()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PodList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub metadata: Option<ListMetadata>,
+    pub items: Vec<Pod>,
+}
+
+/// The outcome of deleting one pod as part of a batch
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct PodDeleteResult {
+    pub namespace: String,
+    pub name: String,
+    pub result: Result<(), KubeError>,
+}
+
+// Events
+/// A reference to the object an event is about
+#[allow(dead_code)]
+#[derive(Debug, Clone, Deserialize)]
+pub struct ObjectReference {
+    pub kind: Option<String>,
+    pub namespace: Option<String>,
+    pub name: Option<String>,
+    /// For events about a container, something like "spec.containers{name}"
+    #[serde(rename = "fieldPath")]
+    pub field_path: Option<String>,
+}
+
+impl ObjectReference {
+    /// If this refers to a container of a pod, the container's name
+    pub fn container_name(&self) -> Option<&str> {
+        self.field_path.as_ref().and_then(|fp| {
+            let start = fp.find('{')?;
+            let end = fp.rfind('}')?;
+            if start < end {
+                Some(&fp[start + 1..end])
+            } else {
+                None
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Event {
+    pub count: u32,
+    pub message: String,
+    pub reason: String,
+    #[serde(rename = "lastTimestamp")]
+    pub last_timestamp: DateTime<Utc>,
+    /// "Normal" or "Warning"
+    #[serde(rename = "type")]
+    pub typ: Option<String>,
+    #[serde(rename = "involvedObject")]
+    pub involved_object: Option<ObjectReference>,
+}
+
+impl Event {
+    /// Is this a warning event that mentions running out of memory
+    pub fn is_oom_warning(&self) -> bool {
+        self.typ.as_ref().map(|t| t == "Warning").unwrap_or(false)
+            && (self.reason.to_uppercase().contains("OOM")
+                || self.message.to_uppercase().contains("OOM"))
+    }
+
+    /// Is this event about the specified container of a pod, or about the pod as a whole if
+    /// container is None
+    fn is_about(&self, pod: &str, container: Option<&str>) -> bool {
+        match self.involved_object {
+            Some(ref obj) => {
+                obj.name.as_ref().map(|n| n == pod).unwrap_or(false)
+                    && obj.container_name() == container
+            }
+            None => false,
+        }
+    }
+}
+
+/// A container that ran out of memory, from its status and/or OOM warning events about it
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct OomKill {
+    pub pod: String,
+    /// None if there were OOM events for the pod that couldn't be tied to a container
+    pub container: Option<String>,
+    pub restart_count: u32,
+    /// When the container was last OOMKilled, if its status says it was
+    pub killed_at: Option<DateTime<Utc>>,
+    /// Messages of matching OOM warning events
+    pub events: Vec<String>,
+}
+
+/// Why the scheduler couldn't place a pod, e.g. from a message like "0/5 nodes are available: 3
+/// Insufficient cpu, 2 node(s) didn't match pod anti-affinity rules."
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SchedulingDiagnosis {
+    pub pod: String,
+    /// False if the pod has been scheduled, in which case the rest is from an old attempt
+    pub pending: bool,
+    /// How many nodes the scheduler considered
+    pub total_nodes: Option<u32>,
+    /// How many nodes were ruled out for each reason, in the order the scheduler gave them
+    pub reasons: Vec<(u32, String)>,
+    /// The full message the reasons were parsed from, if there was one
+    pub message: Option<String>,
+}
+
+/// Parse the reasons out of a scheduler failure message, returning the number of nodes
+/// considered and the number ruled out for each reason.  Anything after the reasons (like what
+/// preemption found) is ignored.
+pub fn parse_scheduling_message(message: &str) -> (Option<u32>, Vec<(u32, String)>) {
+    let (total, rest) = match message.find(" nodes are available: ") {
+        Some(pos) => {
+            let total = message[..pos].rsplit('/').next().and_then(|n| n.trim().parse().ok());
+            (total, &message[pos + " nodes are available: ".len()..])
+        }
+        None => (None, message),
+    };
+    let end = rest.find(". ").unwrap_or_else(|| rest.trim_end_matches('.').len());
+    let reasons = rest[..end]
+        .split(", ")
+        .filter_map(|part| {
+            let mut words = part.trim().splitn(2, ' ');
+            let count = words.next()?.parse().ok()?;
+            Some((count, words.next()?.to_owned()))
+        })
+        .collect();
+    (total, reasons)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Event>,
+}
+
+// Nodes
+#[derive(Debug, Deserialize)]
+pub struct NodeCondition {
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub status: String,
+}
+
+/// Versions of the software on a node, as reported by its kubelet
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct NodeSystemInfo {
+    #[serde(rename = "kubeletVersion")]
+    pub kubelet_version: String,
+    #[serde(rename = "kubeProxyVersion")]
+    pub kube_proxy_version: Option<String>,
+    #[serde(rename = "containerRuntimeVersion")]
+    pub container_runtime_version: Option<String>,
+    #[serde(rename = "osImage")]
+    pub os_image: Option<String>,
+    #[serde(rename = "kernelVersion")]
+    pub kernel_version: Option<String>,
+    pub architecture: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeStatus {
+    pub conditions: Vec<NodeCondition>,
+    pub allocatable: Option<HashMap<String, Quantity>>,
+    #[allow(dead_code)]
+    pub capacity: Option<HashMap<String, Quantity>>,
+    #[serde(rename = "nodeInfo")]
+    pub node_info: Option<NodeSystemInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeSpec {
+    pub unschedulable: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Node {
+    pub metadata: Metadata,
+    pub spec: NodeSpec,
+    pub status: NodeStatus,
+}
+
+impl Node {
+    /// True if the node's Ready condition is True
+    pub fn is_ready(&self) -> bool {
+        self.status
+            .conditions
+            .iter()
+            .any(|c| c.typ == "Ready" && c.status == "True")
+    }
+
+    /// Get the allocatable amount of the specified resource (cpu, memory, pods, ...)
+    pub fn allocatable(&self, resource: &str) -> Option<Quantity> {
+        self.status
+            .allocatable
+            .as_ref()
+            .and_then(|a| a.get(resource).cloned())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NodeList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Node>,
+}
+
+/// How many pods are on a node compared to the most it will run.  A node at its cap rejects new
+/// pods, even if it has plenty of cpu and memory free.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct PodPressure {
+    pub node: String,
+    /// Pods that haven't terminated (succeeded or failed) count against the cap
+    pub pods: u64,
+    pub allocatable: u64,
+}
+
+impl PodPressure {
+    /// How many more pods the node will accept
+    #[allow(dead_code)]
+    pub fn remaining(&self) -> u64 {
+        self.allocatable.saturating_sub(self.pods)
+    }
+
+    #[allow(dead_code)]
+    pub fn at_capacity(&self) -> bool {
+        self.pods >= self.allocatable
+    }
+
+    /// The fraction of the cap in use, from 0 to 1 (or more, if pods were bound before the cap
+    /// was lowered)
+    #[allow(dead_code)]
+    pub fn utilization(&self) -> f64 {
+        if self.allocatable == 0 {
+            1.0
+        } else {
+            self.pods as f64 / self.allocatable as f64
+        }
+    }
+}
+
+/// Total allocatable resources across the ready nodes of a cluster
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct CapacitySummary {
+    pub cpu: Quantity,
+    pub memory: Quantity,
+    pub ready_nodes: usize,
+    pub total_nodes: usize,
+}
+
+/// The kubelet versions running across a cluster's nodes
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct VersionSkew {
+    /// The names of the nodes running each version
+    pub versions: BTreeMap<String, Vec<String>>,
+    /// True if more than one minor version is running (like v1.27 and v1.28), which kubernetes
+    /// only supports while an upgrade is in progress
+    pub skewed: bool,
+}
+
+/// The major and minor parts of a version like "v1.28.3-eks-4f4795d"
+fn minor_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// The nodes of a cluster grouped by whether new pods can be scheduled on them
+#[derive(Debug, Default)]
+pub struct SchedulingHealth {
+    /// Ready and accepting new pods
+    pub schedulable: Vec<String>,
+    /// Ready but cordoned, so running pods stay but no new ones are scheduled
+    pub cordoned: Vec<String>,
+    /// Not ready (whether cordoned or not)
+    pub not_ready: Vec<String>,
+}
+
+impl SchedulingHealth {
+    /// True if every node is ready and schedulable
+    #[allow(dead_code)]
+    pub fn all_schedulable(&self) -> bool {
+        self.cordoned.is_empty() && self.not_ready.is_empty()
+    }
+}
+
+impl NodeList {
+    /// Group the nodes by kubelet version, flagging if more than one minor version is running.
+    /// Nodes that don't report a version are grouped under "unknown".
+    #[allow(dead_code)]
+    pub fn kubelet_version_skew(&self) -> VersionSkew {
+        let mut versions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for node in self.items.iter() {
+            let version = node.status
+                .node_info
+                .as_ref()
+                .map(|info| info.kubelet_version.clone())
+                .unwrap_or_else(|| "unknown".to_owned());
+            versions
+                .entry(version)
+                .or_insert_with(Vec::new)
+                .push(node.metadata.name.clone());
+        }
+        let minors: HashSet<(u32, u32)> =
+            versions.keys().filter_map(|v| minor_version(v)).collect();
+        VersionSkew {
+            skewed: minors.len() > 1,
+            versions,
+        }
+    }
+
+    /// Group the nodes by whether they're ready and schedulable
+    #[allow(dead_code)]
+    pub fn scheduling_health(&self) -> SchedulingHealth {
+        let mut health = SchedulingHealth::default();
+        for node in self.items.iter() {
+            let name = node.metadata.name.clone();
+            if !node.is_ready() {
+                health.not_ready.push(name);
+            } else if node.spec.unschedulable.unwrap_or(false) {
+                health.cordoned.push(name);
+            } else {
+                health.schedulable.push(name);
+            }
+        }
+        health
+    }
+
+    /// Sum the allocatable cpu and memory of all Ready nodes
+    #[allow(dead_code)]
+    pub fn capacity_summary(&self) -> CapacitySummary {
+        let mut summary = CapacitySummary {
+            cpu: Quantity::zero(),
+            memory: Quantity::zero(),
+            ready_nodes: 0,
+            total_nodes: self.items.len(),
+        };
+        for node in self.items.iter().filter(|n| n.is_ready()) {
+            summary.ready_nodes += 1;
+            summary.cpu += node.allocatable("cpu").unwrap_or_default();
+            summary.memory += node.allocatable("memory").unwrap_or_default();
+        }
+        summary
+    }
+}
+
+// Deployments
+/// A requirement in a label selector, like "tier in (frontend, backend)"
+#[derive(Debug, Deserialize)]
+pub struct LabelSelectorRequirement {
+    pub key: String,
+    pub operator: String,
+    pub values: Option<Vec<String>>,
+}
+
+/// The selector a workload uses to find the pods it manages
+#[derive(Debug, Deserialize)]
+pub struct LabelSelector {
+    #[serde(rename = "matchLabels")]
+    pub match_labels: Option<HashMap<String, String>>,
+    #[serde(rename = "matchExpressions")]
+    pub match_expressions: Option<Vec<LabelSelectorRequirement>>,
+}
+
+impl LabelSelector {
+    /// Format as a label selector query string (the form kubectl's -l takes), like
+    /// "app=web,tier in (frontend),!canary".  Returns None for unknown operators, since
+    /// silently dropping a requirement would select too many pods.
+    pub fn to_query_string(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(ref labels) = self.match_labels {
+            let mut labels: Vec<_> = labels.iter().collect();
+            labels.sort();
+            for (k, v) in labels {
+                parts.push(format!("{}={}", k, v));
+            }
+        }
+        if let Some(ref exprs) = self.match_expressions {
+            for expr in exprs.iter() {
+                let values = expr.values
+                    .as_ref()
+                    .map(|vs| vs.join(","))
+                    .unwrap_or_default();
+                let part = match expr.operator.as_str() {
+                    "In" => format!("{} in ({})", expr.key, values),
+                    "NotIn" => format!("{} notin ({})", expr.key, values),
+                    "Exists" => expr.key.clone(),
+                    "DoesNotExist" => format!("!{}", expr.key),
+                    _ => return None,
+                };
+                parts.push(part);
+            }
+        }
+        Some(parts.join(","))
+    }
+}
+
+fn selector_string(selector: &Option<LabelSelector>) -> Option<String> {
+    selector.as_ref().and_then(|s| s.to_query_string())
+}
+
+/// Split a selector into its comma separated requirements, leaving commas inside a set like
+/// "(a,b)" alone
+fn split_requirements(selector: &str) -> Vec<&str> {
+    let mut reqs = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                reqs.push(&selector[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    reqs.push(&selector[start..]);
+    reqs
+}
+
+/// Check one requirement of a selector against a set of labels.  Returns None if the requirement
+/// can't be parsed.
+fn requirement_matches(req: &str, labels: &BTreeMap<String, String>) -> Option<bool> {
+    let req = req.trim();
+    if req.starts_with('!') {
+        let key = req[1..].trim();
+        if key.is_empty() {
+            return None;
+        }
+        return Some(!labels.contains_key(key));
+    }
+    if let Some(open) = req.find('(') {
+        if !req.ends_with(')') {
+            return None;
+        }
+        let mut words = req[..open].split_whitespace();
+        let (key, op) = match (words.next(), words.next(), words.next()) {
+            (Some(key), Some(op), None) => (key, op),
+            _ => return None,
+        };
+        let values: Vec<&str> = req[open + 1..req.len() - 1]
+            .split(',')
+            .map(|v| v.trim())
+            .collect();
+        let has_value = labels
+            .get(key)
+            .map(|v| values.contains(&v.as_str()))
+            .unwrap_or(false);
+        return match op {
+            "in" => Some(has_value),
+            "notin" => Some(!has_value),
+            _ => None,
+        };
+    }
+    let (key, value, equal) = if let Some(pos) = req.find("!=") {
+        (&req[..pos], &req[pos + 2..], false)
+    } else if let Some(pos) = req.find("==") {
+        (&req[..pos], &req[pos + 2..], true)
+    } else if let Some(pos) = req.find('=') {
+        (&req[..pos], &req[pos + 1..], true)
+    } else {
+        // a bare key just has to be present
+        if req.is_empty() || req.contains(char::is_whitespace) {
+            return None;
+        }
+        return Some(labels.contains_key(req));
+    };
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
+    }
+    let matches = labels.get(key).map(|v| v == value.trim()).unwrap_or(false);
+    Some(matches == equal)
+}
+
+/// True if labels satisfy a label selector in the form kubectl's -l takes, like
+/// "app=web,tier in (frontend,backend),!canary", so already fetched objects can be filtered
+/// without another request.  As on the server, "key!=value" and "key notin (..)" match objects
+/// without the label, and an empty selector matches everything.  A selector that can't be parsed
+/// matches nothing.
+#[allow(dead_code)]
+pub fn selector_matches(selector: &str, labels: &BTreeMap<String, String>) -> bool {
+    if selector.trim().is_empty() {
+        return true;
+    }
+    split_requirements(selector)
+        .iter()
+        .all(|req| requirement_matches(req, labels) == Some(true))
+}
+
+/// True if a controller hasn't yet acted on the latest spec of an object, i.e. its status was
+/// computed from an older generation and shouldn't be trusted (e.g. to say a rollout is done).  If
+/// either generation is missing we can't tell, and the status is not considered stale: older
+/// servers and some controllers don't set them.
+pub fn has_stale_status(generation: Option<i64>, observed_generation: Option<i64>) -> bool {
+    match (generation, observed_generation) {
+        (Some(generation), Some(observed)) => observed < generation,
+        _ => false,
+    }
+}
+
+fn replicas_none() -> u32 {
+    0
+}
+
+fn replicas_one() -> u32 {
+    1
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentSpec {
+    #[serde(default = "replicas_one")]
+    pub replicas: u32,
+    pub selector: Option<LabelSelector>,
+    /// While paused, changes to the pod template don't start a rollout
+    pub paused: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentStatus {
+    #[serde(default = "replicas_none")]
+    pub replicas: u32,
+    #[serde(default = "replicas_none", rename = "availableReplicas")]
+    pub available: u32,
+    #[serde(default = "replicas_none", rename = "updatedReplicas")]
+    pub updated: u32,
+    #[serde(default = "replicas_none", rename = "readyReplicas")]
+    pub ready: u32,
+    #[serde(rename = "observedGeneration")]
+    pub observed_generation: Option<i64>,
+    pub conditions: Option<Vec<Condition>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Deployment {
+    pub metadata: Metadata,
+    pub spec: DeploymentSpec,
+    pub status: DeploymentStatus,
+}
+
+/// Where a deployment is in rolling out its latest spec
+#[derive(Debug, PartialEq)]
+pub enum RolloutStatus {
+    /// The deployment controller hasn't yet seen the latest spec
+    Pending,
+    /// The rollout is underway, with a message describing what it's waiting for
+    Progressing(String),
+    Complete,
+    /// The rollout has exceeded its progress deadline
+    Stalled(String),
+}
+
+impl RolloutStatus {
+    /// True if the rollout won't make any more progress (either complete or stalled)
+    pub fn is_finished(&self) -> bool {
+        match *self {
+            RolloutStatus::Complete | RolloutStatus::Stalled(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for RolloutStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RolloutStatus::Pending => {
+                write!(f, "Waiting for deployment spec update to be observed")
+            }
+            RolloutStatus::Progressing(ref msg) => write!(f, "{}", msg),
+            RolloutStatus::Complete => write!(f, "{}", Green.paint("Rollout complete")),
+            RolloutStatus::Stalled(ref msg) => write!(f, "{}: {}", Red.paint("Stalled"), msg),
+        }
+    }
+}
+
+impl Deployment {
+    #[allow(dead_code)]
+    pub fn is_paused(&self) -> bool {
+        self.spec.paused.unwrap_or(false)
+    }
+
+    /// True if the deployment controller hasn't caught up with the latest spec
+    pub fn has_stale_status(&self) -> bool {
+        has_stale_status(self.metadata.generation, self.status.observed_generation)
+    }
+
+    /// The label selector for the pods this deployment manages, suitable for list_with_selector
+    #[allow(dead_code)]
+    pub fn selector_string(&self) -> Option<String> {
+        selector_string(&self.spec.selector)
+    }
+
+    /// Compute the rollout status of this deployment.  This follows the same logic as `kubectl
+    /// rollout status`
+    pub fn rollout_status(&self) -> RolloutStatus {
+        if self.has_stale_status() {
+            return RolloutStatus::Pending;
+        }
+        if let Some(ref conditions) = self.status.conditions {
+            for cond in conditions.iter().filter(|c| c.typ == "Progressing") {
+                if cond.reason.as_ref().map_or(false, |r| r == "ProgressDeadlineExceeded") {
+                    return RolloutStatus::Stalled(format!(
+                        "deployment {} exceeded its progress deadline",
+                        self.metadata.name
+                    ));
+                }
+            }
+        }
+        if self.status.updated < self.spec.replicas {
+            RolloutStatus::Progressing(format!(
+                "{} of {} new replicas have been updated",
+                self.status.updated, self.spec.replicas
+            ))
+        } else if self.status.replicas > self.status.updated {
+            RolloutStatus::Progressing(format!(
+                "{} old replicas are pending termination",
+                self.status.replicas - self.status.updated
+            ))
+        } else if self.status.available < self.status.updated {
+            RolloutStatus::Progressing(format!(
+                "{} of {} updated replicas are available",
+                self.status.available, self.status.updated
+            ))
+        } else {
+            RolloutStatus::Complete
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeploymentList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Deployment>,
+}
+
+/// A list of any kind of object
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ObjectList<T> {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub metadata: Option<ListMetadata>,
+    pub items: Vec<T>,
+}
+
+/// A column of a server side Table
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct TableColumnDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub typ: String,
+    #[serde(default)]
+    pub format: String,
+    pub description: Option<String>,
+    /// Columns with a priority above 0 are only shown in wide output
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct TableRow {
+    pub cells: Vec<Value>,
+    pub object: Option<Value>,
+}
+
+/// A list rendered by the server (a meta.k8s.io/v1 Table), with the columns kubectl would show.
+/// This works for any kind, including custom resources with printer columns.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Table {
+    pub metadata: Option<ListMetadata>,
+    #[serde(rename = "columnDefinitions")]
+    pub column_definitions: Vec<TableColumnDefinition>,
+    #[serde(default)]
+    pub rows: Vec<TableRow>,
+}
+
+/// How a workload's ready replicas compare to the number it wants
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct WorkloadScale {
+    pub kind: &'static str,
+    pub namespace: String,
+    pub name: String,
+    pub desired: u32,
+    pub ready: u32,
+    /// Ready matches desired, and the controller has seen the latest spec (otherwise the counts
+    /// may be for an older desired scale)
+    pub converged: bool,
+}
+
+impl WorkloadScale {
+    fn new(
+        kind: &'static str,
+        metadata: Metadata,
+        desired: u32,
+        ready: u32,
+        stale: bool,
+    ) -> WorkloadScale {
+        WorkloadScale {
+            kind: kind,
+            namespace: metadata.namespace.unwrap_or_default(),
+            name: metadata.name,
+            desired: desired,
+            ready: ready,
+            converged: ready == desired && !stale,
+        }
+    }
+}
+
+/// An overview of what's in a namespace
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct NamespaceSummary {
+    pub namespace: String,
+    /// Number of pods in each phase (Running, Pending, ...)
+    pub pods_by_phase: HashMap<String, usize>,
+    /// These are None if the kind couldn't be listed, e.g. because the api group isn't served
+    pub deployments: Option<usize>,
+    pub services: Option<usize>,
+    pub persistent_volume_claims: Option<usize>,
+    /// Total cpu and memory requested by pods that haven't finished
+    pub cpu_requests: Quantity,
+    pub memory_requests: Quantity,
+}
+
+impl NamespaceSummary {
+    #[allow(dead_code)]
+    pub fn pod_count(&self) -> usize {
+        self.pods_by_phase.values().sum()
+    }
+}
+
+// Services
+fn tcp_str() -> String {
+    "TCP".to_owned()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServicePort {
+    pub name: Option<String>,
+    #[serde(rename = "nodePort")]
+    pub node_port: Option<u32>,
+    pub port: u32,
+    #[serde(default = "tcp_str")]
+    pub protocol: String,
+    #[serde(rename = "targetPort")]
+    pub target_pod: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceSpec {
+    #[serde(rename = "clusterIP")]
+    pub cluster_ip: Option<String>,
+    #[serde(rename = "externalIPs")]
+    pub external_ips: Option<Vec<String>>,
+    pub ports: Option<Vec<ServicePort>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Service {
+    pub metadata: Metadata,
+    pub spec: ServiceSpec,
+    pub status: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ServiceList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Service>,
+}
+
+// Endpoint slices
+#[derive(Debug, Deserialize)]
+pub struct EndpointConditions {
+    pub ready: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EndpointTargetRef {
+    pub kind: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Endpoint {
+    pub addresses: Vec<String>,
+    pub conditions: Option<EndpointConditions>,
+    #[serde(rename = "targetRef")]
+    pub target_ref: Option<EndpointTargetRef>,
+}
+
+impl Endpoint {
+    /// An endpoint with unknown readiness should be treated as ready
+    pub fn is_ready(&self) -> bool {
+        self.conditions
+            .as_ref()
+            .and_then(|c| c.ready)
+            .unwrap_or(true)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct EndpointSlice {
+    pub metadata: Metadata,
+    pub endpoints: Option<Vec<Endpoint>>,
+}
+
+// Ingresses
+#[derive(Debug, Deserialize)]
+pub struct ServiceBackendPort {
+    pub name: Option<String>,
+    pub number: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngressServiceBackend {
+    pub name: String,
+    pub port: Option<ServiceBackendPort>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngressBackend {
+    /// None if the backend is a resource rather than a service
+    pub service: Option<IngressServiceBackend>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpIngressPath {
+    pub path: Option<String>,
+    pub backend: IngressBackend,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HttpIngressRuleValue {
+    pub paths: Vec<HttpIngressPath>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngressRule {
+    pub host: Option<String>,
+    pub http: Option<HttpIngressRuleValue>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IngressSpec {
+    #[serde(rename = "defaultBackend")]
+    pub default_backend: Option<IngressBackend>,
+    pub rules: Option<Vec<IngressRule>>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Ingress {
+    pub metadata: Metadata,
+    pub spec: IngressSpec,
+}
+
+/// An address backing a service, and the pod it belongs to
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct EndpointTrace {
+    pub address: String,
+    pub pod: Option<String>,
+    pub ready: bool,
+}
+
+/// One route through an ingress, and where it ends up
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct IngressRoute {
+    /// None matches any host
+    pub host: Option<String>,
+    /// None for the default backend
+    pub path: Option<String>,
+    /// None if the backend isn't a service
+    pub service: Option<String>,
+    /// The service port, as a number or a name
+    pub port: Option<String>,
+    /// False if the service doesn't exist
+    pub service_found: bool,
+    pub endpoints: Vec<EndpointTrace>,
+}
+
+impl IngressRoute {
+    /// Can this route serve traffic, i.e. does it lead to at least one ready endpoint
+    #[allow(dead_code)]
+    pub fn is_routable(&self) -> bool {
+        self.endpoints.iter().any(|e| e.ready)
+    }
+}
+
+// Namespaces
+#[derive(Debug, Deserialize)]
+pub struct NamespaceStatus {
+    pub phase: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Namespace {
+    pub metadata: Metadata,
+    pub status: NamespaceStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NamespaceList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Namespace>,
+}
+
+// ReplicaSets, StatefulSets, DaemonSets.  Only the selector is parsed for these so far.
+#[derive(Debug, Deserialize)]
+pub struct WorkloadSpec {
+    pub selector: Option<LabelSelector>,
+    /// Not set for DaemonSets
+    pub replicas: Option<u32>,
+}
+
+/// Status counts shared by ReplicaSets and StatefulSets
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct WorkloadStatus {
+    #[serde(default = "replicas_none")]
+    pub replicas: u32,
+    #[serde(default = "replicas_none", rename = "readyReplicas")]
+    pub ready: u32,
+    #[serde(rename = "observedGeneration")]
+    pub observed_generation: Option<i64>,
+}
+
+fn workload_status_stale(metadata: &Metadata, status: &Option<WorkloadStatus>) -> bool {
+    has_stale_status(
+        metadata.generation,
+        status.as_ref().and_then(|s| s.observed_generation),
+    )
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplicaSet {
+    pub metadata: Metadata,
+    pub spec: WorkloadSpec,
+    pub status: Option<WorkloadStatus>,
+}
+
+impl ReplicaSet {
+    /// True if the replica set controller hasn't caught up with the latest spec
+    pub fn has_stale_status(&self) -> bool {
+        workload_status_stale(&self.metadata, &self.status)
+    }
+
+    #[allow(dead_code)]
+    pub fn selector_string(&self) -> Option<String> {
+        selector_string(&self.spec.selector)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReplicaSetList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StatefulSet {
+    pub metadata: Metadata,
+    pub spec: WorkloadSpec,
+    pub status: Option<WorkloadStatus>,
+}
+
+impl StatefulSet {
+    /// True if the stateful set controller hasn't caught up with the latest spec
+    pub fn has_stale_status(&self) -> bool {
+        workload_status_stale(&self.metadata, &self.status)
+    }
+
+    #[allow(dead_code)]
+    pub fn selector_string(&self) -> Option<String> {
+        selector_string(&self.spec.selector)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct DaemonSet {
+    pub metadata: Metadata,
+    pub spec: WorkloadSpec,
+}
+
+impl DaemonSet {
+    #[allow(dead_code)]
+    pub fn selector_string(&self) -> Option<String> {
+        selector_string(&self.spec.selector)
+    }
+}
+
+// Jobs
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct JobStatus {
+    pub conditions: Option<Vec<Condition>>,
+    #[serde(default)]
+    pub active: u32,
+    #[serde(default)]
+    pub succeeded: u32,
+    #[serde(default)]
+    pub failed: u32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Job {
+    pub metadata: Metadata,
+    pub status: Option<JobStatus>,
+}
+
+/// How a finished job ended
+#[derive(Debug, PartialEq)]
+pub struct JobOutcome {
+    /// True if the job completed, false if it failed
+    pub complete: bool,
+    /// Number of pods that succeeded
+    pub succeeded: u32,
+    /// Number of pods that failed
+    pub failed: u32,
+    /// Why the job failed (e.g. "BackoffLimitExceeded"), if it did
+    pub reason: Option<String>,
+    pub message: Option<String>,
+}
+
+impl Job {
+    /// How the job ended, or None if it's still running
+    pub fn outcome(&self) -> Option<JobOutcome> {
+        let status = self.status.as_ref()?;
+        let finished = status.conditions.as_ref().and_then(|conds| {
+            conds
+                .iter()
+                .find(|c| (c.typ == "Complete" || c.typ == "Failed") && c.status == "True")
+        })?;
+        Some(JobOutcome {
+            complete: finished.typ == "Complete",
+            succeeded: status.succeeded,
+            failed: status.failed,
+            reason: finished.reason.clone(),
+            message: finished.message.clone(),
+        })
+    }
+}
+
+// ConfigMaps
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ConfigMap {
+    pub metadata: Metadata,
+    pub data: Option<HashMap<String, String>>,
+    /// Values here are base64 encoded
+    #[serde(rename = "binaryData")]
+    pub binary_data: Option<HashMap<String, String>>,
+}
+
+// Admission webhooks
+/// The in-cluster service a webhook is sent to
+#[derive(Debug, Deserialize)]
+pub struct WebhookServiceReference {
+    pub namespace: String,
+    pub name: String,
+    pub path: Option<String>,
+    pub port: Option<u16>,
+}
+
+/// Where a webhook is sent: a url, or a service
+#[derive(Debug, Deserialize)]
+pub struct WebhookClientConfig {
+    pub url: Option<String>,
+    pub service: Option<WebhookServiceReference>,
+}
+
+/// Which requests a webhook is called for, like CREATE and UPDATE of apps/v1 deployments
+#[derive(Debug, Deserialize)]
+pub struct RuleWithOperations {
+    #[serde(default)]
+    pub operations: Vec<String>,
+    #[serde(rename = "apiGroups", default)]
+    pub api_groups: Vec<String>,
+    #[serde(rename = "apiVersions", default)]
+    pub api_versions: Vec<String>,
+    #[serde(default)]
+    pub resources: Vec<String>,
+}
+
+impl fmt::Display for RuleWithOperations {
+    /// Like "CREATE,UPDATE apps/v1 deployments"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups: Vec<&str> = self.api_groups
+            .iter()
+            .map(|g| if g.is_empty() { "core" } else { g.as_str() })
+            .collect();
+        write!(
+            f,
+            "{} {}/{} {}",
+            self.operations.join(","),
+            groups.join(","),
+            self.api_versions.join(","),
+            self.resources.join(",")
+        )
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Webhook {
+    pub name: String,
+    #[serde(rename = "clientConfig")]
+    pub client_config: WebhookClientConfig,
+    #[serde(default)]
+    pub rules: Vec<RuleWithOperations>,
+    /// "Fail" (the default) or "Ignore"
+    #[serde(rename = "failurePolicy")]
+    pub failure_policy: Option<String>,
+    #[serde(rename = "timeoutSeconds")]
+    pub timeout_seconds: Option<u32>,
+    #[serde(rename = "namespaceSelector")]
+    pub namespace_selector: Option<LabelSelector>,
+}
+
+impl Webhook {
+    /// True if requests are rejected when the webhook can't be reached, which is how a webhook
+    /// whose service is down breaks creates and updates
+    #[allow(dead_code)]
+    pub fn fails_closed(&self) -> bool {
+        self.failure_policy
+            .as_ref()
+            .map(|p| p != "Ignore")
+            .unwrap_or(true)
+    }
+
+    /// Where the webhook is sent, like "svc/namespace/name:443/validate" or its url
+    #[allow(dead_code)]
+    pub fn target(&self) -> String {
+        match (&self.client_config.service, &self.client_config.url) {
+            (&Some(ref svc), _) => format!(
+                "svc/{}/{}:{}{}",
+                svc.namespace,
+                svc.name,
+                svc.port.unwrap_or(443),
+                svc.path.as_ref().map(|p| p.as_str()).unwrap_or("")
+            ),
+            (&None, &Some(ref url)) => url.clone(),
+            (&None, &None) => "<none>".to_owned(),
+        }
+    }
+}
+
+/// A ValidatingWebhookConfiguration or MutatingWebhookConfiguration
+#[derive(Debug, Deserialize)]
+pub struct WebhookConfiguration {
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// A webhook, and the configuration it's from
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct AdmissionWebhook {
+    /// True for a mutating webhook, false for a validating one
+    pub mutating: bool,
+    pub configuration: String,
+    pub webhook: Webhook,
+}
+
+// RBAC
+/// Something a role allows.  A rule applies to either resources or non-resource urls (like
+/// /healthz), not both.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PolicyRule {
+    #[serde(default)]
+    pub verbs: Vec<String>,
+    #[serde(rename = "apiGroups", default)]
+    pub api_groups: Vec<String>,
+    #[serde(default)]
+    pub resources: Vec<String>,
+    /// If not empty, the rule only applies to objects with these names
+    #[serde(rename = "resourceNames", default)]
+    pub resource_names: Vec<String>,
+    #[serde(rename = "nonResourceURLs", default)]
+    pub non_resource_urls: Vec<String>,
+}
+
+impl fmt::Display for PolicyRule {
+    /// Like "get,list apps/deployments,replicasets" or "get /healthz"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ", self.verbs.join(","))?;
+        if !self.non_resource_urls.is_empty() {
+            return write!(f, "{}", self.non_resource_urls.join(","));
+        }
+        let groups: Vec<&str> = self.api_groups
+            .iter()
+            .map(|g| if g.is_empty() { "core" } else { g.as_str() })
+            .collect();
+        write!(f, "{}/{}", groups.join(","), self.resources.join(","))?;
+        if !self.resource_names.is_empty() {
+            write!(f, " named {}", self.resource_names.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// A Role or ClusterRole
+#[derive(Debug, Deserialize)]
+pub struct Role {
+    pub metadata: Metadata,
+    /// For aggregated cluster roles these are filled in by the controller
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RoleRef {
+    /// "Role" or "ClusterRole"
+    pub kind: String,
+    pub name: String,
+}
+
+/// Who a binding grants its role to
+#[derive(Debug, Deserialize)]
+pub struct Subject {
+    /// "User", "Group" or "ServiceAccount"
+    pub kind: String,
+    pub name: String,
+    /// For service accounts
+    pub namespace: Option<String>,
+}
+
+/// A RoleBinding or ClusterRoleBinding
+#[derive(Debug, Deserialize)]
+pub struct RoleBinding {
+    pub metadata: Metadata,
+    #[serde(rename = "roleRef")]
+    pub role_ref: RoleRef,
+    #[serde(default)]
+    pub subjects: Vec<Subject>,
+}
+
+/// A rule granted to a subject, and where it comes from
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct Permission {
+    /// The namespace the rule applies in, None if it applies across the cluster
+    pub namespace: Option<String>,
+    /// The binding that grants it, like "RoleBinding ns/name" or "ClusterRoleBinding name"
+    pub binding: String,
+    /// The role the rule is from, like "Role ns/name" or "ClusterRole name"
+    pub role: String,
+    pub rule: PolicyRule,
+}
+
+/// Everything a subject is allowed to do (see Kluster::rbac_for)
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct SubjectPermissions {
+    pub permissions: Vec<Permission>,
+    /// Bindings that grant the subject a role that doesn't exist, like
+    /// "RoleBinding ns/name refers to missing Role ns/role"
+    pub skipped: Vec<String>,
+}
+
+/// The users or groups that subject_kind/subject_name is known to the api server as, as
+/// (kind, name, namespace) triples that binding subjects are compared with.  A service account
+/// (named "namespace/name" or "namespace:name") is also the user
+/// system:serviceaccount:<namespace>:<name>, and in the groups of all service accounts and of
+/// those in its namespace.  Everyone is in system:authenticated.  Other groups a user is in
+/// come from how they authenticate, so can't be known here.
+fn rbac_identities(
+    subject_kind: &str,
+    subject_name: &str,
+) -> Result<Vec<(&'static str, String, Option<String>)>, KubeError> {
+    let mut ids = Vec::new();
+    match subject_kind {
+        "ServiceAccount" | "serviceaccount" | "sa" => {
+            let mut parts = subject_name.splitn(2, |c| c == '/' || c == ':');
+            let (ns, name) = match (parts.next(), parts.next()) {
+                (Some(ns), Some(name)) => (ns, name),
+                _ => {
+                    return Err(KubeError::ParseErr(format!(
+                        "Service accounts must be given as namespace/name, not {}",
+                        subject_name
+                    )))
+                }
+            };
+            ids.push(("ServiceAccount", name.to_owned(), Some(ns.to_owned())));
+            ids.push(("User", format!("system:serviceaccount:{}:{}", ns, name), None));
+            ids.push(("Group", "system:serviceaccounts".to_owned(), None));
+            ids.push(("Group", format!("system:serviceaccounts:{}", ns), None));
+        }
+        "User" | "user" => ids.push(("User", subject_name.to_owned(), None)),
+        "Group" | "group" => ids.push(("Group", subject_name.to_owned(), None)),
+        _ => {
+            return Err(KubeError::ParseErr(format!(
+                "Unknown subject kind {}, expected User, Group or ServiceAccount",
+                subject_kind
+            )))
+        }
+    }
+    ids.push(("Group", "system:authenticated".to_owned(), None));
+    Ok(ids)
+}
+
+/// Does a binding's subject refer to any of ids (see rbac_identities).  A service account
+/// subject without a namespace is in the binding's namespace.
+fn subject_is(
+    subject: &Subject,
+    binding_ns: Option<&str>,
+    ids: &[(&str, String, Option<String>)],
+) -> bool {
+    let subject_ns = subject.namespace.as_ref().map(|ns| ns.as_str()).or(binding_ns);
+    ids.iter().any(|&(kind, ref name, ref ns)| {
+        subject.kind == kind && subject.name == *name
+            && (kind != "ServiceAccount" || ns.as_ref().map(|ns| ns.as_str()) == subject_ns)
+    })
+}
+
+// Storage classes
+/// Annotations marking the storage class used by claims that don't name one.  The beta one is
+/// still honored.
+const DEFAULT_CLASS_ANNOTATIONS: &[&str] = &[
+    "storageclass.kubernetes.io/is-default-class",
+    "storageclass.beta.kubernetes.io/is-default-class",
+];
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct StorageClass {
+    pub metadata: Metadata,
+    pub provisioner: String,
+    #[serde(rename = "reclaimPolicy")]
+    pub reclaim_policy: Option<String>,
+    /// "Immediate" or "WaitForFirstConsumer"
+    #[serde(rename = "volumeBindingMode")]
+    pub volume_binding_mode: Option<String>,
+    #[serde(rename = "allowVolumeExpansion")]
+    pub allow_volume_expansion: Option<bool>,
+}
+
+/// The storage class claims that don't name one get
+#[derive(Debug)]
+pub enum DefaultStorageClass {
+    /// No class is the default, so claims without a class won't be provisioned
+    None,
+    One(StorageClass),
+    /// The names of the classes marked as the default.  This is a misconfiguration: older
+    /// clusters reject claims without a class, newer ones pick the newest default.
+    Multiple(Vec<String>),
+}
+
+impl StorageClass {
+    /// True if this is annotated as the default class
+    pub fn is_default(&self) -> bool {
+        self.metadata
+            .annotations
+            .as_ref()
+            .map(|a| {
+                DEFAULT_CLASS_ANNOTATIONS
+                    .iter()
+                    .any(|key| a.get(*key).and_then(|v| v.as_str()) == Some("true"))
+            })
+            .unwrap_or(false)
+    }
+}
+
+// Secrets
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct Secret {
+    pub metadata: Metadata,
+    /// Values here are base64 encoded
+    pub data: Option<HashMap<String, String>>,
+}
+
+/// Turn a config map key into something safe to use as a file name.  Valid keys can't contain
+/// slashes, but they can be "." or "..", and we don't trust the server to have validated.
+fn key_to_filename(key: &str) -> String {
+    let name: String = key.chars()
+        .map(|c| match c {
+            '/' | '\\' | '\0' => '_',
+            c => c,
+        })
+        .collect();
+    if name.is_empty() || name.chars().all(|c| c == '.') {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigMapList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Value>,
+}
+
+// Secrets
+#[derive(Debug, Deserialize)]
+pub struct SecretList {
+    #[serde(flatten)]
+    pub type_meta: TypeMeta,
+    pub items: Vec<Value>,
+}
+
+// Watches
+
+/// An event received while watching some resource
+#[derive(Debug)]
+pub enum WatchEvent<T> {
+    Added(T),
+    Modified(T),
+    Deleted(T),
+    /// The server sent an error, the value is a Status object
+    Error(Value),
+}
+
+#[derive(Debug, Deserialize)]
+struct RawWatchEvent {
+    #[serde(rename = "type")]
+    typ: String,
+    object: Value,
+}
+
+impl<T> WatchEvent<T>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    /// Decode one line of a watch stream
+    fn from_line(line: &str) -> Result<WatchEvent<T>, KubeError> {
+        let raw: RawWatchEvent = serde_json::from_str(line)?;
+        match raw.typ.as_str() {
+            "ADDED" => Ok(WatchEvent::Added(serde_json::from_value(raw.object)?)),
+            "MODIFIED" => Ok(WatchEvent::Modified(serde_json::from_value(raw.object)?)),
+            "DELETED" => Ok(WatchEvent::Deleted(serde_json::from_value(raw.object)?)),
+            "ERROR" => Ok(WatchEvent::Error(raw.object)),
+            t => Err(KubeError::ParseErr(format!("Unknown watch event type: {}", t))),
+        }
+    }
+}
+
+/// Something that reacts to changes in a watched collection (see Kluster::register_watcher),
+/// as an alternative to matching on WatchEvents.  Each method returns false to stop watching.
+/// By default events are ignored.
+pub trait Watcher<T> {
+    fn on_added(&mut self, _obj: &T) -> bool {
+        true
+    }
+
+    fn on_modified(&mut self, _obj: &T) -> bool {
+        true
+    }
+
+    /// Called with the last state of the object
+    fn on_deleted(&mut self, _obj: &T) -> bool {
+        true
+    }
+
+    /// Called with the Status the server sent
+    fn on_error(&mut self, _status: &Value) -> bool {
+        true
+    }
+}
+
+/// Pass an event to the matching method of watcher, returning whether to keep watching
+fn dispatch_event<T>(watcher: &mut dyn Watcher<T>, event: &WatchEvent<T>) -> bool {
+    match *event {
+        WatchEvent::Added(ref obj) => watcher.on_added(obj),
+        WatchEvent::Modified(ref obj) => watcher.on_modified(obj),
+        WatchEvent::Deleted(ref obj) => watcher.on_deleted(obj),
+        WatchEvent::Error(ref status) => watcher.on_error(status),
+    }
+}
+
+/// How long a watch can go without receiving anything before we consider it finished.
+const WATCH_READ_TIMEOUT: u64 = 60;
+
+/// A local copy of the objects in a watched collection, kept up to date by applying watch events
+#[derive(Debug, Default)]
+pub struct Reflector {
+    objects: HashMap<String, Value>,
+}
+
+/// The key objects are stored under in a Reflector: namespace/name, or just name for cluster
+/// scoped objects
+pub fn object_key(obj: &Value) -> String {
+    let name = ::values::val_str("/metadata/name", obj, "");
+    match ::values::val_str_opt("/metadata/namespace", obj) {
+        Some(ns) => format!("{}/{}", ns, name),
+        None => name.into_owned(),
+    }
+}
+
+/// An object's resourceVersion as a number.  These are meant to be opaque, but the api server's
+/// are etcd revisions, so comparing them is how an out of order event can be spotted.  None if
+/// it's missing or not a number.
+fn resource_version(obj: &Value) -> Option<u64> {
+    ::values::val_str_opt("/metadata/resourceVersion", obj).and_then(|rv| rv.parse().ok())
+}
+
+impl Reflector {
+    pub fn new() -> Reflector {
+        Reflector::default()
+    }
+
+    /// Update the store from an event, returning the key of the object the event was for and the
+    /// version of it we had before (if any).  A Modified event whose resourceVersion isn't newer
+    /// than the stored one is not applied, and is a WatchInconsistency error: it usually means
+    /// something between us and the server is replaying old events.  Added events can repeat
+    /// after a relist, so they aren't checked.
+    pub fn apply(
+        &mut self,
+        event: WatchEvent<Value>,
+    ) -> Result<Option<(String, Option<Value>)>, KubeError> {
+        match event {
+            WatchEvent::Added(obj) => {
+                let key = object_key(&obj);
+                let previous = self.objects.insert(key.clone(), obj);
+                Ok(Some((key, previous)))
+            }
+            WatchEvent::Modified(obj) => {
+                let key = object_key(&obj);
+                if let Some(stored) = self.objects.get(&key) {
+                    let (old, new) = (resource_version(stored), resource_version(&obj));
+                    if let (Some(old), Some(new)) = (old, new) {
+                        if new <= old {
+                            return Err(KubeError::Kube(KubeErrNo::WatchInconsistency(format!(
+                                "{} modified at resourceVersion {} after {}",
+                                key, new, old
+                            ))));
+                        }
+                    }
+                }
+                let previous = self.objects.insert(key.clone(), obj);
+                Ok(Some((key, previous)))
+            }
+            WatchEvent::Deleted(obj) => {
+                let key = object_key(&obj);
+                let previous = self.objects.remove(&key);
+                Ok(Some((key, previous)))
+            }
+            WatchEvent::Error(_) => Ok(None),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.objects.get(key)
+    }
+
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// The most recent events seen by a watch, up to a fixed number.  Clones share the same buffer,
+/// so one can be filled by a watch on another thread while a clone is read for display.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct EventRing {
+    capacity: usize,
+    events: Arc<Mutex<VecDeque<Event>>>,
+}
+
+impl EventRing {
+    #[allow(dead_code)]
+    pub fn new(capacity: usize) -> EventRing {
+        EventRing {
+            capacity: capacity,
+            events: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    /// Add an event, dropping the oldest one if the ring is full
+    pub fn push(&self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.lock().unwrap();
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// A copy of the events in the ring, newest first
+    #[allow(dead_code)]
+    pub fn snapshot(&self) -> Vec<Event> {
+        self.events.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.lock().unwrap().len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+const MERGE_PATCH_TYPE: &str = "application/merge-patch+json";
+
+/// Asks the server to render a list as a Table rather than returning the objects
+const TABLE_ACCEPT_TYPE: &str = "application/json;as=Table;v=v1;g=meta.k8s.io";
+
+/// How long a burst of changes has to die down for before a watched table is refreshed
+const TABLE_REFRESH_DEBOUNCE_MS: u64 = 250;
+
+/// Refresh a watched table at least this often while changes keep arriving
+const TABLE_REFRESH_MAX_DELAY_MS: u64 = 2000;
+
+/// How long each of the watches behind watch_table lasts.  This bounds how long watch_table takes
+/// to return once asked to stop, since the watching thread is blocked until its watch ends.
+const TABLE_WATCH_SECS: u64 = 5;
+
+/// How often the watching thread behind watch_table checks whether it's been asked to stop while
+/// backing off
+const TABLE_STOP_CHECK_MS: u64 = 100;
+
+/// Sleep for duration, waking up every TABLE_STOP_CHECK_MS to give up early if stop is set.
+/// Returns false if it was.
+fn sleep_unless_stopped(duration: Duration, stop: &AtomicBool) -> bool {
+    let start = Instant::now();
+    let step = Duration::from_millis(TABLE_STOP_CHECK_MS);
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return false;
+        }
+        let slept = start.elapsed();
+        if slept >= duration {
+            return true;
+        }
+        thread::sleep(cmp::min(step, duration - slept));
+    }
+}
+
+/// Wait for a change to arrive on changes, then for the burst it's part of to end (nothing new
+/// for TABLE_REFRESH_DEBOUNCE_MS, or TABLE_REFRESH_MAX_DELAY_MS since the first one), so a burst
+/// of changes causes one refresh.  Returns false if the sender went away before any change came.
+fn wait_for_changes(changes: &mpsc::Receiver<()>) -> bool {
+    if changes.recv().is_err() {
+        return false;
+    }
+    let first = Instant::now();
+    let max_delay = Duration::from_millis(TABLE_REFRESH_MAX_DELAY_MS);
+    loop {
+        let waited = first.elapsed();
+        if waited >= max_delay {
+            return true;
+        }
+        let wait = cmp::min(Duration::from_millis(TABLE_REFRESH_DEBOUNCE_MS), max_delay - waited);
+        match changes.recv_timeout(wait) {
+            Ok(()) => {}
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => return true,
+        }
+    }
+}
+
+/// What happened when editing an object
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum EditOutcome {
+    /// The object was saved without changes, so nothing was sent
+    Unchanged,
+    /// The user saved an empty file
+    Cancelled,
+    /// The changes were applied, giving this updated object
+    Applied(Value),
+}
+
+/// How long to wait for an eviction to be allowed, or for an evicted pod to be replaced
+const EVICTION_TIMEOUT: u64 = 300;
+/// How often to check on progress while evicting
+const EVICTION_POLL: u64 = 2;
+
+/// How many requests fan out helpers (like discovery_health) make at once, unless changed with
+/// with_max_concurrency
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+/// The default limit on response size.  Objects are usually a few KiB of json (pods with many
+/// containers or big annotations are maybe 20KiB), so this fits an unpaginated list of several
+/// thousand, and anything bigger should be fetched in pages (with limit=).
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Initial and maximum number of seconds to wait between attempts to re-establish a watch
+const WATCH_MIN_BACKOFF: u64 = 1;
+const WATCH_MAX_BACKOFF: u64 = 32;
+
+/// Add up to 50% random jitter to a backoff, so many clients don't reconnect in lockstep
+fn with_jitter(backoff_secs: u64) -> Duration {
+    let mut buf = [0u8; 2];
+    let jitter_frac = match SystemRandom::new().fill(&mut buf) {
+        Ok(_) => {
+            f64::from(u16::from(buf[0]) << 8 | u16::from(buf[1])) / f64::from(u16::max_value())
+        }
+        Err(_) => 0.5,
+    };
+    let millis = backoff_secs * 1000;
+    Duration::from_millis(millis + (millis as f64 * 0.5 * jitter_frac) as u64)
+}
+
+/// Is e worth retrying a watch after: the connection failing or timing out, or the server being
+/// unavailable, might be fixed by the time we reconnect, but the server rejecting the request
+/// won't be
+fn is_retryable_watch_error(e: &KubeError) -> bool {
+    match *e {
+        KubeError::Io(_)
+        | KubeError::HyperErr(_)
+        | KubeError::Kube(KubeErrNo::Timeout)
+        | KubeError::Kube(KubeErrNo::Unavailable(..)) => true,
+        _ => false,
+    }
+}
+
+/// Convert an event carrying a Value into one carrying a T
+fn typed_event<T>(event: WatchEvent<Value>) -> Result<WatchEvent<T>, KubeError>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    Ok(match event {
+        WatchEvent::Added(v) => WatchEvent::Added(serde_json::from_value(v)?),
+        WatchEvent::Modified(v) => WatchEvent::Modified(serde_json::from_value(v)?),
+        WatchEvent::Deleted(v) => WatchEvent::Deleted(serde_json::from_value(v)?),
+        WatchEvent::Error(v) => WatchEvent::Error(v),
+    })
+}
+
+/// Add a query parameter (of the form key=value) to a path which may or may not already have a
+/// query string
+fn add_query_param(path: &str, param: &str) -> String {
+    if path.contains('?') {
+        format!("{}&{}", path, param)
+    } else {
+        format!("{}?{}", path, param)
+    }
+}
+
+/// The path to a subresource (like "status" or "scale") of the object at path, keeping any query
+/// string at the end
+pub fn subresource_path(path: &str, subresource: &str) -> String {
+    let mut parts = path.splitn(2, '?');
+    let object = parts.next().unwrap_or("").trim_end_matches('/');
+    let subresource = subresource.trim_matches('/');
+    match parts.next() {
+        Some(query) => format!("{}/{}?{}", object, subresource, query),
+        None => format!("{}/{}", object, subresource),
+    }
+}
+
+/// The path to reach path on the kubelet of node through the api server's proxy
+pub fn node_proxy_path(node: &str, path: &str) -> String {
+    format!("/api/v1/nodes/{}/proxy/{}", node, path.trim_start_matches('/'))
+}
+
+/// The path to the collection the object at path is in, which is where it would be created,
+/// keeping any query string at the end
+fn collection_path(path: &str) -> String {
+    let mut parts = path.splitn(2, '?');
+    let object = parts.next().unwrap_or("").trim_end_matches('/');
+    let collection = match object.rfind('/') {
+        Some(slash) => &object[..slash],
+        None => object,
+    };
+    match parts.next() {
+        Some(query) => format!("{}?{}", collection, query),
+        None => collection.to_owned(),
+    }
+}
+
+/// Guess if path is for a collection (like /api/v1/namespaces/default/pods) rather than a single
+/// object or a subresource.  Watches and log follows are streams, and so never count.
+fn is_collection_path(path: &str) -> bool {
+    let mut parts = path.splitn(2, '?');
+    let path = parts.next().unwrap_or("");
+    if let Some(query) = parts.next() {
+        if query
+            .split('&')
+            .any(|p| p == "watch=true" || p == "watch=1" || p == "follow=true")
+        {
+            return false;
+        }
+    }
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    // skip /api/<version> or /apis/<group>/<version>
+    let rest = match segments.first() {
+        Some(&"api") if segments.len() >= 2 => &segments[2..],
+        Some(&"apis") if segments.len() >= 3 => &segments[3..],
+        _ => return false,
+    };
+    // skip namespaces/<ns>, unless that's all there is (listing namespaces or getting one)
+    let rest = if rest.len() > 2 && rest[0] == "namespaces" {
+        &rest[2..]
+    } else {
+        rest
+    };
+    rest.len() == 1
+}
+
+/// Percent-encode a string for use as a query parameter value
+fn encode_query_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(format!("%{:02X}", b).as_str()),
+        }
+    }
+    encoded
+}
+
+// Service accounts
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ServiceAccount {
+    pub metadata: Metadata,
+    #[serde(rename = "imagePullSecrets")]
+    pub image_pull_secrets: Option<Vec<LocalObjectReference>>,
+}
+
+/// An image pull secret a pod will use, and where it came from
+#[derive(Debug, PartialEq)]
+pub struct PullSecret {
+    pub name: String,
+    /// The service account that lists it, or None if it's in the pod spec
+    pub service_account: Option<String>,
+    /// False if there's no secret with this name, so pulls needing it will fail.  None if we
+    /// couldn't check (usually for lack of permission to read secrets).
+    pub exists: Option<bool>,
+}
+
+// Service account tokens
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct TokenRequestStatus {
+    pub token: String,
+    #[serde(rename = "expirationTimestamp")]
+    pub expiration_timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TokenRequest {
+    pub status: TokenRequestStatus,
+}
+
+// Certificate signing requests
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CertificateSigningRequestSpec {
+    /// Who made the request (filled in by the api server)
+    pub username: Option<String>,
+    pub groups: Option<Vec<String>>,
+    #[serde(rename = "signerName")]
+    pub signer_name: String,
+    pub usages: Option<Vec<String>>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CertificateSigningRequestStatus {
+    pub conditions: Option<Vec<Condition>>,
+    /// The issued certificate, once signed
+    pub certificate: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct CertificateSigningRequest {
+    pub metadata: Metadata,
+    pub spec: CertificateSigningRequestSpec,
+    pub status: Option<CertificateSigningRequestStatus>,
+}
+
+impl CertificateSigningRequest {
+    fn has_condition(&self, typ: &str) -> bool {
+        self.status
+            .as_ref()
+            .and_then(|s| s.conditions.as_ref())
+            .map(|conds| conds.iter().any(|c| c.typ == typ && c.status == "True"))
+            .unwrap_or(false)
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.has_condition("Approved")
+    }
+
+    pub fn is_denied(&self) -> bool {
+        self.has_condition("Denied")
+    }
+
+    /// Neither approved nor denied yet
+    #[allow(dead_code)]
+    pub fn is_pending(&self) -> bool {
+        !(self.is_approved() || self.is_denied())
+    }
+}
+
+/// The body of a merge patch to the approval subresource of a csr, adding an Approved condition
+/// to the existing ones (a merge patch replaces the whole list)
+fn csr_approval_patch(existing: &[Value], message: &str, now: DateTime<Utc>) -> Value {
+    let mut conditions = existing.to_vec();
+    conditions.push(json!({
+        "type": "Approved",
+        "status": "True",
+        "reason": "ClickApprove",
+        "message": message,
+        "lastUpdateTime": now.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+    }));
+    json!({ "status": { "conditions": conditions } })
+}
+
+/// Kinds that are usually created (and so owned) by other objects, as (group, version,
+/// resource, kind).  These are what deletion_preview looks through for dependents.
+const DEPENDENT_KINDS: &[(&str, &str, &str, &str)] = &[
+    ("apps", "v1", "replicasets", "ReplicaSet"),
+    ("apps", "v1", "controllerrevisions", "ControllerRevision"),
+    ("batch", "v1", "jobs", "Job"),
+    ("", "v1", "pods", "Pod"),
+    ("", "v1", "persistentvolumeclaims", "PersistentVolumeClaim"),
+    ("discovery.k8s.io", "v1", "endpointslices", "EndpointSlice"),
+];
+
+/// An object the garbage collector would delete along with its owner
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dependent {
+    pub kind: String,
+    pub namespace: Option<String>,
+    pub name: String,
+    /// The name of the object that owns this one
+    pub owner: String,
+    /// 1 for objects owned by the one being deleted, 2 for objects they own, and so on
+    pub depth: usize,
+}
+
+/// What deleting an object would take with it
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct DeletionPreview {
+    /// Everything the garbage collector would delete, in breadth first order
+    pub dependents: Vec<Dependent>,
+    /// The resources (of DEPENDENT_KINDS) we weren't allowed to list, so could have dependents
+    /// that aren't in the preview
+    pub skipped: Vec<String>,
+}
+
+/// Find everything in candidates (as (kind, object) pairs) owned, directly or through other
+/// candidates, by the object with uid root_uid, in breadth first order
+fn find_dependents(
+    root_uid: &str,
+    root_name: &str,
+    candidates: &[(&str, Value)],
+) -> Vec<Dependent> {
+    let mut found = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut frontier = vec![(root_uid.to_owned(), root_name.to_owned())];
+    let mut depth = 0;
+    while !frontier.is_empty() {
+        depth += 1;
+        let mut next = Vec::new();
+        for &(kind, ref obj) in candidates.iter() {
+            let uid = ::values::val_str("/metadata/uid", obj, "").into_owned();
+            if seen.contains(&uid) {
+                continue;
+            }
+            let owners = obj.pointer("/metadata/ownerReferences")
+                .and_then(|refs| refs.as_array())
+                .map(|refs| refs.as_slice())
+                .unwrap_or(&[]);
+            let owner = frontier.iter().find(|&&(ref owner_uid, _)| {
+                owners.iter().any(|r| r["uid"].as_str() == Some(owner_uid.as_str()))
+            });
+            if let Some(&(_, ref owner_name)) = owner {
+                let name = ::values::val_str("/metadata/name", obj, "").into_owned();
+                found.push(Dependent {
+                    kind: kind.to_owned(),
+                    namespace: ::values::val_str_opt("/metadata/namespace", obj),
+                    name: name.clone(),
+                    owner: owner_name.clone(),
+                    depth,
+                });
+                seen.insert(uid.clone());
+                next.push((uid, name));
+            }
+        }
+        frontier = next;
+    }
+    found
+}
+
+/// The body to PUT to a namespace's /finalize subresource to clear its finalizers: the namespace
+/// itself with an empty spec.finalizers
+fn namespace_finalize_body(mut namespace: Value) -> Value {
+    if let Some(obj) = namespace.as_object_mut() {
+        obj.insert("spec".to_owned(), json!({ "finalizers": [] }));
+    }
+    namespace
+}
+
+/// The merge patch that pauses or resumes a deployment.  Resuming sets paused to false rather
+/// than removing it, as kubectl rollout resume does.
+fn paused_patch(paused: bool) -> Value {
+    json!({ "spec": { "paused": paused } })
+}
+
+// Resource identification
+
+/// Identifies a type of resource on the api server by group, version and (plural) resource name,
+/// e.g. apps/v1/deployments.  The core group is represented by the empty string.
+#[derive(Debug, Clone)]
+pub struct GroupVersionResource {
+    pub group: String,
+    pub version: String,
+    pub resource: String,
+}
+
+impl GroupVersionResource {
+    pub fn new(group: &str, version: &str, resource: &str) -> GroupVersionResource {
+        GroupVersionResource {
+            group: group.to_owned(),
+            version: version.to_owned(),
+            resource: resource.to_owned(),
+        }
+    }
+
+    /// The same group and resource, but at a different version
+    pub fn with_version(&self, version: &str) -> GroupVersionResource {
+        GroupVersionResource::new(self.group.as_str(), version, self.resource.as_str())
+    }
+
+    /// Build the api path for this resource.  If namespace is None the path is cluster scoped (or
+    /// across all namespaces for a list), and if name is None the path is to the collection.
+    pub fn path(&self, namespace: Option<&str>, name: Option<&str>) -> String {
+        let mut path = if self.group.is_empty() {
+            format!("/api/{}", self.version)
+        } else {
+            format!("/apis/{}/{}", self.group, self.version)
+        };
+        if let Some(ns) = namespace {
+            path.push_str("/namespaces/");
+            path.push_str(ns);
+        }
+        path.push('/');
+        path.push_str(self.resource.as_str());
+        if let Some(n) = name {
+            path.push('/');
+            path.push_str(n);
+        }
+        path
+    }
+}
+
+/// Kinds of resource click knows about without asking the server, as (plural resource name,
+/// other names and short names, group, version, namespaced)
+const BUILTIN_KINDS: &[(&str, &[&str], &str, &str, bool)] = &[
+    ("pods", &["pod", "po"], "", "v1", true),
+    ("services", &["service", "svc"], "", "v1", true),
+    ("configmaps", &["configmap", "cm"], "", "v1", true),
+    ("secrets", &["secret"], "", "v1", true),
+    ("events", &["event", "ev"], "", "v1", true),
+    ("serviceaccounts", &["serviceaccount", "sa"], "", "v1", true),
+    ("persistentvolumeclaims", &["persistentvolumeclaim", "pvc"], "", "v1", true),
+    ("persistentvolumes", &["persistentvolume", "pv"], "", "v1", false),
+    ("nodes", &["node", "no"], "", "v1", false),
+    ("namespaces", &["namespace", "ns"], "", "v1", false),
+    ("deployments", &["deployment", "deploy"], "apps", "v1", true),
+    ("replicasets", &["replicaset", "rs"], "apps", "v1", true),
+    ("statefulsets", &["statefulset", "sts"], "apps", "v1", true),
+    ("daemonsets", &["daemonset", "ds"], "apps", "v1", true),
+    ("jobs", &["job"], "batch", "v1", true),
+    ("cronjobs", &["cronjob", "cj"], "batch", "v1", true),
+    ("ingresses", &["ingress", "ing"], "networking.k8s.io", "v1", true),
+    ("storageclasses", &["storageclass", "sc"], "storage.k8s.io", "v1", false),
+    (
+        "certificatesigningrequests",
+        &["certificatesigningrequest", "csr"],
+        "certificates.k8s.io",
+        "v1",
+        false,
+    ),
+];
+
+/// The kinds export_namespace saves, as (resource, kind).  Pods, replica sets and events are
+/// left out, since they're created by the kinds that are saved (or by the cluster itself).
+const EXPORT_KINDS: &[(&str, &str)] = &[
+    ("configmaps", "ConfigMap"),
+    ("secrets", "Secret"),
+    ("serviceaccounts", "ServiceAccount"),
+    ("persistentvolumeclaims", "PersistentVolumeClaim"),
+    ("services", "Service"),
+    ("deployments", "Deployment"),
+    ("statefulsets", "StatefulSet"),
+    ("daemonsets", "DaemonSet"),
+    ("jobs", "Job"),
+    ("cronjobs", "CronJob"),
+    ("ingresses", "Ingress"),
+];
+
+/// Look up a kind by any of its names (case insensitive), returning its resource and whether it's
+/// namespaced
+fn builtin_kind(name: &str) -> Option<(GroupVersionResource, bool)> {
+    let name = name.to_lowercase();
+    BUILTIN_KINDS
+        .iter()
+        .find(|&&(plural, others, _, _, _)| plural == name || others.contains(&name.as_str()))
+        .map(|&(plural, _, group, version, namespaced)| {
+            (GroupVersionResource::new(group, version, plural), namespaced)
+        })
+}
+
+/// A pod or service to reach through the api server's proxy, optionally on a specific port
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyTarget {
+    pub namespace: String,
+    /// "pods" or "services"
+    pub resource: &'static str,
+    pub name: String,
+    /// A port number or name.  For services this can be prefixed with a scheme, like
+    /// "https:web".
+    pub port: Option<String>,
+}
+
+impl ProxyTarget {
+    /// kind can be any name for pods or services ("pod", "po", "svc", ...).  An empty port means
+    /// the default one.
+    pub fn new(
+        namespace: &str,
+        kind: &str,
+        name: &str,
+        port: &str,
+    ) -> Result<ProxyTarget, KubeError> {
+        let resource = match builtin_kind(kind) {
+            Some((ref gvr, _)) if gvr.resource == "pods" => "pods",
+            Some((ref gvr, _)) if gvr.resource == "services" => "services",
+            _ => {
+                return Err(KubeError::ParseErr(format!(
+                    "Can only proxy to pods and services, not {}",
+                    kind
+                )))
+            }
+        };
+        let valid_port = port.split(':').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+        if !(port.is_empty() || valid_port) {
+            return Err(KubeError::ParseErr(format!("Invalid port: {}", port)));
+        }
+        Ok(ProxyTarget {
+            namespace: namespace.to_owned(),
+            resource: resource,
+            name: name.to_owned(),
+            port: if port.is_empty() {
+                None
+            } else {
+                Some(port.to_owned())
+            },
+        })
+    }
+
+    /// The api path that proxies to path on the target
+    pub fn path(&self, path: &str) -> String {
+        let port = self.port
+            .as_ref()
+            .map(|p| format!(":{}", p))
+            .unwrap_or_default();
+        format!(
+            "/api/v1/namespaces/{}/{}/{}{}/proxy/{}",
+            self.namespace,
+            self.resource,
+            self.name,
+            port,
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+/// Identifies a kind of object by group, version and kind, e.g. apps/v1 Deployment.  The core
+/// group is represented by the empty string.
+#[derive(Debug, Clone)]
+pub struct GroupVersionKind {
+    pub group: String,
+    pub version: String,
+    pub kind: String,
+}
+
+impl GroupVersionKind {
+    #[allow(dead_code)]
+    pub fn new(group: &str, version: &str, kind: &str) -> GroupVersionKind {
+        GroupVersionKind {
+            group: group.to_owned(),
+            version: version.to_owned(),
+            kind: kind.to_owned(),
+        }
+    }
+}
+
+/// A group/version the server supports, from the /apis discovery document
+#[derive(Debug, Deserialize)]
+pub struct GroupVersionForDiscovery {
+    #[serde(rename = "groupVersion")]
+    pub group_version: String,
+    pub version: String,
+}
+
+/// An api group and the versions of it the server supports
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ApiGroup {
+    pub name: String,
+    pub versions: Vec<GroupVersionForDiscovery>,
+    #[serde(rename = "preferredVersion")]
+    pub preferred_version: Option<GroupVersionForDiscovery>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiGroupList {
+    pub groups: Vec<ApiGroup>,
+}
+
+/// A type of resource a group/version serves, from its discovery document
+#[derive(Debug, Deserialize)]
+pub struct ApiResource {
+    pub name: String,
+    #[serde(rename = "singularName", default)]
+    pub singular_name: String,
+    pub namespaced: bool,
+    pub kind: String,
+    #[serde(rename = "shortNames", default)]
+    pub short_names: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiResourceList {
+    pub resources: Vec<ApiResource>,
+}
+
+impl ApiResource {
+    /// Whether name (already lower case) is any of the names this resource goes by
+    fn is_named(&self, name: &str) -> bool {
+        self.name == name
+            || self.singular_name == name
+            || self.kind.to_lowercase() == name
+            || self.short_names.iter().any(|n| n == name)
+    }
+}
+
+/// How a strategic merge patch treats a field.  For lists with a merge key (like containers,
+/// keyed by name) entries with the same key are merged rather than the list being replaced.
+#[derive(Debug, PartialEq)]
+pub struct PatchStrategy {
+    /// e.g. "merge", "retainKeys" or "merge,retainKeys".  None means the field is replaced.
+    pub strategy: Option<String>,
+    pub merge_key: Option<String>,
+}
+
+/// Find the schema definition for gvk in an OpenAPI v2 document
+fn openapi_definition<'a>(doc: &'a Value, gvk: &GroupVersionKind) -> Option<&'a Value> {
+    doc.get("definitions")
+        .and_then(|d| d.as_object())
+        .and_then(|defs| {
+            defs.values().find(|def| {
+                def.get("x-kubernetes-group-version-kind")
+                    .and_then(|g| g.as_array())
+                    .map(|gvks| {
+                        gvks.iter().any(|g| {
+                            ::values::val_str("/group", g, "") == gvk.group.as_str()
+                                && ::values::val_str("/version", g, "") == gvk.version.as_str()
+                                && ::values::val_str("/kind", g, "") == gvk.kind.as_str()
+                        })
+                    })
+                    .unwrap_or(false)
+            })
+        })
+}
+
+/// Follow a schema's $ref (like "#/definitions/io.k8s.api.core.v1.PodSpec") if it has one
+fn openapi_resolve<'a>(doc: &'a Value, schema: &'a Value) -> Option<&'a Value> {
+    match schema.get("$ref").and_then(|r| r.as_str()) {
+        Some(reference) => {
+            let name = reference.trim_start_matches("#/definitions/");
+            doc.get("definitions").and_then(|defs| defs.get(name))
+        }
+        None => Some(schema),
+    }
+}
+
+/// Get the patch strategy for the field at field_path (like "spec.template.spec.containers") in
+/// the schema of gvk.  Lists along the path are stepped through to their items.
+fn openapi_patch_strategy(
+    doc: &Value,
+    gvk: &GroupVersionKind,
+    field_path: &str,
+) -> Option<PatchStrategy> {
+    let mut schema = openapi_definition(doc, gvk)?;
+    let mut field = None;
+    for part in field_path.split('.').filter(|p| !p.is_empty()) {
+        schema = openapi_resolve(doc, schema)?;
+        if schema.get("type").and_then(|t| t.as_str()) == Some("array") {
+            schema = openapi_resolve(doc, schema.get("items")?)?;
+        }
+        let prop = schema.get("properties").and_then(|p| p.get(part))?;
+        field = Some(prop);
+        schema = prop;
+    }
+    field.map(|f| PatchStrategy {
+        strategy: ::values::val_str_opt("/x-kubernetes-patch-strategy", f),
+        merge_key: ::values::val_str_opt("/x-kubernetes-patch-merge-key", f),
+    })
+}
+
+// Logs
+
+/// Options for fetching the logs of a container
+#[derive(Debug, Default, Clone)]
+pub struct LogParams {
+    /// Which container to get logs for, can be None if the pod only has one
+    pub container: Option<String>,
+    pub follow: bool,
+    pub previous: bool,
+    pub tail_lines: Option<u32>,
+    pub since_seconds: Option<i64>,
+    pub timestamps: bool,
+}
+
+impl LogParams {
+    /// Build the path to fetch logs for the specified pod with these params
+    pub fn path(&self, namespace: &str, pod: &str) -> String {
+        let mut path = format!("/api/v1/namespaces/{}/pods/{}/log", namespace, pod);
+        if let Some(ref container) = self.container {
+            path = add_query_param(path.as_str(), format!("container={}", container).as_str());
+        }
+        if self.follow {
+            path = add_query_param(path.as_str(), "follow=true");
+        }
+        if self.previous {
+            path = add_query_param(path.as_str(), "previous=true");
+        }
+        if let Some(tail) = self.tail_lines {
+            path = add_query_param(path.as_str(), format!("tailLines={}", tail).as_str());
+        }
+        if let Some(since) = self.since_seconds {
+            path = add_query_param(path.as_str(), format!("sinceSeconds={}", since).as_str());
+        }
+        if self.timestamps {
+            path = add_query_param(path.as_str(), "timestamps=true");
+        }
+        path
+    }
+}
+
+/// How long a line from one log stream is held back so earlier lines from other streams can be
+/// written before it
+const LOG_REORDER_WINDOW_MS: u64 = 500;
+
+/// A line from one of several log streams being combined
+struct LogLine {
+    source: usize,
+    time: Option<DateTime<Utc>>,
+    /// The line without its timestamp
+    text: String,
+    arrived: Instant,
+}
+
+impl LogLine {
+    /// Parse a line logged with timestamps=true, which looks like "<rfc3339 time> <text>"
+    fn parse(source: usize, line: &str) -> LogLine {
+        let line = line.trim_end_matches('\n');
+        let mut parts = line.splitn(2, ' ');
+        let time = parts
+            .next()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Utc));
+        let text = match time {
+            Some(_) => parts.next().unwrap_or(""),
+            None => line,
+        };
+        LogLine {
+            source: source,
+            time: time,
+            text: text.to_owned(),
+            arrived: Instant::now(),
+        }
+    }
+}
+
+/// What grep_logs looks for, like grep's -v, -B and -A options
+#[derive(Debug, Clone)]
+pub struct LogGrep {
+    pub pattern: Regex,
+    /// Write the lines that don't match instead
+    pub invert: bool,
+    /// Lines of context to write before and after each matching line
+    pub before: usize,
+    pub after: usize,
+}
+
+impl LogGrep {
+    #[allow(dead_code)]
+    pub fn new(pattern: Regex) -> LogGrep {
+        LogGrep {
+            pattern: pattern,
+            invert: false,
+            before: 0,
+            after: 0,
+        }
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        self.pattern.is_match(line) != self.invert
+    }
+
+    /// Read lines from reader and write those that match (with their context) to out, returning
+    /// how many matched.  As with grep, when there's context, non-adjacent groups of lines are
+    /// separated by "--".
+    /// Read timeouts are ignored, since a followed log can go quiet for a while.
+    pub fn filter<R: BufRead>(&self, mut reader: R, out: &mut dyn Write) -> Result<u64, KubeError> {
+        let mut before: VecDeque<(u64, String)> = VecDeque::with_capacity(self.before);
+        let mut after_left = 0;
+        let mut last_written: Option<u64> = None;
+        let mut matches = 0;
+        let mut line_no = 0;
+        let mut line = Vec::new();
+        loop {
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => return Ok(matches),
+                Ok(_) => {}
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    continue // keep whatever was read of the line
+                }
+                Err(e) => return Err(KubeError::from(e)),
+            }
+            let text = String::from_utf8_lossy(line.as_slice())
+                .trim_end_matches('\n')
+                .to_owned();
+            line.clear();
+            line_no += 1;
+            if self.is_match(text.as_str()) {
+                matches += 1;
+                let first = before.front().map(|&(n, _)| n).unwrap_or(line_no);
+                let with_context = self.before > 0 || self.after > 0;
+                if with_context && last_written.map(|last| last + 1 < first).unwrap_or(false) {
+                    out.write_all(b"--\n")?;
+                }
+                for (_, context) in before.drain(..) {
+                    write!(out, "{}\n", context)?;
+                }
+                write!(out, "{}\n", text)?;
+                last_written = Some(line_no);
+                after_left = self.after;
+            } else if after_left > 0 {
+                write!(out, "{}\n", text)?;
+                last_written = Some(line_no);
+                after_left -= 1;
+            } else if self.before > 0 {
+                if before.len() == self.before {
+                    before.pop_front();
+                }
+                before.push_back((line_no, text));
+            }
+        }
+    }
+}
+
+/// A warning the api server sent with a response (in a Warning header with code 299), usually
+/// that the api version used is deprecated
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeprecationWarning {
+    pub text: String,
+    /// Which server sent the warning, None if it was given as "-"
+    pub agent: Option<String>,
+}
+
+/// Parse a quoted-string (RFC 7230) from the start of s, returning its unescaped contents and the
+/// rest of s
+fn parse_quoted_string(s: &str) -> Option<(String, &str)> {
+    if !s.starts_with('"') {
+        return None;
+    }
+    let mut text = String::new();
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        if escaped {
+            text.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            return Some((text, &s[i + 1..]));
+        } else {
+            text.push(c);
+        }
+    }
+    None
+}
+
+/// Parse the value of a Warning header (RFC 7234), which is a comma separated list of
+/// `warn-code warn-agent "warn-text" ["warn-date"]`, returning the 299 (miscellaneous persistent)
+/// warnings.  Parsing stops at anything malformed.
+pub fn parse_warning_header(value: &str) -> Vec<DeprecationWarning> {
+    let mut warnings = Vec::new();
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        if rest.is_empty() {
+            return warnings;
+        }
+        let mut parts = rest.splitn(3, ' ');
+        let (code, agent, after) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(code), Some(agent), Some(after)) => (code, agent, after),
+            _ => return warnings,
+        };
+        let (text, after) = match parse_quoted_string(after) {
+            Some(parsed) => parsed,
+            None => return warnings,
+        };
+        rest = after.trim_start();
+        if rest.starts_with('"') {
+            // skip the optional date
+            rest = match parse_quoted_string(rest) {
+                Some((_, after)) => after,
+                None => return warnings,
+            };
+        }
+        if code == "299" {
+            warnings.push(DeprecationWarning {
+                text: text,
+                agent: if agent == "-" {
+                    None
+                } else {
+                    Some(agent.to_owned())
+                },
+            });
+        }
+    }
+}
+
+/// Warnings received from the server, each recorded only the first time it's seen
+#[derive(Debug, Default)]
+struct WarningLog {
+    seen: HashSet<String>,
+    unread: Vec<DeprecationWarning>,
+}
+
+// Kubernetes authentication data
+
+// Auth is either a token, a username/password, or a cert and key
+pub enum KlusterAuth {
+    Token(String),
+    UserPass(String, String),
+    CertKey(Vec<Certificate>, PrivateKey),
+}
+
+impl KlusterAuth {
+    pub fn with_token(token: &str) -> KlusterAuth {
+        KlusterAuth::Token(token.to_owned())
+    }
+
+    pub fn with_userpass(user: &str, pass: &str) -> KlusterAuth {
+        KlusterAuth::UserPass(user.to_owned(), pass.to_owned())
+    }
+    
+    pub fn with_cert_and_key(cert: Certificate, private_key: PrivateKey) -> KlusterAuth {
+        KlusterAuth::CertKey(vec![cert], private_key)
+    }
+
+    /// If this is a bearer token that's a JWT (like service account tokens are), decode the
+    /// claims it makes.  The signature is NOT checked, so this is only for seeing which identity
+    /// and audiences a token is for and when it expires, not for trusting it.  Returns None for
+    /// other kinds of auth and for opaque tokens.
+    #[allow(dead_code)]
+    pub fn token_info(&self) -> Option<TokenInfo> {
+        match *self {
+            KlusterAuth::Token(ref token) => TokenInfo::from_jwt(token.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// The claims of a JWT bearer token
+#[derive(Debug, PartialEq)]
+pub struct TokenInfo {
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub audiences: Vec<String>,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub expires: Option<DateTime<Utc>>,
+}
+
+impl TokenInfo {
+    /// Decode the payload of a JWT, without verifying it
+    pub fn from_jwt(token: &str) -> Option<TokenInfo> {
+        let mut parts = token.split('.');
+        let payload = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(_), Some(payload), Some(_), None) => payload,
+            _ => return None,
+        };
+        let payload = payload.trim_end_matches('=');
+        let payload = ::base64::decode_config(payload, ::base64::URL_SAFE_NO_PAD).ok()?;
+        let claims: Value = serde_json::from_slice(payload.as_slice()).ok()?;
+        if !claims.is_object() {
+            return None;
+        }
+        let time = |claim: &str| {
+            claims
+                .get(claim)
+                .and_then(|t| t.as_i64())
+                .and_then(|t| NaiveDateTime::from_timestamp_opt(t, 0)) // None if out of range
+                .map(|t| DateTime::<Utc>::from_utc(t, Utc))
+        };
+        // aud can be a single string or a list
+        let audiences = match claims.get("aud") {
+            Some(&Value::String(ref aud)) => vec![aud.clone()],
+            Some(&Value::Array(ref auds)) => auds.iter()
+                .filter_map(|a| a.as_str().map(|a| a.to_owned()))
+                .collect(),
+            _ => Vec::new(),
+        };
+        Some(TokenInfo {
+            issuer: ::values::val_str_opt("/iss", &claims),
+            subject: ::values::val_str_opt("/sub", &claims),
+            audiences: audiences,
+            issued_at: time("iat"),
+            expires: time("exp"),
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn is_expired(&self) -> bool {
+        self.expires.map(|exp| exp <= Utc::now()).unwrap_or(false)
+    }
+}
+
+/// A TLS session store shared between the configs of all connections to a cluster.  Streaming
+/// requests (like following logs) each make a new connection, and sharing the store lets those
+/// resume an earlier session rather than doing a full handshake every time.
+#[derive(Clone)]
+struct SharedSessionCache {
+    cache: Arc<Mutex<Box<dyn StoresClientSessions>>>,
+}
+
+impl SharedSessionCache {
+    fn new() -> SharedSessionCache {
+        SharedSessionCache {
+            cache: Arc::new(Mutex::new(ClientSessionMemoryCache::new(32))),
+        }
+    }
+}
+
+impl StoresClientSessions for SharedSessionCache {
+    fn put(&mut self, key: Vec<u8>, value: Vec<u8>) -> bool {
+        match self.cache.lock() {
+            Ok(mut cache) => cache.put(key, value),
+            Err(_) => false,
+        }
+    }
+
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.cache.lock() {
+            Ok(mut cache) => cache.get(key),
+            Err(_) => None,
+        }
+    }
+}
+
+pub struct Kluster {
+    pub name: String,
+    endpoint: Url,
+    auth: KlusterAuth,
+    cert_opt: Option<String>,
+    client: Client,
+    session_cache: SharedSessionCache,
+    /// The server's OpenAPI (v2) document, fetched the first time it's needed
+    openapi: Mutex<Option<Arc<Value>>>,
+    /// The most requests fan out helpers will have in flight at once
+    max_concurrency: usize,
+    /// The largest (decompressed) response body get, get_value, etc. will read
+    max_response_bytes: usize,
+    warnings: Mutex<WarningLog>,
+}
+
+impl Kluster {
+    fn make_tlsclient(
+        cert_opt: &Option<String>,
+        auth: &KlusterAuth,
+        session_cache: &SharedSessionCache,
+    ) -> TlsClient {
+        let mut tlsclient = TlsClient::new();
+        if let Some(cfg) = Arc::get_mut(&mut tlsclient.cfg) {
+            cfg.set_persistence(Box::new(session_cache.clone()));
+        }
+        if let &Some(ref cert_data) = cert_opt {
+            // add the cert to the root store
+            if let Some(cfg) = Arc::get_mut(&mut tlsclient.cfg) {
+                let mut br = BufReader::new(cert_data.as_bytes());
+                match cfg.root_store.add_pem_file(&mut br) {
+                    Ok(added) => {
+                        if added.1 > 0 {
+                            println!(
+                                "[WARNING] Couldn't add your server cert, connection will probably fail"
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        println!(
+                            "[WARNING] Coudln't add your server cert, connection will probably fail. Error was: {:?}",
+                            e
+                        )
+                    }
+                }
+
+                if let &KlusterAuth::CertKey(ref cert, ref key) = auth {
+                    cfg.set_single_client_cert(cert.clone(), key.clone());
+                }
+            } else {
+                println!(
+                    "[WARNING] Failed to configure tlsclient, connection will probably fail.  Please restart click"
+                );
+            }
+        }
+        tlsclient
+    }
+
+    // We map ip addresses to a name in the certificate if needed, to keep hyper happy.  see
+    // comments on try_ip_to_name and in connector.rs.
+    fn make_connector(tlsclient: TlsClient, endpoint: &mut Url) -> ClickSslConnector<TlsClient> {
+        let mut dns_host: Option<String> = None;
+        let mut ip: Option<String> = None;
+        if let Some(host) = endpoint.host_str() {
+            if let Ok(addr) = IpAddr::from_str(host) {
+                dns_host = ::certs::try_ip_to_name(&addr, endpoint.port().unwrap_or(443));
+                ip = Some(host.to_owned());
+            }
+        };
+        if let (Some(host), Some(ip_addr)) = (dns_host, ip) {
+            // The cert has a matching IP and a host name, use that
+            endpoint.set_host(Some(host.as_str())).unwrap();
+            ClickSslConnector::new(tlsclient, Some((host, ip_addr)))
+        } else {
+            ClickSslConnector::new(tlsclient, None)
+        }
+    }
+
+    fn add_auth_header<'a>(&self, req: RequestBuilder<'a>) -> RequestBuilder<'a> {
+        match self.auth {
+            KlusterAuth::Token(ref token) => {
+                req.header(Authorization(Bearer {
+                    token: token.clone(),
+                }))
+            },
+            KlusterAuth::UserPass(ref user, ref pass) => {
+                req.header(Authorization(Basic {
+                    username: user.clone(),
+                    password: Some(pass.clone()),
+                }))
+            },
+            KlusterAuth::CertKey(..) => req
+        }
+    }
+    
+    pub fn new(
+        name: &str,
+        cert_opt: Option<String>,
+        server: &str,
+        auth: KlusterAuth,
+    ) -> Result<Kluster, KubeError> {
+        let session_cache = SharedSessionCache::new();
+        let tlsclient = Kluster::make_tlsclient(&cert_opt, &auth, &session_cache);
+        let mut endpoint = try!(Url::parse(server));
+        let mut client = Client::with_connector(Kluster::make_connector(tlsclient, &mut endpoint));
+        client.set_read_timeout(Some(Duration::new(20, 0)));
+        client.set_write_timeout(Some(Duration::new(20, 0)));
+        Ok(Kluster {
+            name: name.to_owned(),
+            endpoint: endpoint,
+            auth: auth,
+            cert_opt: cert_opt,
+            client: client,
+            session_cache: session_cache,
+            openapi: Mutex::new(None),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            warnings: Mutex::new(WarningLog::default()),
+        })
+    }
+
+    /// Get the warnings (like deprecation notices) the server has sent since the last call.  Each
+    /// distinct warning is only returned once per session.
+    pub fn take_warnings(&self) -> Vec<DeprecationWarning> {
+        let mut log = self.warnings.lock().unwrap();
+        log.unread.drain(..).collect()
+    }
+
+    /// Record any warnings in the headers of resp
+    fn record_warnings(&self, resp: &Response) {
+        if let Some(values) = resp.headers.get_raw("Warning") {
+            let mut log = self.warnings.lock().unwrap();
+            for value in values.iter() {
+                let value = String::from_utf8_lossy(value.as_slice());
+                for warning in parse_warning_header(&value).into_iter() {
+                    if log.seen.insert(warning.text.clone()) {
+                        log.unread.push(warning);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Limit how big a response body (including error bodies) we'll read, so a runaway response
+    /// fails with ResponseTooLarge rather than using all our memory.  Streaming reads (watches,
+    /// logs) aren't limited.
+    #[allow(dead_code)]
+    pub fn with_max_response_bytes(mut self, max: usize) -> Kluster {
+        self.max_response_bytes = max;
+        self
+    }
+
+    /// Limit the number of requests helpers that fan out (over groups, namespaces, etc.) will
+    /// have in flight at once.  n is at least 1, which makes them sequential.
+    #[allow(dead_code)]
+    pub fn with_max_concurrency(mut self, n: usize) -> Kluster {
+        self.max_concurrency = cmp::max(n, 1);
+        self
+    }
+
+    /// Run f on each item using a pool of at most max_concurrency threads, returning the results
+    /// in the same order as items
+    pub fn fan_out<T, R, F>(&self, items: Vec<T>, f: F) -> Vec<R>
+    where
+        T: Send,
+        R: Send,
+        F: Fn(&Kluster, T) -> R + Sync,
+    {
+        let count = items.len();
+        let workers = cmp::min(self.max_concurrency, count);
+        if workers <= 1 {
+            return items.into_iter().map(|item| f(self, item)).collect();
+        }
+        let queue = Mutex::new(items.into_iter().enumerate());
+        let results = Mutex::new(Vec::with_capacity(count));
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    // take the lock only long enough to get the next item
+                    let next = queue.lock().unwrap().next();
+                    match next {
+                        Some((index, item)) => {
+                            let result = f(self, item);
+                            results.lock().unwrap().push((index, result));
+                        }
+                        None => break,
+                    }
+                });
+            }
+        });
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|&(index, _)| index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
+    /// Get the full url that logs for the specified pod would be fetched from.  This doesn't send
+    /// anything, it's for handing off to other tools.
+    #[allow(dead_code)]
+    pub fn log_url(
+        &self,
+        namespace: &str,
+        pod: &str,
+        params: &LogParams,
+    ) -> Result<Url, KubeError> {
+        self.endpoint
+            .join(params.path(namespace, pod).as_str())
+            .map_err(|pe| KubeError::from(pe))
+    }
+
+    fn send_req(&self, path: &str) -> Result<Response, KubeError> {
+        let url = try!(self.endpoint.join(path));
+        let req = self.client.get(url);
+        let req = self.add_auth_header(req);
+        // lists can be huge, but compressing small responses just costs latency
+        let req = if is_collection_path(path) {
+            req.header(AcceptEncoding(vec![qitem(Encoding::Gzip)]))
+        } else {
+            req
+        };
+        req.send().map_err(|he| KubeError::from(he))
+    }
+
+    fn check_resp(&self, resp: Response) -> Result<Response, KubeError> {
+        self.record_warnings(&resp);
+        if resp.status.is_success() {
+            Ok(resp)
+        } else if resp.status == StatusCode::Unauthorized {
+            // the body is usually a Status saying why, but don't fail if it isn't
+            let msg = self.parse_body::<Value>(resp)
+                .ok()
+                .and_then(|val| ::values::val_str_opt("/message", &val));
+            Err(KubeError::Kube(KubeErrNo::Unauthorized(msg)))
+        } else {
+            // try and read an error message out, an empty body just means we don't know why
+            let val: Value = match self.parse_body(resp) {
+                Ok(v) => v,
+                Err(KubeError::Kube(KubeErrNo::EmptyResponse)) => Value::Null,
+                Err(e) => return Err(e),
+            };
+            match ::values::val_str_opt("/message", &val) {
+                Some(msg) => Err(KubeError::KubeServerError(msg)),
+                None => Err(KubeError::Kube(KubeErrNo::Unknown)),
+            }
+        }
+    }
+
+    /// Like check_resp, but for requests a watch is retrying: the server being overloaded (429
+    /// Too Many Requests) or unavailable (any 5xx, like a 503 while the api server restarts or a
+    /// 502 from a proxy in front of it) is an Unavailable error, which is worth retrying
+    fn check_watch_resp(&self, resp: Response) -> Result<Response, KubeError> {
+        if resp.status == StatusCode::TooManyRequests || resp.status.is_server_error() {
+            self.record_warnings(&resp);
+            let code = resp.status.to_u16();
+            // a proxy's error page won't be a Status, so don't insist on a message
+            let msg = self.parse_body::<Value>(resp)
+                .ok()
+                .and_then(|val| ::values::val_str_opt("/message", &val));
+            Err(KubeError::Kube(KubeErrNo::Unavailable(code, msg)))
+        } else {
+            self.check_resp(resp)
+        }
+    }
+
+    /// Read the whole body of a response, decompressing it if needed.  Fails with
+    /// ResponseTooLarge if the (decompressed) body is longer than max_len.
+    fn read_body(resp: &mut Response, max_len: usize) -> Result<Vec<u8>, KubeError> {
+        let mut body = Vec::new();
+        // read one byte past the limit so we can tell if it was exceeded
+        resp.take(max_len.saturating_add(1) as u64)
+            .read_to_end(&mut body)?;
+        if body.len() > max_len {
+            return Err(KubeError::Kube(KubeErrNo::ResponseTooLarge));
+        }
+        let gzipped = match resp.headers.get::<ContentEncoding>() {
+            Some(&ContentEncoding(ref encodings)) => encodings.contains(&Encoding::Gzip),
+            None => false,
+        };
+        if gzipped {
+            body = gzip::decode(body.as_slice(), max_len)?;
+        }
+        Ok(body)
+    }
+
+    /// Read the body of a response as text, up to max_response_bytes
+    fn read_text(&self, mut resp: Response) -> Result<String, KubeError> {
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        String::from_utf8(body)
+            .map_err(|e| KubeError::ParseErr(format!("Response was not valid utf-8: {}", e)))
+    }
+
+    /// Deserialize a response body as a T.  Some requests (like deleting a collection) can
+    /// succeed with an empty body, which we report as EmptyResponse so callers can decide if
+    /// that's okay, rather than as a confusing json parse error.
+    fn parse_json<T>(body: &[u8]) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        if body.iter().all(|b| (*b as char).is_whitespace()) {
+            Err(KubeError::Kube(KubeErrNo::EmptyResponse))
+        } else {
+            serde_json::from_slice(body).map_err(|sje| KubeError::from(sje))
+        }
+    }
+
+    /// Read the body of a response, up to max_response_bytes, and deserialize it as a T (see
+    /// parse_json)
+    fn parse_body<T>(&self, mut resp: Response) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        Kluster::parse_json(body.as_slice())
+    }
+
+    /// Get the raw body at path.  Like get and get_value this fails with ResponseTooLarge if the
+    /// body is bigger than the limit set by with_max_response_bytes.
+    pub fn get_bytes(&self, path: &str) -> Result<Vec<u8>, KubeError> {
+        let resp = self.send_req(path)?;
+        let mut resp = self.check_resp(resp)?;
+        Kluster::read_body(&mut resp, self.max_response_bytes)
+    }
+
+    /// Get a resource and deserialize it as a T
+    pub fn get<T>(&self, path: &str) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let body = self.get_bytes(path)?;
+        Kluster::parse_json(body.as_slice())
+    }
+
+    /// Get the list at path, checking the server sent the kind of list asked for
+    pub fn get_list<T>(&self, path: &str) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de> + KubeList,
+    {
+        let list: T = self.get(path)?;
+        list.type_meta().expect_kind(T::KIND)?;
+        Ok(list)
+    }
+
+    /// Get the collection at path as a Table, rendered by the server the way kubectl get shows it
+    pub fn get_table(&self, path: &str) -> Result<Table, KubeError> {
+        let url = self.endpoint.join(path)?;
+        let req = self.client
+            .get(url)
+            .header(Accept(vec![qitem(TABLE_ACCEPT_TYPE.parse().unwrap())])) // safe, constant
+            .header(AcceptEncoding(vec![qitem(Encoding::Gzip)]));
+        let req = self.add_auth_header(req);
+        let resp = req.send()?;
+        let mut resp = self.check_resp(resp)?;
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        Kluster::parse_json(body.as_slice())
+    }
+
+    /// List the objects at path matching a label selector (e.g. as returned by
+    /// Deployment::selector_string)
+    pub fn list_with_selector<T>(&self, path: &str, selector: &str) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let param = format!("labelSelector={}", encode_query_value(selector));
+        self.get(add_query_param(path, param.as_str()).as_str())
+    }
+
+    /// Get the named object as the specified version of its resource, regardless of the version in
+    /// gvr.  The api server converts from whatever version the object is stored as, so this lets
+    /// us read an object in the schema T expects.  If the request fails and discovery says the
+    /// group doesn't serve version, that's the error returned, rather than a bare not found.
+    #[allow(dead_code)]
+    pub fn get_as_version<T>(
+        &self,
+        gvr: &GroupVersionResource,
+        namespace: Option<&str>,
+        name: &str,
+        version: &str,
+    ) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let path = gvr.with_version(version).path(namespace, Some(name));
+        self.get(path.as_str()).map_err(|e| {
+            match self.served_versions(gvr.group.as_str()) {
+                Ok(ref versions) if !versions.iter().any(|v| v == version) => {
+                    let group = if gvr.group.is_empty() {
+                        "core"
+                    } else {
+                        gvr.group.as_str()
+                    };
+                    KubeError::KubeServerError(format!(
+                        "The {} api group doesn't serve version {} (it serves: {})",
+                        group,
+                        version,
+                        versions.join(", ")
+                    ))
+                }
+                // either the version is fine or we can't tell, so the original error is best
+                _ => e,
+            }
+        })
+    }
+
+    /// The versions of an api group (the empty string for the core group) the server serves,
+    /// according to discovery
+    fn served_versions(&self, group: &str) -> Result<Vec<String>, KubeError> {
+        if group.is_empty() {
+            let versions: Value = self.get_value("/api")?;
+            return Ok(versions
+                .get("versions")
+                .and_then(|v| v.as_array())
+                .map(|v| v.iter().filter_map(|v| v.as_str()).map(|v| v.to_owned()).collect())
+                .unwrap_or_default());
+        }
+        Ok(self.api_groups()?
+            .into_iter()
+            .find(|g| g.name == group)
+            .map(|g| g.versions.into_iter().map(|v| v.version).collect())
+            .unwrap_or_default())
+    }
+
+    /// Follow the logs of several pods at once, writing their lines to out interleaved in time
+    /// order with each prefixed by the pod it came from.  sources are (namespace, pod) pairs, and
+    /// params applies to all of them.  Lines are fetched with timestamps so they can be ordered,
+    /// and held back for a moment in case an earlier line from another pod is still on its way.
+    /// Returns once all the streams have ended (which with params.follow is when the pods stop),
+    /// or writing to out fails.
+    #[allow(dead_code)]
+    pub fn stream_combined_logs(
+        &self,
+        sources: Vec<(String, String)>,
+        params: &LogParams,
+        out: &mut dyn Write,
+    ) -> Result<(), KubeError> {
+        let mut stream_params = params.clone();
+        stream_params.timestamps = true;
+        let (tx, rx) = mpsc::channel();
+        let mut prefixes = Vec::new();
+        for (index, (namespace, pod)) in sources.into_iter().enumerate() {
+            let path = stream_params.path(namespace.as_str(), pod.as_str());
+            let resp = self.get_read(
+                path.as_str(),
+                Some(Duration::new(WATCH_READ_TIMEOUT, 0)),
+            )?;
+            prefixes.push(match params.container {
+                Some(ref container) => format!("{}/{}", pod, container),
+                None => pod,
+            });
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut reader = BufReader::new(resp);
+                let mut line = Vec::new();
+                loop {
+                    match reader.read_until(b'\n', &mut line) {
+                        Ok(0) => break,
+                        Ok(_) => {
+                            let text = String::from_utf8_lossy(line.as_slice()).into_owned();
+                            line.clear();
+                            if tx.send(LogLine::parse(index, text.as_str())).is_err() {
+                                break; // nothing is listening any more
+                            }
+                        }
+                        // a quiet pod isn't the end of the stream, keep whatever was read
+                        Err(ref e)
+                            if e.kind() == io::ErrorKind::WouldBlock
+                                || e.kind() == io::ErrorKind::TimedOut => {}
+                        Err(_) => break,
+                    }
+                }
+            });
+        }
+        drop(tx); // so rx disconnects once all the readers are done
+
+        let window = Duration::from_millis(LOG_REORDER_WINDOW_MS);
+        let mut buffer: Vec<LogLine> = Vec::new();
+        loop {
+            let done = match rx.recv_timeout(window) {
+                Ok(line) => {
+                    buffer.push(line);
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+            // stable, so lines from one stream with the same time stay in order
+            buffer.sort_by_key(|line| line.time);
+            let now = Instant::now();
+            while !buffer.is_empty() && (done || now.duration_since(buffer[0].arrived) >= window) {
+                let line = buffer.remove(0);
+                if params.timestamps {
+                    if let Some(time) = line.time {
+                        write!(out, "[{}] {} {}\n", prefixes[line.source], time, line.text)?;
+                        continue;
+                    }
+                }
+                write!(out, "[{}] {}\n", prefixes[line.source], line.text)?;
+            }
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Stream the logs of a pod, writing only the lines grep matches (see LogGrep::filter) to out
+    /// as they arrive.  Returns the number of matching lines once the stream ends (which with
+    /// params.follow is when the container stops).
+    #[allow(dead_code)]
+    pub fn grep_logs(
+        &self,
+        namespace: &str,
+        pod: &str,
+        params: &LogParams,
+        grep: &LogGrep,
+        out: &mut dyn Write,
+    ) -> Result<u64, KubeError> {
+        let path = params.path(namespace, pod);
+        let resp = self.get_read(
+            path.as_str(),
+            Some(Duration::new(WATCH_READ_TIMEOUT, 0)),
+        )?;
+        grep.filter(BufReader::new(resp), out)
+    }
+
+    /// Get a Response.  Response implements Read, so this allows for a streaming read (for things
+    /// like printing logs)
+    pub fn get_read(&self, path: &str, timeout: Option<Duration>) -> Result<Response, KubeError> {
+        let resp = self.send_read(path, timeout)?;
+        self.check_resp(resp)
+    }
+
+    /// Send a GET for a streaming read (see get_read), without checking the response status
+    fn send_read(&self, path: &str, timeout: Option<Duration>) -> Result<Response, KubeError> {
+        if timeout.is_some() {
+            let url = try!(self.endpoint.join(path));
+            let mut req = try!(Request::with_connector(
+                Method::Get,
+                url,
+                &HttpsConnector::new(Kluster::make_tlsclient(
+                    &self.cert_opt,
+                    &self.auth,
+                    &self.session_cache,
+                ))
+            ));
+            {
+                // scope for mutable borrow of req
+                let headers = req.headers_mut();
+                if let KlusterAuth::Token(ref token) = self.auth {
+                    headers.set(Authorization(Bearer {
+                        token: token.clone(),
+                    }));
+                } else if let KlusterAuth::UserPass(ref user, ref pass) = self.auth {
+                    headers.set(Authorization(Basic {
+                        username: user.clone(),
+                        password: Some(pass.clone()),
+                    }));
+                }
+            }
+            try!(req.set_read_timeout(timeout));
+            let next = try!(req.start());
+            next.send().map_err(|he| KubeError::from(he))
+        } else {
+            self.send_req(path)
+        }
+    }
+
+    /// Get a serde_json::Value
+    pub fn get_value(&self, path: &str) -> Result<Value, KubeError> {
+        let body = self.get_bytes(path)?;
+        Kluster::parse_json(body.as_slice())
+    }
+
+    /// Get the objects at each of paths, in parallel (see with_max_concurrency), returning each
+    /// path with its result in the same order as paths
+    #[allow(dead_code)]
+    pub fn get_many<T>(&self, paths: &[String]) -> Vec<(String, Result<T, KubeError>)>
+    where
+        for<'de> T: Deserialize<'de>,
+        T: Send,
+    {
+        self.fan_out(paths.to_vec(), |kluster, path| {
+            let result = kluster.get(path.as_str());
+            (path, result)
+        })
+    }
+
+    /// Get a subresource (like "status" or "scale") of the object at path as a Value
+    #[allow(dead_code)]
+    pub fn get_value_subresource(&self, path: &str, subresource: &str) -> Result<Value, KubeError> {
+        self.get_value(subresource_path(path, subresource).as_str())
+    }
+
+    /// Fetch the object at path, of any kind, and summarize its health (see StatusSummary)
+    #[allow(dead_code)]
+    pub fn status_summary(&self, path: &str) -> Result<StatusSummary, KubeError> {
+        let obj = self.get_value(path)?;
+        Ok(StatusSummary::from_value(&obj))
+    }
+
+    /// Get the body at path as text, for endpoints (like /healthz or metrics) that don't return
+    /// json
+    pub fn get_text(&self, path: &str) -> Result<String, KubeError> {
+        let resp = self.send_req(path)?;
+        let resp = self.check_resp(resp)?;
+        self.read_text(resp)
+    }
+
+    /// Get the object at path rendered as YAML
+    #[allow(dead_code)]
+    pub fn get_yaml(&self, path: &str) -> Result<String, KubeError> {
+        let value = self.get_value(path)?;
+        serde_yaml::to_string(&value).map_err(KubeError::from)
+    }
+
+    /// Issue an HTTP POST request to the specified path, with body as json
+    pub fn post(&self, path: &str, body: &str) -> Result<Response, KubeError> {
+        let url = try!(self.endpoint.join(path));
+        let req = self.client
+            .post(url)
+            .header(ContentType::json())
+            .body(Body::BufBody(body.as_bytes(), body.len()));
+        let req = self.add_auth_header(req);
+        req.send().map_err(|he| KubeError::from(he))
+    }
+
+    /// Issue an HTTP PUT request to the specified path, with body as json
+    pub fn put(&self, path: &str, body: &str) -> Result<Response, KubeError> {
+        let url = self.endpoint.join(path)?;
+        let req = self.client
+            .put(url)
+            .header(ContentType::json())
+            .body(Body::BufBody(body.as_bytes(), body.len()));
+        let req = self.add_auth_header(req);
+        req.send().map_err(|he| KubeError::from(he))
+    }
+
+    /// Make the object at path be body (json), replacing it if it exists and creating it if it
+    /// doesn't, and return the resulting object.  Without a resourceVersion in body the replace
+    /// is unconditional, so this is for the "make sure this exists as specified" case.
+    #[allow(dead_code)]
+    pub fn upsert<T>(&self, path: &str, body: &str) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let resp = self.put(path, body)?;
+        let resp = if resp.status == StatusCode::NotFound {
+            self.post(collection_path(path).as_str(), body)?
+        } else {
+            resp
+        };
+        let resp = self.check_resp(resp)?;
+        self.parse_body(resp)
+    }
+
+    /// Issue an HTTP PATCH request to the specified path, with body as a JSON merge patch
+    pub fn merge_patch(&self, path: &str, body: &str) -> Result<Response, KubeError> {
+        let url = try!(self.endpoint.join(path));
+        let req = self.client
+            .request(Method::Patch, url)
+            .header(ContentType(MERGE_PATCH_TYPE.parse().unwrap())) // safe, constant
+            .body(Body::BufBody(body.as_bytes(), body.len()));
+        let req = self.add_auth_header(req);
+        req.send().map_err(|he| KubeError::from(he))
+    }
+
+    /// Open the object at path in editor as YAML, and when the editor exits apply any changes as
+    /// a merge patch, as kubectl edit does.  If the server rejects the changes (or they aren't
+    /// valid YAML) the editor is reopened with the error at the top of the file.  Saving an empty
+    /// file cancels the edit.
+    #[allow(dead_code)]
+    pub fn edit(&self, path: &str, editor: &str) -> Result<EditOutcome, KubeError> {
+        let mut original = self.get_value(path)?;
+        ::values::strip_apply_metadata(&mut original);
+        let tmpdir = TempDir::new("click-edit")?;
+        let file_path = tmpdir.path().join("edit.yaml");
+        let mut contents = serde_yaml::to_string(&original)?;
+        let mut last_failed: Option<String> = None;
+        loop {
+            File::create(&file_path)?.write_all(contents.as_bytes())?;
+            let mut eargs: Vec<&str> = editor.split_whitespace().collect();
+            if eargs.is_empty() {
+                return Err(KubeError::ParseErr("No editor specified".to_owned()));
+            }
+            eargs.push(file_path.to_str().unwrap_or(""));
+            duct::cmd(eargs[0], &eargs[1..]).run()?;
+
+            let mut edited_text = String::new();
+            File::open(&file_path)?.read_to_string(&mut edited_text)?;
+            // drop any error message we put at the top
+            let edited_text: String = edited_text
+                .lines()
+                .skip_while(|l| l.starts_with('#'))
+                .collect::<Vec<&str>>()
+                .join("\n");
+            if edited_text.trim().is_empty() {
+                return Ok(EditOutcome::Cancelled);
+            }
+            if last_failed.as_ref() == Some(&edited_text) {
+                return Err(KubeError::ParseErr(
+                    "Edit failed and the file was not changed, giving up".to_owned(),
+                ));
+            }
+
+            let error = match serde_yaml::from_str::<Value>(edited_text.as_str()) {
+                Ok(ref edited) if *edited == original => return Ok(EditOutcome::Unchanged),
+                Ok(edited) => {
+                    let patch = ::values::create_merge_patch(&original, &edited);
+                    let result = self.merge_patch(path, patch.to_string().as_str())
+                        .and_then(|resp| self.check_resp(resp))
+                        .and_then(|resp| self.parse_body(resp));
+                    match result {
+                        Ok(updated) => return Ok(EditOutcome::Applied(updated)),
+                        Err(KubeError::KubeServerError(msg)) => msg,
+                        Err(e) => return Err(e),
+                    }
+                }
+                Err(e) => format!("Invalid YAML: {}", e),
+            };
+            let mut with_error = String::new();
+            with_error.push_str("# Please fix the error below and save, or save an empty file to\n");
+            with_error.push_str("# cancel the edit:\n");
+            for line in error.lines() {
+                with_error.push_str(format!("# {}\n", line).as_str());
+            }
+            with_error.push_str(edited_text.as_str());
+            contents = with_error;
+            last_failed = Some(edited_text);
+        }
+    }
+
+    /// Issue an HTTP DELETE request to the specified path
+    pub fn delete(&self, path: &str, body: Option<String>) -> Result<Response, KubeError> {
+        let url = try!(self.endpoint.join(path));
+        let req = self.client.delete(url);
+        let req = match body {
+            Some(ref b) => {
+                let hyper_body = Body::BufBody(b.as_bytes(), b.len());
+                req.body(hyper_body)
+            },
+            None => req
+        };
+        let req = self.add_auth_header(req);
+        req.send().map_err(|he| KubeError::from(he))
+    }
+
+    /// Ask the server to process a DELETE of path, including validation, admission and working out
+    /// what would cascade, but without actually deleting anything (dryRun=All).  The response
+    /// body is what a real delete would return.
+    pub fn delete_dry_run(&self, path: &str, body: Option<String>) -> Result<Response, KubeError> {
+        self.delete(add_query_param(path, "dryRun=All").as_str(), body)
+    }
+
+    /// Watch the resource(s) at path, calling on_event for each event received.  The watch ends
+    /// when on_event returns false, the server closes the stream, or nothing arrives for
+    /// WATCH_READ_TIMEOUT seconds.  If the server rejects the watch because the resourceVersion
+    /// is too old (410 Gone), the Status it sent is passed to on_event as an Error event, just as
+    /// if it had been sent in the stream.  The server being overloaded or unavailable is an
+    /// Unavailable error (see check_watch_resp).
+    pub fn watch<T, F>(&self, path: &str, mut on_event: F) -> Result<(), KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+        F: FnMut(WatchEvent<T>) -> bool,
+    {
+        let path = add_query_param(path, "watch=true");
+        let resp = self.send_read(
+            path.as_str(),
+            Some(Duration::new(WATCH_READ_TIMEOUT, 0)),
+        )?;
+        if resp.status == StatusCode::Gone {
+            self.record_warnings(&resp);
+            let mut status = self.parse_body::<Value>(resp).unwrap_or(Value::Null);
+            if !status.is_object() {
+                status = json!({ "kind": "Status", "status": "Failure" });
+            }
+            status["code"] = json!(410);
+            on_event(WatchEvent::Error(status));
+            return Ok(());
+        }
+        let resp = self.check_watch_resp(resp)?;
+        let mut reader = BufReader::new(resp);
+        let mut line = String::new();
+        loop {
+            match reader.read_line(&mut line) {
+                Ok(0) => return Ok(()),
+                Ok(_) if !line.ends_with('\n') => {
+                    // every event ends with a newline, so the connection dropped part way through
+                    return Err(KubeError::Io(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Watch stream ended part way through an event",
+                    )));
+                }
+                Ok(_) => {
+                    let event = WatchEvent::from_line(line.as_str())?;
+                    line.clear();
+                    if !on_event(event) {
+                        return Ok(());
+                    }
+                }
+                Err(ref e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    return Ok(());
+                }
+                Err(e) => return Err(KubeError::from(e)),
+            }
+        }
+    }
+
+    /// Get path as get does, but with the server being unavailable an error a watch can retry
+    /// (see check_watch_resp)
+    fn get_for_watch<T>(&self, path: &str) -> Result<T, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let resp = self.send_req(path)?;
+        let mut resp = self.check_watch_resp(resp)?;
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        Kluster::parse_json(body.as_slice())
+    }
+
+    /// List the collection at path, calling on_event with an Added event for each item, and return
+    /// the resourceVersion of the list so a watch can be started from it
+    fn relist<T, F>(&self, path: &str, on_event: &mut F) -> Result<Option<String>, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+        F: FnMut(WatchEvent<T>) -> bool,
+    {
+        let list: Value = self.get_for_watch(path)?;
+        if let Some(items) = list.get("items").and_then(|i| i.as_array()) {
+            for item in items.iter() {
+                let obj: T = serde_json::from_value(item.clone())?;
+                if !on_event(WatchEvent::Added(obj)) {
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(::values::val_str("/metadata/resourceVersion", &list, "").into_owned()))
+    }
+
+    /// Watch the collection at path indefinitely.  The collection is first listed, with each
+    /// existing item passed to on_event as an Added event, and then watched from the list's
+    /// resourceVersion.  If the watch drops it is re-established from the last resourceVersion
+    /// seen, backing off (with jitter) if the server can't be reached, is unavailable, or keeps
+    /// closing the watch without sending anything.  If the server says our
+    /// resourceVersion is too old (410 Gone) we relist, so on_event may see Added events for
+    /// objects it has seen before.  Returns once on_event returns false, or with the error if the
+    /// server refuses the request (bad credentials, no permission, not found) or sends something
+    /// we can't decode, since retrying won't help with those.
+    pub fn watch_forever<T, F>(&self, path: &str, mut on_event: F) -> Result<(), KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+        F: FnMut(WatchEvent<T>) -> bool,
+    {
+        let mut resource_version: Option<String> = None;
+        let mut backoff = WATCH_MIN_BACKOFF;
+        let mut stop = false;
+        while !stop {
+            if resource_version.is_none() {
+                match self.relist(path, &mut on_event) {
+                    Ok(Some(rv)) => {
+                        resource_version = Some(rv);
+                        backoff = WATCH_MIN_BACKOFF;
+                    }
+                    Ok(None) => return Ok(()), // on_event asked us to stop
+                    Err(e) => {
+                        if !is_retryable_watch_error(&e) {
+                            return Err(e);
+                        }
+                        thread::sleep(with_jitter(backoff));
+                        backoff = cmp::min(backoff * 2, WATCH_MAX_BACKOFF);
+                        continue;
+                    }
+                }
+            }
+            let watch_path = add_query_param(
+                path,
+                format!("resourceVersion={}", resource_version.as_ref().unwrap()).as_str(),
+            );
+            let mut gone = false;
+            let mut bad_event = None;
+            let mut got_event = false;
+            let started = Instant::now();
+            let res = self.watch(watch_path.as_str(), |event: WatchEvent<Value>| {
+                got_event = true;
+                backoff = WATCH_MIN_BACKOFF;
+                if let WatchEvent::Error(ref status) = event {
+                    if ::values::val_u64("/code", status, 0) == 410 {
+                        gone = true;
+                        return false;
+                    }
+                }
+                let rv = match event {
+                    WatchEvent::Added(ref o)
+                    | WatchEvent::Modified(ref o)
+                    | WatchEvent::Deleted(ref o) => {
+                        ::values::val_str_opt("/metadata/resourceVersion", o)
+                    }
+                    WatchEvent::Error(_) => None,
+                };
+                match typed_event(event) {
+                    Ok(typed) => {
+                        if let Some(rv) = rv {
+                            resource_version = Some(rv);
+                        }
+                        stop = !on_event(typed);
+                        !stop
+                    }
+                    Err(e) => {
+                        bad_event = Some(e);
+                        false
+                    }
+                }
+            });
+            if let Some(e) = bad_event {
+                return Err(e);
+            }
+            match res {
+                Err(e) => {
+                    if !is_retryable_watch_error(&e) {
+                        return Err(e);
+                    }
+                    thread::sleep(with_jitter(backoff));
+                    backoff = cmp::min(backoff * 2, WATCH_MAX_BACKOFF);
+                }
+                Ok(()) => if gone {
+                    resource_version = None;
+                } else if !got_event && !stop
+                    && started.elapsed() < Duration::from_secs(WATCH_MAX_BACKOFF)
+                {
+                    // closed early with nothing sent, rather than timing out on a quiet
+                    // collection, so don't hammer a server that keeps doing that
+                    thread::sleep(with_jitter(backoff));
+                    backoff = cmp::min(backoff * 2, WATCH_MAX_BACKOFF);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    /// Wait for the specified job to complete or fail, returning how it ended.  Returns a Timeout
+    /// error if it's still running after timeout.
+    #[allow(dead_code)]
+    pub fn wait_for_job(
+        &self,
+        namespace: &str,
+        name: &str,
+        timeout: Duration,
+    ) -> Result<JobOutcome, KubeError> {
+        let deadline = Instant::now() + timeout;
+        let mut outcome = None;
+        let mut server_err = None;
+        // like watch_rollout, each watch starts by sending the current job
+        while outcome.is_none() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(KubeError::Kube(KubeErrNo::Timeout));
+            }
+            // round up so we never ask for a zero second (unlimited) watch
+            let remaining = (deadline - now).as_secs() + 1;
+            let path = format!(
+                "/apis/batch/v1/namespaces/{}/jobs?fieldSelector=metadata.name={}",
+                namespace, name
+            );
+            let path = add_query_param(&path, format!("timeoutSeconds={}", remaining).as_str());
+            self.watch(path.as_str(), |event: WatchEvent<Job>| {
+                match event {
+                    WatchEvent::Added(job) | WatchEvent::Modified(job) => {
+                        outcome = job.outcome();
+                        outcome.is_none()
+                    }
+                    WatchEvent::Deleted(_) => {
+                        server_err = Some(KubeError::KubeServerError(format!(
+                            "Job {} was deleted",
+                            name
+                        )));
+                        false
+                    }
+                    WatchEvent::Error(status) => {
+                        server_err = Some(KubeError::KubeServerError(
+                            ::values::val_str("/message", &status, "Unknown watch error")
+                                .into_owned(),
+                        ));
+                        false
+                    }
+                }
+            })?;
+            if let Some(e) = server_err.take() {
+                return Err(e);
+            }
+        }
+        Ok(outcome.unwrap()) // safe, loop only ends when it's set
+    }
+
+    /// Watch events in namespace (or all namespaces if it's None), calling on_event for each new
+    /// or updated event until it returns false.  Events that can't be parsed (like ones with no
+    /// lastTimestamp) are skipped.
+    pub fn watch_events<F>(&self, namespace: Option<&str>, mut on_event: F) -> Result<(), KubeError>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        let path = GroupVersionResource::new("", "v1", "events").path(namespace, None);
+        // watch Values, since a typed watch would keep reconnecting at an unparseable event
+        self.watch_forever(path.as_str(), |event: WatchEvent<Value>| match event {
+            WatchEvent::Added(obj) | WatchEvent::Modified(obj) => {
+                match serde_json::from_value::<Event>(obj) {
+                    Ok(event) => on_event(&event),
+                    Err(_) => true,
+                }
+            }
+            WatchEvent::Deleted(_) | WatchEvent::Error(_) => true,
+        })
+    }
+
+    /// Watch events (as watch_events does) forever, keeping the most recent in ring
+    #[allow(dead_code)]
+    pub fn fill_event_ring(
+        &self,
+        namespace: Option<&str>,
+        ring: &EventRing,
+    ) -> Result<(), KubeError> {
+        self.watch_events(namespace, |event| {
+            ring.push(event.clone());
+            true
+        })
+    }
+
+    /// Watch the collection at path, and each time an object changes call on_diff with its key
+    /// (namespace/name) and the fields that changed since the last version seen.  Fields that
+    /// change on every update (resourceVersion, managedFields) are ignored.  Keeps watching until
+    /// on_diff returns false, or an out of order event is seen (which is returned as a
+    /// WatchInconsistency error, since the diffs can't be trusted after it).
+    #[allow(dead_code)]
+    pub fn watch_diff<F>(&self, path: &str, mut on_diff: F) -> Result<(), KubeError>
+    where
+        F: FnMut(&str, &[FieldDiff]) -> bool,
+    {
+        let mut reflector = Reflector::new();
+        let mut inconsistency = None;
+        self.watch_forever(path, |event: WatchEvent<Value>| {
+            let is_delete = match event {
+                WatchEvent::Deleted(_) => true,
+                _ => false,
+            };
+            match reflector.apply(event) {
+                // objects are re-added after a relist, so diff those against what we had too
+                Ok(Some((ref key, Some(ref previous)))) if !is_delete => {
+                    let current = reflector.get(key).unwrap(); // safe, was just inserted
+                    let diffs: Vec<FieldDiff> = ::values::diff_values(previous, current)
+                        .into_iter()
+                        .filter(|d| {
+                            d.path != "/metadata/resourceVersion"
+                                && !d.path.starts_with("/metadata/managedFields")
+                        })
+                        .collect();
+                    diffs.is_empty() || on_diff(key, diffs.as_slice())
+                }
+                Err(e) => {
+                    inconsistency = Some(e);
+                    false
+                }
+                _ => true,
+            }
+        })?;
+        match inconsistency {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Watch the collection at path indefinitely (see watch_forever), calling the method of
+    /// watcher for each kind of event, until one returns false.  Objects that already exist
+    /// are passed to on_added first, and may be again if the watch has to relist.
+    #[allow(dead_code)]
+    pub fn register_watcher<T>(
+        &self,
+        path: &str,
+        mut watcher: Box<dyn Watcher<T>>,
+    ) -> Result<(), KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        self.watch_forever(path, |event: WatchEvent<T>| {
+            dispatch_event(watcher.as_mut(), &event)
+        })
+    }
+
+    /// Keep a Table of the collection at path current: on_update is called with the table, and
+    /// again with a freshly fetched one whenever objects in the collection change, until it
+    /// returns false.  Bursts of changes (like a rollout) are debounced into a single refresh,
+    /// and modifications that don't change anything but the resourceVersion are ignored.
+    #[allow(dead_code)]
+    pub fn watch_table<F>(&self, path: &str, mut on_update: F) -> Result<(), KubeError>
+    where
+        F: FnMut(&Table) -> bool,
+    {
+        let table = self.get_table(path)?;
+        if !on_update(&table) {
+            return Ok(());
+        }
+        let resource_version = table.metadata.and_then(|m| m.resource_version);
+        let stop = AtomicBool::new(false);
+        let (changed, changes) = mpsc::channel();
+        thread::scope(|scope| {
+            let watcher = scope.spawn(|| {
+                self.watch_table_changes(path, resource_version, &stop, changed)
+            });
+            let mut refreshed = Ok(());
+            // the watcher only stops on its own if something went wrong
+            while wait_for_changes(&changes) {
+                match self.get_table(path) {
+                    Ok(ref table) if on_update(table) => {}
+                    Ok(_) => break,
+                    Err(e) => {
+                        refreshed = Err(e);
+                        break;
+                    }
+                }
+            }
+            stop.store(true, Ordering::SeqCst);
+            drop(changes);
+            let watched = watcher.join().unwrap_or(Ok(()));
+            refreshed.and(watched)
+        })
+    }
+
+    /// The watching half of watch_table: watch path from resource_version, sending on changed
+    /// each time an object is added, deleted or really modified, until stop is set or changed's
+    /// receiver goes away.  Errors worth retrying (see is_retryable_watch_error) are, with
+    /// backoff, but others (like not being allowed to watch path) are returned.
+    fn watch_table_changes(
+        &self,
+        path: &str,
+        mut resource_version: Option<String>,
+        stop: &AtomicBool,
+        changed: mpsc::Sender<()>,
+    ) -> Result<(), KubeError> {
+        let mut reflector = Reflector::new();
+        let mut backoff = WATCH_MIN_BACKOFF;
+        while !stop.load(Ordering::SeqCst) {
+            let rv = match resource_version.take() {
+                Some(rv) => rv,
+                None => {
+                    // our version is too old, so find the current one.  Anything that changed in
+                    // between was missed, so refresh too.
+                    let list_path = add_query_param(path, "limit=1");
+                    match self.get_for_watch::<ObjectList<Value>>(list_path.as_str()) {
+                        Ok(list) => {
+                            if changed.send(()).is_err() {
+                                return Ok(());
+                            }
+                            list.metadata.and_then(|m| m.resource_version).ok_or_else(|| {
+                                KubeError::ParseErr(format!("No resourceVersion for {}", path))
+                            })?
+                        }
+                        Err(e) => {
+                            if !is_retryable_watch_error(&e) {
+                                return Err(e);
+                            }
+                            if !sleep_unless_stopped(with_jitter(backoff), stop) {
+                                return Ok(());
+                            }
+                            backoff = cmp::min(backoff * 2, WATCH_MAX_BACKOFF);
+                            continue;
+                        }
+                    }
+                }
+            };
+            let watch_path = add_query_param(
+                path,
+                format!("resourceVersion={}&timeoutSeconds={}", rv, TABLE_WATCH_SECS).as_str(),
+            );
+            let mut latest = Some(rv);
+            let res = self.watch(watch_path.as_str(), |event: WatchEvent<Value>| {
+                if let WatchEvent::Error(ref status) = event {
+                    if ::values::val_u64("/code", status, 0) == 410 {
+                        latest = None;
+                        return false;
+                    }
+                }
+                let is_modify = match event {
+                    WatchEvent::Added(ref o)
+                    | WatchEvent::Modified(ref o)
+                    | WatchEvent::Deleted(ref o) => {
+                        if let Some(rv) = ::values::val_str_opt("/metadata/resourceVersion", o) {
+                            latest = Some(rv);
+                        }
+                        match event {
+                            WatchEvent::Modified(_) => true,
+                            _ => false,
+                        }
+                    }
+                    WatchEvent::Error(_) => return true,
+                };
+                let relevant = match reflector.apply(event) {
+                    Ok(Some((ref key, Some(ref previous)))) if is_modify => {
+                        let current = reflector.get(key).unwrap(); // safe, was just inserted
+                        ::values::diff_values(previous, current).iter().any(|d| {
+                            d.path != "/metadata/resourceVersion"
+                                && !d.path.starts_with("/metadata/managedFields")
+                        })
+                    }
+                    Ok(_) => true,
+                    // a stale event, nothing has really changed
+                    Err(_) => false,
+                };
+                if relevant && changed.send(()).is_err() {
+                    return false;
+                }
+                !stop.load(Ordering::SeqCst)
+            });
+            match res {
+                Ok(()) => backoff = WATCH_MIN_BACKOFF,
+                Err(e) => {
+                    if !is_retryable_watch_error(&e) {
+                        return Err(e);
+                    }
+                    if !sleep_unless_stopped(with_jitter(backoff), stop) {
+                        return Ok(());
+                    }
+                    backoff = cmp::min(backoff * 2, WATCH_MAX_BACKOFF);
+                }
+            }
+            resource_version = latest;
+        }
+        Ok(())
+    }
+
+    /// Watch all nodes, calling on_change with a node each time its Ready condition flips (so
+    /// nodes that are flapping can be spotted).  Nodes are not reported when first seen, only
+    /// when their readiness differs from the last time we saw them.  Keeps watching until
+    /// on_change returns false.
+    #[allow(dead_code)]
+    pub fn watch_nodes<F>(&self, mut on_change: F) -> Result<(), KubeError>
+    where
+        F: FnMut(&Node) -> bool,
+    {
+        let mut ready: HashMap<String, bool> = HashMap::new();
+        self.watch_forever("/api/v1/nodes", |event: WatchEvent<Node>| match event {
+            WatchEvent::Added(node) | WatchEvent::Modified(node) => {
+                let now_ready = node.is_ready();
+                match ready.insert(node.metadata.name.clone(), now_ready) {
+                    Some(was_ready) if was_ready != now_ready => on_change(&node),
+                    _ => true,
+                }
+            }
+            WatchEvent::Deleted(node) => {
+                ready.remove(&node.metadata.name);
+                true
+            }
+            WatchEvent::Error(_) => true,
+        })
+    }
+
+    /// Watch the specified deployment, calling on_update with its rollout status each time it
+    /// changes.  Returns the final status once the rollout is complete or has stalled.
+    #[allow(dead_code)]
+    pub fn watch_rollout<F>(
+        &self,
+        namespace: &str,
+        deployment: &str,
+        mut on_update: F,
+    ) -> Result<RolloutStatus, KubeError>
+    where
+        F: FnMut(&RolloutStatus),
+    {
+        let path = format!(
+            "/apis/apps/v1/namespaces/{}/deployments?fieldSelector=metadata.name={}",
+            namespace, deployment
+        );
+        let mut last = RolloutStatus::Pending;
+        let mut server_err = None;
+        // A watch with no resource version starts by sending the current object, so each time
+        // around we'll get the current status even if the previous watch timed out
+        while !last.is_finished() {
+            self.watch(path.as_str(), |event: WatchEvent<Deployment>| {
+                match event {
+                    WatchEvent::Added(dep) | WatchEvent::Modified(dep) => {
+                        let status = dep.rollout_status();
+                        if status != last {
+                            on_update(&status);
+                            last = status;
+                        }
+                        !last.is_finished()
+                    }
+                    WatchEvent::Deleted(_) => {
+                        server_err = Some(KubeError::KubeServerError(format!(
+                            "Deployment {} was deleted",
+                            deployment
+                        )));
+                        false
+                    }
+                    WatchEvent::Error(status) => {
+                        server_err = Some(KubeError::KubeServerError(
+                            ::values::val_str("/message", &status, "Unknown watch error")
+                                .into_owned(),
+                        ));
+                        false
+                    }
+                }
+            })?;
+            if let Some(e) = server_err.take() {
+                return Err(e);
+            }
+        }
+        Ok(last)
+    }
+
+    /// Delete all pods in the Failed phase (which includes evicted pods), in the specified
+    /// namespace or across all namespaces if None.  Pods are listed a page at a time, and a
+    /// result is returned for each pod so partial failures are visible.
+    #[allow(dead_code)]
+    pub fn prune_failed_pods(
+        &self,
+        namespace: Option<&str>,
+    ) -> Result<Vec<PodDeleteResult>, KubeError> {
+        let base = match namespace {
+            Some(ns) => format!("/api/v1/namespaces/{}/pods", ns),
+            None => "/api/v1/pods".to_owned(),
+        };
+        let base = add_query_param(base.as_str(), "fieldSelector=status.phase=Failed&limit=250");
+        let mut failed_pods = Vec::new();
+        let mut cont: Option<String> = None;
+        loop {
+            let path = match cont {
+                Some(ref c) => add_query_param(base.as_str(), format!("continue={}", c).as_str()),
+                None => base.clone(),
+            };
+            let list: PodList = self.get(path.as_str())?;
+            failed_pods.extend(list.items.into_iter());
+            cont = list.metadata.and_then(|m| m.cont).and_then(|c| {
+                if c.is_empty() {
+                    None
+                } else {
+                    Some(c)
+                }
+            });
+            if cont.is_none() {
+                break;
+            }
+        }
+
+        Ok(failed_pods
+            .into_iter()
+            // the field selector should have done this, but a server that ignored it mustn't get
+            // running pods deleted
+            .filter(|pod| pod.status.phase == "Failed")
+            .map(|pod| {
+                let ns = pod.metadata.namespace.unwrap_or_else(|| "default".to_owned());
+                let path = format!("/api/v1/namespaces/{}/pods/{}", ns, pod.metadata.name);
+                let result = self.delete(path.as_str(), None)
+                    .and_then(|resp| self.check_resp(resp))
+                    .map(|_| ());
+                PodDeleteResult {
+                    namespace: ns,
+                    name: pod.metadata.name,
+                    result: result,
+                }
+            })
+            .collect())
+    }
+
+    /// Mint a token for the specified service account using the TokenRequest api.  The token is
+    /// bound to the given audiences (the api server's default audience if empty) and expires
+    /// after expiration_seconds (the server may choose a shorter time).
+    #[allow(dead_code)]
+    pub fn request_token(
+        &self,
+        namespace: &str,
+        service_account: &str,
+        audiences: &[&str],
+        expiration_seconds: i64,
+    ) -> Result<TokenRequestStatus, KubeError> {
+        let path = format!(
+            "/api/v1/namespaces/{}/serviceaccounts/{}/token",
+            namespace, service_account
+        );
+        let body = json!({
+            "kind": "TokenRequest",
+            "apiVersion": "authentication.k8s.io/v1",
+            "spec": {
+                "audiences": audiences,
+                "expirationSeconds": expiration_seconds,
+            }
+        });
+        let resp = self.post(path.as_str(), body.to_string().as_str())?;
+        let resp = self.check_resp(resp)?;
+        let token_request: TokenRequest = self.parse_body(resp)?;
+        Ok(token_request.status)
+    }
+
+    /// Find the pods in namespace that reference the config map or secret with the given name,
+    /// i.e. the pods that could be affected by changing it
+    #[allow(dead_code)]
+    pub fn consumers_of(
+        &self,
+        namespace: &str,
+        kind: ConfigRefKind,
+        name: &str,
+    ) -> Result<Vec<Pod>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods", namespace);
+        let list: PodList = self.get(path.as_str())?;
+        Ok(list.items
+            .into_iter()
+            .filter(|pod| pod.spec.references(kind, name))
+            .collect())
+    }
+
+    /// Compare the number of pods that haven't terminated on node with the number it allows
+    #[allow(dead_code)]
+    pub fn node_pod_pressure(&self, node: &str) -> Result<PodPressure, KubeError> {
+        let node_obj: Node = self.get(format!("/api/v1/nodes/{}", node).as_str())?;
+        let allocatable = node_obj.allocatable("pods").ok_or_else(|| {
+            KubeError::ParseErr(format!("Node {} does not report allocatable pods", node))
+        })?;
+        let selector = format!(
+            "spec.nodeName={},status.phase!=Succeeded,status.phase!=Failed",
+            node
+        );
+        let path = add_query_param(
+            "/api/v1/pods",
+            format!("fieldSelector={}", encode_query_value(selector.as_str())).as_str(),
+        );
+        let pods: ObjectList<PartialObject> = self.get(path.as_str())?;
+        Ok(PodPressure {
+            node: node.to_owned(),
+            pods: pods.items.len() as u64,
+            allocatable: allocatable.as_f64() as u64,
+        })
+    }
+
+    /// Summarize what's in namespace: pods by phase, counts of deployments, services and pvcs,
+    /// and the cpu and memory requested by its pods.  The lists are fetched in parallel (see
+    /// with_max_concurrency).  Only failing to list pods is an error, other kinds that can't be
+    /// listed get a count of None.
+    #[allow(dead_code)]
+    pub fn namespace_summary(&self, namespace: &str) -> Result<NamespaceSummary, KubeError> {
+        let paths = vec![
+            GroupVersionResource::new("", "v1", "pods"),
+            GroupVersionResource::new("apps", "v1", "deployments"),
+            GroupVersionResource::new("", "v1", "services"),
+            GroupVersionResource::new("", "v1", "persistentvolumeclaims"),
+        ].into_iter()
+            .map(|gvr| gvr.path(Some(namespace), None))
+            .collect();
+        let mut lists = self.fan_out(paths, |kluster, path| {
+            kluster.get::<ObjectList<Value>>(path.as_str())
+        }).into_iter();
+        let pods: Vec<Pod> = lists
+            .next()
+            .unwrap()? // safe, one result per path
+            .items
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<Pod>, serde_json::Error>>()?;
+        let mut counts = lists.map(|list| list.ok().map(|l| l.items.len()));
+
+        let mut pods_by_phase = HashMap::new();
+        let mut cpu_requests = Quantity::zero();
+        let mut memory_requests = Quantity::zero();
+        for pod in pods.iter() {
+            *pods_by_phase.entry(pod.status.phase.clone()).or_insert(0) += 1;
+            if pod.status.phase == "Succeeded" || pod.status.phase == "Failed" {
+                continue;
+            }
+            for container in pod.spec.containers.iter() {
+                cpu_requests += container.request("cpu").unwrap_or_default();
+                memory_requests += container.request("memory").unwrap_or_default();
+            }
+        }
+        Ok(NamespaceSummary {
+            namespace: namespace.to_owned(),
+            pods_by_phase: pods_by_phase,
+            deployments: counts.next().and_then(|c| c),
+            services: counts.next().and_then(|c| c),
+            persistent_volume_claims: counts.next().and_then(|c| c),
+            cpu_requests: cpu_requests,
+            memory_requests: memory_requests,
+        })
+    }
+
+    /// Get the object at path, or None if it doesn't exist
+    pub fn get_opt<T>(&self, path: &str) -> Result<Option<T>, KubeError>
+    where
+        for<'de> T: Deserialize<'de>,
+    {
+        let resp = self.send_req(path)?;
+        if resp.status == StatusCode::NotFound {
+            return Ok(None);
+        }
+        let mut resp = self.check_resp(resp)?;
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        Kluster::parse_json(body.as_slice()).map(Some)
+    }
+
+    /// Follow each route of an ingress to the service it sends traffic to and the endpoints
+    /// (and so pods) backing that service.  A route with no ready endpoints is why an ingress
+    /// returns 503s.
+    #[allow(dead_code)]
+    pub fn trace_ingress(
+        &self,
+        namespace: &str,
+        name: &str,
+    ) -> Result<Vec<IngressRoute>, KubeError> {
+        let path = format!(
+            "/apis/networking.k8s.io/v1/namespaces/{}/ingresses/{}",
+            namespace, name
+        );
+        let ingress: Ingress = self.get(path.as_str())?;
+
+        let mut routes = Vec::new();
+        if let Some(ref backend) = ingress.spec.default_backend {
+            routes.push((None, None, backend));
+        }
+        for rule in ingress.spec.rules.iter().flat_map(|r| r.iter()) {
+            for http_path in rule.http.iter().flat_map(|h| h.paths.iter()) {
+                routes.push((rule.host.clone(), http_path.path.clone(), &http_path.backend));
+            }
+        }
+
+        let mut traces = Vec::new();
+        for (host, path, backend) in routes.into_iter() {
+            let service = backend.service.as_ref();
+            let port = service.and_then(|s| s.port.as_ref()).and_then(|p| {
+                p.number
+                    .map(|n| n.to_string())
+                    .or_else(|| p.name.clone())
+            });
+            let (service_found, endpoints) = match service {
+                Some(svc) => self.trace_service(namespace, svc.name.as_str())?,
+                None => (false, Vec::new()),
+            };
+            traces.push(IngressRoute {
+                host: host,
+                path: path,
+                service: service.map(|s| s.name.clone()),
+                port: port,
+                service_found: service_found,
+                endpoints: endpoints,
+            });
+        }
+        Ok(traces)
+    }
+
+    /// Check that a service exists and find the endpoints backing it
+    fn trace_service(
+        &self,
+        namespace: &str,
+        service: &str,
+    ) -> Result<(bool, Vec<EndpointTrace>), KubeError> {
+        let path = format!("/api/v1/namespaces/{}/services/{}", namespace, service);
+        if self.get_opt::<Service>(path.as_str())?.is_none() {
+            return Ok((false, Vec::new()));
+        }
+        let path = format!(
+            "/apis/discovery.k8s.io/v1/namespaces/{}/endpointslices?labelSelector={}",
+            namespace,
+            encode_query_value(format!("kubernetes.io/service-name={}", service).as_str())
+        );
+        let slices: ObjectList<EndpointSlice> = self.get(path.as_str())?;
+        let mut endpoints = Vec::new();
+        for endpoint in slices
+            .items
+            .iter()
+            .flat_map(|s| s.endpoints.iter().flat_map(|e| e.iter()))
+        {
+            let pod = endpoint
+                .target_ref
+                .as_ref()
+                .filter(|r| r.kind.as_ref().map(|k| k == "Pod").unwrap_or(false))
+                .and_then(|r| r.name.clone());
+            for address in endpoint.addresses.iter() {
+                endpoints.push(EndpointTrace {
+                    address: address.clone(),
+                    pod: pod.clone(),
+                    ready: endpoint.is_ready(),
+                });
+            }
+        }
+        Ok((true, endpoints))
+    }
+
+    /// Work out which image pull secrets the kubelet will try for a pod: those in its spec, plus
+    /// those of its service account, and check each one exists.  Missing secrets are a common
+    /// cause of ImagePullBackOff.
+    #[allow(dead_code)]
+    pub fn effective_pull_secrets(
+        &self,
+        namespace: &str,
+        pod: &str,
+    ) -> Result<Vec<PullSecret>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods/{}", namespace, pod);
+        let pod: Pod = self.get(path.as_str())?;
+        let sa_name = pod.spec
+            .service_account_name
+            .clone()
+            .unwrap_or_else(|| "default".to_owned());
+        let path = format!(
+            "/api/v1/namespaces/{}/serviceaccounts/{}",
+            namespace, sa_name
+        );
+        let sa: Option<ServiceAccount> = self.get_opt(path.as_str())?;
+
+        let from_pod = pod.spec
+            .image_pull_secrets
+            .iter()
+            .flat_map(|s| s.iter())
+            .map(|r| (r, None));
+        let from_sa = sa.iter()
+            .flat_map(|sa| sa.image_pull_secrets.iter().flat_map(|s| s.iter()))
+            .map(|r| (r, Some(sa_name.clone())));
+        let mut secrets: Vec<PullSecret> = Vec::new();
+        for (reference, service_account) in from_pod.chain(from_sa) {
+            let name = match reference.name {
+                Some(ref name) => name.clone(),
+                None => continue,
+            };
+            if secrets.iter().any(|s| s.name == name) {
+                continue;
+            }
+            let path = format!("/api/v1/namespaces/{}/secrets/{}", namespace, name);
+            let exists = self.get_opt::<Value>(path.as_str())
+                .map(|secret| secret.is_some())
+                .ok();
+            secrets.push(PullSecret {
+                name: name,
+                service_account: service_account,
+                exists: exists,
+            });
+        }
+        Ok(secrets)
+    }
+
+    /// Explain why the object at path hasn't gone away after being deleted, by listing the
+    /// finalizers still pending and its owners.  Returns None if the object isn't being deleted.
+    /// See remove_finalizers for forcing the issue.
+    #[allow(dead_code)]
+    pub fn deletion_blockers(&self, path: &str) -> Result<Option<DeletionBlockers>, KubeError> {
+        let obj: PartialObject = self.get(path)?;
+        let metadata = obj.metadata;
+        Ok(metadata.deletion_timestamp.map(|deletion_timestamp| DeletionBlockers {
+            deletion_timestamp: deletion_timestamp,
+            finalizers: metadata.finalizers.unwrap_or_default(),
+            owners: metadata.owner_refs.unwrap_or_default(),
+        }))
+    }
+
+    /// Remove all finalizers from the object at path, so a deleted object stuck waiting on them
+    /// is removed immediately.  This skips whatever cleanup they were for (e.g. releasing a
+    /// volume or load balancer), so it can leave resources behind.  Returns the updated object.
+    #[allow(dead_code)]
+    pub fn remove_finalizers(&self, path: &str) -> Result<Value, KubeError> {
+        let body = json!({ "metadata": { "finalizers": null } });
+        let resp = self.merge_patch(path, body.to_string().as_str())?;
+        let resp = self.check_resp(resp)?;
+        self.parse_body(resp)
+    }
+
+    /// Pause or resume the rollout of the deployment at path, returning the updated deployment.
+    /// Pausing a rollout part way through leaves the old and new replica sets as they are, so
+    /// the new pods can be inspected before the rest are rolled out.
+    #[allow(dead_code)]
+    pub fn set_deployment_paused(&self, path: &str, paused: bool) -> Result<Deployment, KubeError> {
+        let body = paused_patch(paused);
+        let resp = self.merge_patch(path, body.to_string().as_str())?;
+        let resp = self.check_resp(resp)?;
+        self.parse_body(resp)
+    }
+
+    /// Work out what a subject can do: find the RoleBindings and ClusterRoleBindings that grant
+    /// it a role (directly, or through the groups it's known to be in, see rbac_identities) and
+    /// flatten the rules of those roles into a list of permissions.  subject_kind is "User",
+    /// "Group" or "ServiceAccount", and a service account is named "namespace/name".  Bindings
+    /// to roles that don't exist are skipped, and listed in the result's skipped.
+    #[allow(dead_code)]
+    pub fn rbac_for(
+        &self,
+        subject_kind: &str,
+        subject_name: &str,
+    ) -> Result<SubjectPermissions, KubeError> {
+        let ids = rbac_identities(subject_kind, subject_name)?;
+        let cluster_bindings: ObjectList<RoleBinding> =
+            self.get("/apis/rbac.authorization.k8s.io/v1/clusterrolebindings")?;
+        let bindings: ObjectList<RoleBinding> =
+            self.get("/apis/rbac.authorization.k8s.io/v1/rolebindings")?;
+        let cluster_roles: ObjectList<Role> =
+            self.get("/apis/rbac.authorization.k8s.io/v1/clusterroles")?;
+        let cluster_roles: HashMap<String, Role> = cluster_roles
+            .items
+            .into_iter()
+            .map(|role| (role.metadata.name.clone(), role))
+            .collect();
+        let roles: ObjectList<Role> = self.get("/apis/rbac.authorization.k8s.io/v1/roles")?;
+        let roles: HashMap<String, Role> = roles
+            .items
+            .into_iter()
+            .map(|role| {
+                let ns = role.metadata.namespace.clone().unwrap_or_default();
+                (format!("{}/{}", ns, role.metadata.name), role)
+            })
+            .collect();
+
+        let mut permissions = Vec::new();
+        let mut skipped = Vec::new();
+        let all_bindings = cluster_bindings
+            .items
+            .iter()
+            .map(|b| (true, b))
+            .chain(bindings.items.iter().map(|b| (false, b)));
+        for (cluster_wide, binding) in all_bindings {
+            let binding_ns = binding.metadata.namespace.as_ref().map(|ns| ns.as_str());
+            if !binding.subjects.iter().any(|s| subject_is(s, binding_ns, &ids)) {
+                continue;
+            }
+            let (binding_desc, namespace) = if cluster_wide {
+                (format!("ClusterRoleBinding {}", binding.metadata.name), None)
+            } else {
+                let ns = binding_ns.unwrap_or("");
+                (
+                    format!("RoleBinding {}/{}", ns, binding.metadata.name),
+                    Some(ns.to_owned()),
+                )
+            };
+            let role_name = binding.role_ref.name.as_str();
+            let (role_desc, role) = if binding.role_ref.kind == "ClusterRole" {
+                (format!("ClusterRole {}", role_name), cluster_roles.get(role_name))
+            } else {
+                let key = format!("{}/{}", binding_ns.unwrap_or(""), role_name);
+                let role = roles.get(&key);
+                (format!("Role {}", key), role)
+            };
+            match role {
+                Some(role) => for rule in role.rules.iter() {
+                    permissions.push(Permission {
+                        namespace: namespace.clone(),
+                        binding: binding_desc.clone(),
+                        role: role_desc.clone(),
+                        rule: rule.clone(),
+                    });
+                },
+                None => skipped.push(format!("{} refers to missing {}", binding_desc, role_desc)),
+            }
+        }
+        Ok(SubjectPermissions {
+            permissions,
+            skipped,
+        })
+    }
+
+    /// List every validating and mutating admission webhook in the cluster, mutating ones first
+    /// since they're called first.  A webhook that fails closed and can't be reached (see
+    /// Webhook::fails_closed) is a common reason for creates and updates failing.
+    #[allow(dead_code)]
+    pub fn webhooks(&self) -> Result<Vec<AdmissionWebhook>, KubeError> {
+        let mut webhooks = Vec::new();
+        for &(mutating, resource) in [
+            (true, "mutatingwebhookconfigurations"),
+            (false, "validatingwebhookconfigurations"),
+        ].iter()
+        {
+            let path = format!("/apis/admissionregistration.k8s.io/v1/{}", resource);
+            let list: ObjectList<WebhookConfiguration> = self.get(path.as_str())?;
+            for config in list.items.into_iter() {
+                for webhook in config.webhooks.into_iter() {
+                    webhooks.push(AdmissionWebhook {
+                        mutating,
+                        configuration: config.metadata.name.clone(),
+                        webhook,
+                    });
+                }
+            }
+        }
+        Ok(webhooks)
+    }
+
+    /// Find the storage class that persistent volume claims which don't name one use
+    pub fn default_storage_class(&self) -> Result<DefaultStorageClass, KubeError> {
+        let list: ObjectList<StorageClass> = self.get("/apis/storage.k8s.io/v1/storageclasses")?;
+        let mut defaults: Vec<StorageClass> =
+            list.items.into_iter().filter(|sc| sc.is_default()).collect();
+        Ok(match defaults.len() {
+            0 => DefaultStorageClass::None,
+            1 => DefaultStorageClass::One(defaults.pop().unwrap()), // safe, there's one
+            _ => DefaultStorageClass::Multiple(
+                defaults.into_iter().map(|sc| sc.metadata.name).collect(),
+            ),
+        })
+    }
+
+    /// Preview what deleting the object at path would take with it: everything that (directly or
+    /// through other dependents) has it as an owner, which the garbage collector deletes too.
+    /// For a deployment that's its replica sets and their pods.  The delete is first sent as a
+    /// dry run with foreground propagation, so an object that can't be deleted (or that we
+    /// aren't allowed to delete) is an error.  Only the kinds in DEPENDENT_KINDS are searched,
+    /// and kinds we can't list are skipped (and listed in the preview's skipped).
+    #[allow(dead_code)]
+    pub fn deletion_preview(&self, path: &str) -> Result<DeletionPreview, KubeError> {
+        let target = self.get_value(path)?;
+        let body = json!({
+            "kind": "DeleteOptions",
+            "apiVersion": "v1",
+            "propagationPolicy": "Foreground",
+        });
+        let resp = self.delete_dry_run(path, Some(body.to_string()))?;
+        self.check_resp(resp)?;
+
+        let namespace = ::values::val_str_opt("/metadata/namespace", &target);
+        let namespace = namespace.as_ref().map(|ns| ns.as_str());
+        let kinds: Vec<(GroupVersionResource, &str)> = DEPENDENT_KINDS
+            .iter()
+            .map(|&(group, version, resource, kind)| {
+                (GroupVersionResource::new(group, version, resource), kind)
+            })
+            .collect();
+        let lists = self.fan_out(kinds, |kluster, (gvr, kind)| {
+            let items = kluster.list_if_allowed(gvr.path(namespace, None).as_str());
+            (gvr, kind, items)
+        });
+        let mut candidates = Vec::new();
+        let mut skipped = Vec::new();
+        for (gvr, kind, items) in lists.into_iter() {
+            match items? {
+                Some(items) => candidates.extend(items.into_iter().map(|item| (kind, item))),
+                None => skipped.push(gvr.resource),
+            }
+        }
+        let dependents = find_dependents(
+            ::values::val_str("/metadata/uid", &target, "").as_ref(),
+            ::values::val_str("/metadata/name", &target, "").as_ref(),
+            candidates.as_slice(),
+        );
+        Ok(DeletionPreview {
+            dependents,
+            skipped,
+        })
+    }
+
+    /// Force a namespace stuck terminating to go away, by clearing its spec.finalizers through the
+    /// /finalize subresource and then deleting it.  Namespace finalizers (unlike the metadata
+    /// ones remove_finalizers clears) are how the namespace controller deletes everything in
+    /// the namespace, so skipping them is dangerous: anything still in the namespace is
+    /// orphaned, left in etcd where it can't be listed or cleaned up normally, and will
+    /// reappear if a namespace of the same name is created.  Resources outside the cluster
+    /// (volumes, load balancers) held by those objects are leaked.  Only use this once
+    /// whatever is blocking the delete (usually an unavailable api service) can't be fixed.
+    #[allow(dead_code)]
+    pub fn force_delete_namespace(&self, namespace: &str) -> Result<(), KubeError> {
+        let path = format!("/api/v1/namespaces/{}", namespace);
+        let current = self.get_value(path.as_str())?;
+        let body = namespace_finalize_body(current);
+        let resp = self.put(
+            subresource_path(path.as_str(), "finalize").as_str(),
+            body.to_string().as_str(),
+        )?;
+        self.check_resp(resp)?;
+        let resp = self.delete(path.as_str(), None)?;
+        if resp.status == StatusCode::NotFound {
+            return Ok(()); // clearing the finalizers finished it off
+        }
+        self.check_resp(resp)?;
+        Ok(())
+    }
+
+    /// Find the pods (in namespace, or across all namespaces if it's None) with a container
+    /// running image, either exactly or by prefix.  See image_matches for details.
+    #[allow(dead_code)]
+    pub fn pods_using_image(
+        &self,
+        namespace: Option<&str>,
+        image: &str,
+        prefix: bool,
+    ) -> Result<Vec<Pod>, KubeError> {
+        let gvr = GroupVersionResource::new("", "v1", "pods");
+        let list: PodList = self.get(gvr.path(namespace, None).as_str())?;
+        Ok(list.items
+            .into_iter()
+            .filter(|pod| pod.spec.uses_image(image, prefix))
+            .collect())
+    }
+
+    /// Find the pods in namespace that have no controller, and so won't be recreated if deleted.
+    /// Mirror pods are not included since the kubelet manages them.
+    #[allow(dead_code)]
+    pub fn orphaned_pods(&self, namespace: &str) -> Result<Vec<Pod>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods", namespace);
+        let list: PodList = self.get(path.as_str())?;
+        Ok(list.items
+            .into_iter()
+            .filter(|pod| pod.ownership() == PodOwnership::Bare)
+            .collect())
+    }
+
+    /// Find the containers (including init containers) that are stuck because their image can't
+    /// be pulled, in namespace or across all namespaces if it's None.  Usually this means the
+    /// image name is wrong or the pod lacks credentials for the registry.
+    #[allow(dead_code)]
+    pub fn image_pull_failures(
+        &self,
+        namespace: Option<&str>,
+    ) -> Result<Vec<ImagePullFailure>, KubeError> {
+        let gvr = GroupVersionResource::new("", "v1", "pods");
+        let list: PodList = self.get(gvr.path(namespace, None).as_str())?;
+        let mut failures = Vec::new();
+        for pod in list.items.into_iter() {
+            let statuses = pod.status
+                .init_container_statuses
+                .iter()
+                .chain(pod.status.container_statuses.iter())
+                .flat_map(|s| s.iter());
+            for status in statuses {
+                if let Some((reason, message)) = status.image_pull_failure() {
+                    failures.push(ImagePullFailure {
+                        namespace: pod.metadata.namespace.clone().unwrap_or_default(),
+                        pod: pod.metadata.name.clone(),
+                        container: status.name.clone(),
+                        image: status.image.clone(),
+                        reason: reason.to_owned(),
+                        message: message.to_owned(),
+                    });
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Fetch a pod and work out how long each stage of its startup took (see
+    /// Pod::startup_breakdown), to see whether a slow start was waiting for a node, for images,
+    /// or for readiness probes
+    #[allow(dead_code)]
+    pub fn pod_startup_breakdown(
+        &self,
+        namespace: &str,
+        pod: &str,
+    ) -> Result<StartupBreakdown, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods/{}", namespace, pod);
+        let pod: Pod = self.get(path.as_str())?;
+        Ok(pod.startup_breakdown())
+    }
+
+    /// Work out why a pod can't be scheduled, from the most recent FailedScheduling event about
+    /// it, or its PodScheduled condition if there are no such events (they expire after an
+    /// hour).  Reasons are things like insufficient resources, taints, node selectors, and
+    /// topology spread or anti-affinity constraints that no node can satisfy.
+    #[allow(dead_code)]
+    pub fn scheduling_diagnosis(
+        &self,
+        namespace: &str,
+        pod: &str,
+    ) -> Result<SchedulingDiagnosis, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods/{}", namespace, pod);
+        let pod_obj: Pod = self.get(path.as_str())?;
+        let scheduled = pod_obj
+            .status
+            .conditions
+            .iter()
+            .flat_map(|cs| cs.iter())
+            .find(|c| c.typ == "PodScheduled");
+        let pending = scheduled.map(|c| c.status != "True").unwrap_or(true);
+        let selector = format!("involvedObject.name={},reason=FailedScheduling", pod);
+        let path = add_query_param(
+            format!("/api/v1/namespaces/{}/events", namespace).as_str(),
+            format!("fieldSelector={}", encode_query_value(selector.as_str())).as_str(),
+        );
+        let events: EventList = self.get(path.as_str())?;
+        let message = events
+            .items
+            .iter()
+            .max_by_key(|e| e.last_timestamp)
+            .map(|e| e.message.clone())
+            .or_else(|| scheduled.and_then(|c| c.message.clone()));
+        let (total_nodes, reasons) = match message {
+            Some(ref msg) => parse_scheduling_message(msg),
+            None => (None, Vec::new()),
+        };
+        Ok(SchedulingDiagnosis {
+            pod: pod.to_owned(),
+            pending,
+            total_nodes,
+            reasons,
+            message,
+        })
+    }
+
+    /// Find the containers in namespace that have been killed for running out of memory, either
+    /// according to their current or last state or to OOM warning events about them.  OOM
+    /// events about a pod rather than a specific container are attached to its OOM killed
+    /// container if there's just one, and otherwise reported with no container.
+    #[allow(dead_code)]
+    pub fn oom_report(&self, namespace: &str) -> Result<Vec<OomKill>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods", namespace);
+        let pods: PodList = self.get(path.as_str())?;
+        let path = format!(
+            "/api/v1/namespaces/{}/events?fieldSelector=type%3DWarning",
+            namespace
+        );
+        let events: EventList = self.get(path.as_str())?;
+        let oom_events: Vec<&Event> = events.items.iter().filter(|e| e.is_oom_warning()).collect();
+        let messages = |pod: &str, container: Option<&str>| -> Vec<String> {
+            oom_events
+                .iter()
+                .filter(|e| e.is_about(pod, container))
+                .map(|e| e.message.clone())
+                .collect()
+        };
+
+        let mut report = Vec::new();
+        for pod in pods.items.iter() {
+            let name = pod.metadata.name.as_str();
+            let start = report.len();
+            let statuses = pod.status
+                .init_container_statuses
+                .iter()
+                .chain(pod.status.container_statuses.iter())
+                .flat_map(|s| s.iter());
+            for status in statuses {
+                let events = messages(name, Some(status.name.as_str()));
+                if status.was_oom_killed() || !events.is_empty() {
+                    report.push(OomKill {
+                        pod: name.to_owned(),
+                        container: Some(status.name.clone()),
+                        restart_count: status.restart_count,
+                        killed_at: status.oom_killed_at(),
+                        events: events,
+                    });
+                }
+            }
+            let pod_events = messages(name, None);
+            if !pod_events.is_empty() {
+                if report.len() == start + 1 {
+                    report[start].events.extend(pod_events);
+                } else {
+                    report.push(OomKill {
+                        pod: name.to_owned(),
+                        container: None,
+                        restart_count: 0,
+                        killed_at: None,
+                        events: pod_events,
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Fetch path from an http endpoint of a pod or service through the api server's proxy,
+    /// which avoids having to set up a port forward for a quick look at something like a health
+    /// check.  See ProxyTarget::new for what kind and port can be.
+    #[allow(dead_code)]
+    pub fn proxy_get(
+        &self,
+        namespace: &str,
+        kind: &str,
+        name: &str,
+        port: &str,
+        path: &str,
+    ) -> Result<String, KubeError> {
+        let target = ProxyTarget::new(namespace, kind, name, port)?;
+        self.get_text(target.path(path).as_str())
+    }
+
+    /// Get the kubelet's resource metrics for node, in the Prometheus text format, through the
+    /// api server's node proxy.  Access to the proxy is often restricted, so a 403 gets an error
+    /// saying which permission is missing.
+    #[allow(dead_code)]
+    pub fn node_proxy_metrics(&self, node: &str) -> Result<String, KubeError> {
+        let path = node_proxy_path(node, "metrics/resource");
+        let resp = self.send_req(path.as_str())?;
+        if resp.status == StatusCode::Forbidden {
+            let msg = self.parse_body::<Value>(resp)
+                .ok()
+                .and_then(|val| ::values::val_str_opt("/message", &val))
+                .unwrap_or_else(|| "Forbidden".to_owned());
+            return Err(KubeError::KubeServerError(format!(
+                "{} (reading node metrics needs get permission on nodes/proxy)",
+                msg
+            )));
+        }
+        let resp = self.check_resp(resp)?;
+        self.read_text(resp)
+    }
+
+    /// List the (cluster scoped) certificate signing requests
+    #[allow(dead_code)]
+    pub fn csrs(&self) -> Result<Vec<CertificateSigningRequest>, KubeError> {
+        let list: ObjectList<CertificateSigningRequest> =
+            self.get("/apis/certificates.k8s.io/v1/certificatesigningrequests")?;
+        Ok(list.items)
+    }
+
+    /// Approve the named certificate signing request, so its signer will issue the certificate.
+    /// Approving an already approved request does nothing, and a denied one is an error.
+    #[allow(dead_code)]
+    pub fn approve_csr(&self, name: &str) -> Result<CertificateSigningRequest, KubeError> {
+        let path = format!(
+            "/apis/certificates.k8s.io/v1/certificatesigningrequests/{}",
+            name
+        );
+        let value = self.get_value(path.as_str())?;
+        let csr: CertificateSigningRequest = serde_json::from_value(value.clone())?;
+        if csr.is_denied() {
+            return Err(KubeError::ParseErr(format!(
+                "Certificate signing request {} has been denied",
+                name
+            )));
+        }
+        if csr.is_approved() {
+            return Ok(csr);
+        }
+        let existing = value
+            .pointer("/status/conditions")
+            .and_then(|c| c.as_array())
+            .map(|c| c.as_slice())
+            .unwrap_or(&[]);
+        let body = csr_approval_patch(existing, "Approved via click", Utc::now());
+        let resp = self.merge_patch(
+            subresource_path(path.as_str(), "approval").as_str(),
+            body.to_string().as_str(),
+        )?;
+        let resp = self.check_resp(resp)?;
+        self.parse_body(resp)
+    }
+
+    /// Get the server's OpenAPI document, fetching it only the first time
+    fn openapi(&self) -> Result<Arc<Value>, KubeError> {
+        let mut cached = self.openapi.lock().unwrap();
+        if let Some(ref doc) = *cached {
+            return Ok(doc.clone());
+        }
+        let doc = Arc::new(self.get_value("/openapi/v2")?);
+        *cached = Some(doc.clone());
+        Ok(doc)
+    }
+
+    /// Get the strategic merge patch strategy and merge key for a field (like "spec.containers")
+    /// of a kind, from the server's OpenAPI schema.  Returns None if the field isn't found.
+    #[allow(dead_code)]
+    pub fn patch_strategy(
+        &self,
+        gvk: &GroupVersionKind,
+        field_path: &str,
+    ) -> Result<Option<PatchStrategy>, KubeError> {
+        let doc = self.openapi()?;
+        Ok(openapi_patch_strategy(&doc, gvk, field_path))
+    }
+
+    /// Get the api groups the server supports (not including the core group)
+    pub fn api_groups(&self) -> Result<Vec<ApiGroup>, KubeError> {
+        let list: ApiGroupList = self.get("/apis")?;
+        Ok(list.groups)
+    }
+
+    /// Fetch the discovery document for every group/version the server advertises, returning
+    /// the ones that failed along with their errors.  A failing group/version is usually served
+    /// by an aggregated api server that's down, and its resource types will be unavailable until
+    /// it's back.
+    #[allow(dead_code)]
+    pub fn discovery_health(&self) -> Result<Vec<(String, KubeError)>, KubeError> {
+        let mut paths = vec![("v1".to_owned(), "/api/v1".to_owned())];
+        for group in self.api_groups()?.into_iter() {
+            for version in group.versions.into_iter() {
+                let path = format!("/apis/{}", version.group_version);
+                paths.push((version.group_version, path));
+            }
+        }
+        let results = self.fan_out(paths, |kluster, (group_version, path)| {
+            kluster
+                .get_value(path.as_str())
+                .err()
+                .map(|e| (group_version, e))
+        });
+        Ok(results.into_iter().filter_map(|r| r).collect())
+    }
+
+    /// List the objects at path, or None if we aren't allowed to (403 Forbidden)
+    fn list_if_allowed(&self, path: &str) -> Result<Option<Vec<Value>>, KubeError> {
+        let resp = self.send_req(path)?;
+        if resp.status == StatusCode::Forbidden {
+            return Ok(None);
+        }
+        let mut resp = self.check_resp(resp)?;
+        let body = Kluster::read_body(&mut resp, self.max_response_bytes)?;
+        let list: ObjectList<Value> = Kluster::parse_json(body.as_slice())?;
+        Ok(Some(list.items))
+    }
+
+    /// Export the common kinds of object in namespace (see EXPORT_KINDS) to dir, as one YAML
+    /// file per object in a directory per kind, like dir/deployments/web.yaml.  Server managed
+    /// fields are stripped, so the files can be applied to recreate the namespace.  Objects with
+    /// a controlling owner (like a cronjob's jobs) and service account tokens are skipped, since
+    /// they're recreated automatically.  Kinds are listed in parallel (see with_max_concurrency),
+    /// and kinds we aren't allowed to list are skipped with a warning.  Returns the paths
+    /// written.
+    #[allow(dead_code)]
+    pub fn export_namespace(&self, namespace: &str, dir: &Path) -> Result<Vec<PathBuf>, KubeError> {
+        let kinds: Vec<(GroupVersionResource, &str)> = EXPORT_KINDS
+            .iter()
+            .filter_map(|&(resource, kind)| builtin_kind(resource).map(|(gvr, _)| (gvr, kind)))
+            .collect();
+        let lists = self.fan_out(kinds, |kluster, (gvr, kind)| {
+            let items = kluster.list_if_allowed(gvr.path(Some(namespace), None).as_str());
+            (gvr, kind, items)
+        });
+        let mut written = Vec::new();
+        for (gvr, kind, items) in lists.into_iter() {
+            let items = match items? {
+                Some(items) => items,
+                None => {
+                    println!(
+                        "[WARNING] Not allowed to list {} in {}, skipping",
+                        gvr.resource, namespace
+                    );
+                    continue;
+                }
+            };
+            let kind_dir = dir.join(gvr.resource.as_str());
+            for mut item in items.into_iter() {
+                let controlled = item.pointer("/metadata/ownerReferences")
+                    .and_then(|refs| refs.as_array())
+                    .map(|refs| refs.iter().any(|r| r["controller"] == Value::Bool(true)))
+                    .unwrap_or(false);
+                let sa_token = ::values::val_str_opt("/type", &item)
+                    .map(|t| t == "kubernetes.io/service-account-token")
+                    .unwrap_or(false);
+                if controlled || sa_token {
+                    continue;
+                }
+                let name = ::values::val_str("/metadata/name", &item, "").into_owned();
+                // lists don't set kind and apiVersion on their items
+                if let Some(obj) = item.as_object_mut() {
+                    let api_version = if gvr.group.is_empty() {
+                        gvr.version.clone()
+                    } else {
+                        format!("{}/{}", gvr.group, gvr.version)
+                    };
+                    obj.insert("apiVersion".to_owned(), Value::String(api_version));
+                    obj.insert("kind".to_owned(), Value::String(kind.to_owned()));
+                }
+                ::values::strip_server_fields(&mut item);
+                fs::create_dir_all(&kind_dir)?;
+                let file_path = kind_dir.join(format!("{}.yaml", key_to_filename(name.as_str())));
+                File::create(&file_path)?.write_all(serde_yaml::to_string(&item)?.as_bytes())?;
+                written.push(file_path);
+            }
+        }
+        written.sort();
+        Ok(written)
+    }
+
+    /// Write each key of the specified config map to a file of the same name (made safe for use
+    /// as a file name) in dir, which is created if needed.  Binary data is decoded.  Returns the
+    /// paths written.
+    #[allow(dead_code)]
+    pub fn dump_configmap(
+        &self,
+        namespace: &str,
+        name: &str,
+        dir: &Path,
+    ) -> Result<Vec<PathBuf>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/configmaps/{}", namespace, name);
+        let cm: ConfigMap = self.get(path.as_str())?;
+        fs::create_dir_all(dir)?;
+        let mut written = Vec::new();
+        for (key, value) in cm.data.iter().flat_map(|d| d.iter()) {
+            let file_path = dir.join(key_to_filename(key));
+            File::create(&file_path)?.write_all(value.as_bytes())?;
+            written.push(file_path);
+        }
+        for (key, value) in cm.binary_data.iter().flat_map(|d| d.iter()) {
+            let file_path = dir.join(key_to_filename(key));
+            File::create(&file_path)?.write_all(::base64::decode(value.as_str())?.as_slice())?;
+            written.push(file_path);
+        }
+        written.sort();
+        Ok(written)
+    }
+
+    /// Work out the environment a container in namespace runs with, as (name, value) pairs in the
+    /// order they're defined.  Values from config maps are looked up.  Values from secrets are
+    /// only looked up if reveal_secrets is true, otherwise they're masked.  Values that depend on
+    /// the running pod (fields and resources) are described rather than resolved.
+    #[allow(dead_code)]
+    pub fn container_env(
+        &self,
+        namespace: &str,
+        container: &ContainerSpec,
+        reveal_secrets: bool,
+    ) -> Result<Vec<(String, String)>, KubeError> {
+        let mut config_maps: HashMap<&str, Option<ConfigMap>> = HashMap::new();
+        let mut secrets: HashMap<&str, Option<Secret>> = HashMap::new();
+        let mut env = Vec::new();
+        for var in container.env.iter().flat_map(|e| e.iter()) {
+            let value = match var.source() {
+                EnvValue::ConfigMapKey(name, key) => {
+                    if !config_maps.contains_key(name) {
+                        let path = format!("/api/v1/namespaces/{}/configmaps/{}", namespace, name);
+                        config_maps.insert(name, self.get_opt(path.as_str())?);
+                    }
+                    config_maps[name]
+                        .as_ref()
+                        .and_then(|cm| cm.data.as_ref())
+                        .and_then(|data| data.get(key))
+                        .cloned()
+                        .unwrap_or_else(|| format!("{} (not found)", var.value_summary()))
+                }
+                EnvValue::SecretKey(name, key) if reveal_secrets => {
+                    if !secrets.contains_key(name) {
+                        let path = format!("/api/v1/namespaces/{}/secrets/{}", namespace, name);
+                        secrets.insert(name, self.get_opt(path.as_str())?);
+                    }
+                    let encoded = secrets[name]
+                        .as_ref()
+                        .and_then(|secret| secret.data.as_ref())
+                        .and_then(|data| data.get(key));
+                    match encoded {
+                        Some(encoded) => {
+                            let decoded = ::base64::decode(encoded.as_str())?;
+                            String::from_utf8_lossy(decoded.as_slice()).into_owned()
+                        }
+                        None => format!("{} (not found)", var.value_summary()),
+                    }
+                }
+                _ => var.value_summary(),
+            };
+            env.push((var.name.clone(), value));
+        }
+        Ok(env)
+    }
+
+    /// Report desired vs ready replicas for every Deployment, StatefulSet and ReplicaSet in
+    /// namespace (or all namespaces if None), to quickly see if anything isn't at its desired
+    /// scale.  ReplicaSets controlled by a Deployment are left out, since the Deployment covers
+    /// them.
+    #[allow(dead_code)]
+    pub fn scaling_report(&self, namespace: Option<&str>) -> Result<Vec<WorkloadScale>, KubeError> {
+        let mut report = Vec::new();
+
+        let gvr = GroupVersionResource::new("apps", "v1", "deployments");
+        let deps: ObjectList<Deployment> = self.get(gvr.path(namespace, None).as_str())?;
+        for dep in deps.items.into_iter() {
+            let (desired, ready, stale) =
+                (dep.spec.replicas, dep.status.ready, dep.has_stale_status());
+            report.push(WorkloadScale::new("Deployment", dep.metadata, desired, ready, stale));
+        }
+
+        let gvr = GroupVersionResource::new("apps", "v1", "statefulsets");
+        let sets: ObjectList<StatefulSet> = self.get(gvr.path(namespace, None).as_str())?;
+        for set in sets.items.into_iter() {
+            let desired = set.spec.replicas.unwrap_or(1);
+            let stale = set.has_stale_status();
+            let ready = set.status.map(|s| s.ready).unwrap_or(0);
+            report.push(WorkloadScale::new("StatefulSet", set.metadata, desired, ready, stale));
+        }
+
+        let gvr = GroupVersionResource::new("apps", "v1", "replicasets");
+        let sets: ObjectList<ReplicaSet> = self.get(gvr.path(namespace, None).as_str())?;
+        for set in sets.items.into_iter() {
+            let from_deployment = set.metadata
+                .owner_refs
+                .iter()
+                .flat_map(|refs| refs.iter())
+                .any(|r| r.controller && r.kind == "Deployment");
+            if from_deployment {
+                continue;
+            }
+            let desired = set.spec.replicas.unwrap_or(1);
+            let stale = set.has_stale_status();
+            let ready = set.status.map(|s| s.ready).unwrap_or(0);
+            report.push(WorkloadScale::new("ReplicaSet", set.metadata, desired, ready, stale));
+        }
+
+        Ok(report)
+    }
+
+    /// Evict a pod via the eviction api, which respects PodDisruptionBudgets.  Returns Ok(false)
+    /// if a budget doesn't currently allow the eviction.
+    pub fn evict_pod(&self, namespace: &str, name: &str) -> Result<bool, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods/{}/eviction", namespace, name);
+        let body = json!({
+            "apiVersion": "policy/v1",
+            "kind": "Eviction",
+            "metadata": {
+                "name": name,
+                "namespace": namespace,
+            }
+        });
+        let resp = self.post(path.as_str(), body.to_string().as_str())?;
+        if resp.status == StatusCode::TooManyRequests {
+            return Ok(false);
+        }
+        self.check_resp(resp)?;
+        Ok(true)
+    }
+
+    /// Restart the pods in namespace matching selector one at a time, by evicting each and then
+    /// waiting until it's gone and there are as many ready pods as there were to start with before
+    /// moving to the next.  Evictions blocked by a PodDisruptionBudget are retried.  Returns the
+    /// names of the pods evicted.
+    #[allow(dead_code)]
+    pub fn rolling_restart_pods(
+        &self,
+        namespace: &str,
+        selector: &str,
+    ) -> Result<Vec<String>, KubeError> {
+        self.rolling_restart_pods_polling(namespace, selector, Duration::from_secs(EVICTION_POLL))
+    }
+
+    /// rolling_restart_pods, checking on progress every poll
+    fn rolling_restart_pods_polling(
+        &self,
+        namespace: &str,
+        selector: &str,
+        poll: Duration,
+    ) -> Result<Vec<String>, KubeError> {
+        let path = format!("/api/v1/namespaces/{}/pods", namespace);
+        let list_live = || -> Result<Vec<Pod>, KubeError> {
+            let list: PodList = self.list_with_selector(path.as_str(), selector)?;
+            Ok(list.items
+                .into_iter()
+                .filter(|p| p.metadata.deletion_timestamp.is_none())
+                .collect())
+        };
+        let pods = list_live()?;
+        let want_ready = pods.iter().filter(|p| p.is_ready()).count();
+        let timeout = Duration::from_secs(EVICTION_TIMEOUT);
+
+        let mut evicted = Vec::new();
+        for pod in pods.into_iter() {
+            let start = Instant::now();
+            while !self.evict_pod(namespace, pod.metadata.name.as_str())? {
+                if start.elapsed() > timeout {
+                    return Err(KubeError::Kube(KubeErrNo::Timeout));
+                }
+                thread::sleep(poll);
+            }
+
+            // a replacement could have the same name (for StatefulSets), so track by uid
+            let start = Instant::now();
+            loop {
+                thread::sleep(poll);
+                let live = list_live()?;
+                let still_there = live.iter().any(|p| p.metadata.uid == pod.metadata.uid);
+                let ready = live.iter().filter(|p| p.is_ready()).count();
+                if !still_there && ready >= want_ready {
+                    break;
+                }
+                if start.elapsed() > timeout {
+                    return Err(KubeError::Kube(KubeErrNo::Timeout));
+                }
+            }
+            evicted.push(pod.metadata.name);
+        }
+        Ok(evicted)
+    }
+
+    /// Find a kind the builtin table doesn't know (like a custom resource) by any of its names,
+    /// using the discovery document of the preferred version of each group, returning its
+    /// resource and whether it's namespaced.  Group/versions whose discovery fails (usually an
+    /// aggregated api server that's down) are skipped.
+    fn discover_kind(&self, name: &str) -> Result<Option<(GroupVersionResource, bool)>, KubeError> {
+        let name = name.to_lowercase();
+        let mut group_versions = vec![(String::new(), "v1".to_owned())];
+        for group in self.api_groups()?.into_iter() {
+            let version = match group.preferred_version {
+                Some(v) => Some(v.version),
+                None => group.versions.into_iter().next().map(|v| v.version),
+            };
+            if let Some(version) = version {
+                group_versions.push((group.name, version));
+            }
+        }
+        let lists = self.fan_out(group_versions, |kluster, (group, version)| {
+            let path = if group.is_empty() {
+                format!("/api/{}", version)
+            } else {
+                format!("/apis/{}/{}", group, version)
+            };
+            let list = kluster.get::<ApiResourceList>(path.as_str());
+            (group, version, list)
+        });
+        for (group, version, list) in lists.into_iter() {
+            let found = list.ok().and_then(|list| {
+                list.resources
+                    .into_iter()
+                    // subresources like pods/log aren't kinds
+                    .find(|r| !r.name.contains('/') && r.is_named(name.as_str()))
+            });
+            if let Some(resource) = found {
+                let gvr =
+                    GroupVersionResource::new(group.as_str(), version.as_str(), &resource.name);
+                return Ok(Some((gvr, resource.namespaced)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Work out the api path (and query string) for a query like "pods -n kube-system -l app=dns"
+    /// or "deploy/web -n prod".  A query is a kind, optionally followed by a name (as kind/name or
+    /// a separate word), with -n (namespace) and -l (label selector) flags.  Kinds in the builtin
+    /// table are resolved without talking to the server, and any other kind (like a custom
+    /// resource) is looked up through discovery.  Listing a namespaced kind without -n lists
+    /// across all namespaces, while getting a named object without -n looks in "default" as
+    /// kubectl does.
+    #[allow(dead_code)]
+    pub fn explain_request(&self, query: &str) -> Result<String, KubeError> {
+        let mut words = query.split_whitespace();
+        let mut kind = None;
+        let mut name = None;
+        let mut namespace = None;
+        let mut selector = None;
+        while let Some(word) = words.next() {
+            match word {
+                "-n" | "--namespace" => namespace = words.next(),
+                "-l" | "--selector" => selector = words.next(),
+                _ if kind.is_none() => {
+                    let mut parts = word.splitn(2, '/');
+                    kind = parts.next();
+                    name = parts.next();
+                }
+                _ if name.is_none() => name = Some(word),
+                _ => {
+                    return Err(KubeError::ParseErr(format!(
+                        "Unexpected '{}' in query '{}'",
+                        word, query
+                    )))
+                }
+            }
+        }
+        let kind = kind.ok_or_else(|| KubeError::ParseErr("Query must specify a kind".to_owned()))?;
+        let found = match builtin_kind(kind) {
+            Some(found) => Some(found),
+            None => self.discover_kind(kind)?,
+        };
+        let (gvr, namespaced) =
+            found.ok_or_else(|| KubeError::ParseErr(format!("Unknown kind: {}", kind)))?;
+        let namespace = if !namespaced {
+            None
+        } else if namespace.is_none() && name.is_some() {
+            Some("default")
+        } else {
+            namespace
+        };
+        let path = gvr.path(namespace, name);
+        Ok(match selector {
+            Some(sel) => add_query_param(
+                path.as_str(),
+                format!("labelSelector={}", encode_query_value(sel)).as_str(),
+            ),
+            None => path,
+        })
+    }
+
+    /// Get all namespaces in this cluster
+    pub fn namespaces_for_context(&self) -> Result<Vec<String>, KubeError> {
+        let mut vec = Vec::new();
+        let res = try!(self.get::<NamespaceList>("/api/v1/namespaces"));
+        for ns in res.items.iter() {
+            vec.push(ns.metadata.name.clone());
+        }
+        Ok(vec)
+    }
+}
+
+/// Objects of one kind that differ between two clusters
+#[allow(dead_code)]
+#[derive(Debug)]
+pub struct InventoryDiff {
+    /// The names of the clusters compared
+    pub left: String,
+    pub right: String,
+    /// Keys (namespace/name, or just name for cluster scoped kinds) of objects only in one
+    /// cluster, sorted
+    pub only_left: Vec<String>,
+    pub only_right: Vec<String>,
+    /// Objects in both clusters whose contents (everything but metadata and status) differ
+    pub changed: Vec<(String, Vec<FieldDiff>)>,
+}
+
+impl InventoryDiff {
+    /// True if both clusters have the same objects with the same contents
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Several clusters to run the same request against at once, like the same service deployed to
+/// different regions
+pub struct MultiKluster {
+    pub klusters: Vec<Kluster>,
+}
+
+impl MultiKluster {
+    #[allow(dead_code)]
+    pub fn new(klusters: Vec<Kluster>) -> MultiKluster {
+        MultiKluster { klusters }
+    }
+
+    /// Run f against every cluster at once, returning the results in the same order as the
+    /// clusters
+    pub fn fan_out<R, F>(&self, f: F) -> Vec<R>
+    where
+        R: Send,
+        F: Fn(&Kluster) -> R + Sync,
+    {
+        let f = &f;
+        thread::scope(|scope| {
+            let handles: Vec<_> = self.klusters
+                .iter()
+                .map(|kluster| scope.spawn(move || f(kluster)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    /// List a kind (any name click knows it by, like "deploy") on two clusters, in namespace or
+    /// across all of them if None, and report which objects only exist in one, and how the
+    /// objects in both differ.  Metadata and status are ignored since they always differ between
+    /// clusters.  There must be exactly two clusters.
+    #[allow(dead_code)]
+    pub fn compare_inventory(
+        &self,
+        kind: &str,
+        namespace: Option<&str>,
+    ) -> Result<InventoryDiff, KubeError> {
+        if self.klusters.len() != 2 {
+            return Err(KubeError::ParseErr(format!(
+                "Can only compare two clusters, not {}",
+                self.klusters.len()
+            )));
+        }
+        let (gvr, namespaced) = builtin_kind(kind)
+            .ok_or_else(|| KubeError::ParseErr(format!("Unknown kind: {}", kind)))?;
+        let path = gvr.path(if namespaced { namespace } else { None }, None);
+        let mut lists = self.fan_out(|kluster| {
+            kluster.get::<ObjectList<Value>>(path.as_str()).map(|list| {
+                list.items
+                    .into_iter()
+                    .map(|obj| (object_key(&obj), inventory_contents(obj)))
+                    .collect::<BTreeMap<String, Value>>()
+            })
+        });
+        let right = lists.pop().unwrap()?; // safe, checked there are two
+        let left = lists.pop().unwrap()?;
+        let only_left = left.keys().filter(|k| !right.contains_key(*k)).cloned().collect();
+        let only_right = right.keys().filter(|k| !left.contains_key(*k)).cloned().collect();
+        let changed = left.iter()
+            .filter_map(|(key, obj)| {
+                right.get(key).and_then(|other| {
+                    let diffs = ::values::diff_values(obj, other);
+                    if diffs.is_empty() {
+                        None
+                    } else {
+                        Some((key.clone(), diffs))
+                    }
+                })
+            })
+            .collect();
+        Ok(InventoryDiff {
+            left: self.klusters[0].name.clone(),
+            right: self.klusters[1].name.clone(),
+            only_left,
+            only_right,
+            changed,
+        })
+    }
+}
+
+/// The parts of an object worth comparing across clusters: everything but its metadata and
+/// status, which are specific to the cluster it's in
+fn inventory_contents(mut obj: Value) -> Value {
+    if let Some(map) = obj.as_object_mut() {
+        map.remove("metadata");
+        map.remove("status");
+    }
+    obj
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::{TcpListener, TcpStream};
+    use tempdir::TempDir;
+
+    /// A request the mock server got
+    #[derive(Debug, Clone)]
+    struct MockRequest {
+        method: String,
+        /// The path including any query string
+        path: String,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl MockRequest {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|&&(ref n, _)| n.eq_ignore_ascii_case(name))
+                .map(|&(_, ref v)| v.as_str())
+        }
+
+        fn body_json(&self) -> Value {
+            serde_json::from_slice(self.body.as_slice()).unwrap()
+        }
+    }
+
+    /// What the mock server sends back for a matching request
+    #[derive(Debug, Clone)]
+    struct MockResponse {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl MockResponse {
+        fn json(status: u16, body: Value) -> MockResponse {
+            MockResponse::raw(status, body.to_string().into_bytes())
+                .header("Content-Type", "application/json")
+        }
+
+        fn raw(status: u16, body: Vec<u8>) -> MockResponse {
+            MockResponse {
+                status,
+                headers: Vec::new(),
+                body,
+            }
+        }
+
+        fn header(mut self, name: &str, value: &str) -> MockResponse {
+            self.headers.push((name.to_owned(), value.to_owned()));
+            self
+        }
+    }
+
+    /// A route is a method, a path and the responses to give in order.  A path with a '?' has to
+    /// match the query too, otherwise the query is ignored.  Once a route is down to its last
+    /// response, that's returned for every later request.
+    type Route = (&'static str, String, Vec<MockResponse>);
+
+    fn route(method: &'static str, path: &str, response: MockResponse) -> Route {
+        (method, path.to_owned(), vec![response])
+    }
+
+    fn status_body(code: u16, message: &str) -> Value {
+        json!({"kind": "Status", "status": "Failure", "message": message, "code": code})
+    }
+
+    fn read_request<R: BufRead>(reader: &mut R) -> Option<MockRequest> {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next()?.to_owned();
+        let path = parts.next()?.to_owned();
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).ok()?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            let mut kv = line.splitn(2, ':');
+            let name = kv.next()?.trim().to_owned();
+            let value = kv.next().unwrap_or("").trim().to_owned();
+            headers.push((name, value));
+        }
+        let len = headers
+            .iter()
+            .find(|&&(ref n, _)| n.eq_ignore_ascii_case("content-length"))
+            .and_then(|&(_, ref v)| v.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; len];
+        reader.read_exact(body.as_mut_slice()).ok()?;
+        Some(MockRequest {
+            method,
+            path,
+            headers,
+            body,
+        })
+    }
+
+    fn respond(routes: &Mutex<Vec<Route>>, req: &MockRequest) -> MockResponse {
+        let bare_path = req.path.split('?').next().unwrap();
+        let mut routes = routes.lock().unwrap();
+        let found = routes.iter_mut().find(|&&mut (method, ref path, _)| {
+            method == req.method && (*path == req.path || *path == bare_path)
+        });
+        match found {
+            Some(&mut (_, _, ref mut responses)) if responses.len() > 1 => responses.remove(0),
+            Some(&mut (_, _, ref responses)) => responses[0].clone(),
+            None => MockResponse::json(404, status_body(404, "not found")),
+        }
+    }
+
+    fn serve(stream: TcpStream, routes: &Mutex<Vec<Route>>, log: &Mutex<Vec<MockRequest>>) {
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        while let Some(req) = read_request(&mut reader) {
+            let resp = respond(routes, &req);
+            log.lock().unwrap().push(req);
+            let mut head = format!(
+                "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\n",
+                resp.status,
+                resp.body.len()
+            );
+            for &(ref name, ref value) in resp.headers.iter() {
+                head.push_str(format!("{}: {}\r\n", name, value).as_str());
+            }
+            head.push_str("\r\n");
+            if writer.write_all(head.as_bytes()).is_err()
+                || writer.write_all(resp.body.as_slice()).is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    /// Start a server on localhost that answers with routes, and a Kluster that talks to it.
+    /// Every request the server gets is recorded in the returned log.
+    fn mock_kluster(routes: Vec<Route>) -> (Kluster, Arc<Mutex<Vec<MockRequest>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let routes = Arc::new(Mutex::new(routes));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let server_log = log.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(s) => s,
+                    Err(_) => continue,
+                };
+                let routes = routes.clone();
+                let log = server_log.clone();
+                thread::spawn(move || serve(stream, &routes, &log));
+            }
+        });
+        // localhost rather than the ip, so we don't go looking for a cert to map it to a name
+        let server = format!("http://localhost:{}", port);
+        let kluster = Kluster::new("mock", None, server.as_str(), KlusterAuth::with_token("t"))
+            .unwrap();
+        (kluster, log)
+    }
+
+    fn paths(log: &Mutex<Vec<MockRequest>>) -> Vec<String> {
+        log.lock().unwrap().iter().map(|r| r.path.clone()).collect()
+    }
+
+    #[test]
+    fn version_paths() {
+        let gvr = GroupVersionResource::new("apps", "v1", "deployments");
+        assert_eq!(
+            gvr.path(Some("ns"), Some("web")),
+            "/apis/apps/v1/namespaces/ns/deployments/web"
+        );
+        assert_eq!(
+            gvr.with_version("v1beta2").path(Some("ns"), Some("web")),
+            "/apis/apps/v1beta2/namespaces/ns/deployments/web"
+        );
+        let core = GroupVersionResource::new("", "v1", "nodes");
+        assert_eq!(core.path(None, None), "/api/v1/nodes");
+        assert_eq!(core.with_version("v2").path(None, Some("n1")), "/api/v2/nodes/n1");
+    }
+
+    #[test]
+    fn get_as_version_reports_unserved_version() {
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/deployments/web",
+                MockResponse::json(200, json!({"metadata": {"name": "web"}})),
+            ),
+            route(
+                "GET",
+                "/apis",
+                MockResponse::json(200, json!({"groups": [{
+                    "name": "apps",
+                    "versions": [{"groupVersion": "apps/v1", "version": "v1"}],
+                }]})),
+            ),
+        ]);
+        let gvr = GroupVersionResource::new("apps", "v1beta2", "deployments");
+        let obj: PartialObject = kluster.get_as_version(&gvr, Some("ns"), "web", "v1").unwrap();
+        assert_eq!(obj.metadata.name, "web");
+        assert_eq!(log.lock().unwrap()[0].header("authorization"), Some("Bearer t"));
+
+        match kluster.get_as_version::<Value>(&gvr, Some("ns"), "web", "v1typo") {
+            Err(KubeError::KubeServerError(msg)) => {
+                assert!(msg.contains("doesn't serve version v1typo"), "{}", msg);
+                assert!(msg.contains("v1"), "{}", msg);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/apis/apps/v1/namespaces/ns/deployments/web",
+                "/apis/apps/v1typo/namespaces/ns/deployments/web",
+                "/apis",
+            ]
+        );
+    }
+
+    /// A watch response sending each of events, as (type, object) pairs
+    fn watch_stream(events: Vec<(&str, Value)>) -> MockResponse {
+        let mut body = String::new();
+        for (typ, object) in events.into_iter() {
+            body.push_str(json!({"type": typ, "object": object}).to_string().as_str());
+            body.push('\n');
+        }
+        MockResponse::raw(200, body.into_bytes()).header("Content-Type", "application/json")
+    }
+
+    fn deployment_json(
+        generation: i64,
+        observed: i64,
+        replicas: u32,
+        updated: u32,
+        available: u32,
+    ) -> Value {
+        json!({
+            "metadata": {"name": "web", "namespace": "ns", "generation": generation},
+            "spec": {"replicas": 3},
+            "status": {
+                "observedGeneration": observed,
+                "replicas": replicas,
+                "updatedReplicas": updated,
+                "availableReplicas": available,
+            },
+        })
+    }
+
+    #[test]
+    fn watch_rollout_to_complete() {
+        let events = watch_stream(vec![
+            ("ADDED", deployment_json(2, 1, 3, 0, 3)),
+            ("MODIFIED", deployment_json(2, 2, 4, 1, 3)),
+            ("MODIFIED", deployment_json(2, 2, 4, 3, 3)),
+            ("MODIFIED", deployment_json(2, 2, 3, 3, 2)),
+            ("MODIFIED", deployment_json(2, 2, 3, 3, 3)),
+        ]);
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/apis/apps/v1/namespaces/ns/deployments", events),
+        ]);
+        let mut updates = Vec::new();
+        let last = kluster
+            .watch_rollout("ns", "web", |status| updates.push(format!("{:?}", status)))
+            .unwrap();
+        assert_eq!(last, RolloutStatus::Complete);
+        assert_eq!(
+            updates,
+            vec![
+                "Progressing(\"1 of 3 new replicas have been updated\")",
+                "Progressing(\"1 old replicas are pending termination\")",
+                "Progressing(\"2 of 3 updated replicas are available\")",
+                "Complete",
+            ]
+        );
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/apis/apps/v1/namespaces/ns/deployments?fieldSelector=metadata.name=web\
+                 &watch=true",
+            ]
+        );
+    }
+
+    /// A running pod with one container ("app") that's been up for an hour, for tests to adjust
+    fn pod_json(name: &str) -> Value {
+        json!({
+            "metadata": {"name": name, "namespace": "ns", "uid": format!("{}-uid", name)},
+            "spec": {"containers": [{"name": "app", "image": "app:1"}]},
+            "status": {
+                "phase": "Running",
+                "containerStatuses": [{
+                    "name": "app",
+                    "image": "app:1",
+                    "restartCount": 0,
+                    "ready": true,
+                    "state": {"running": {"startedAt": "2018-01-01T00:00:00Z"}},
+                }],
+            },
+        })
+    }
+
+    #[test]
+    fn ephemeral_container_logs() {
+        let mut pod = pod_json("distroless");
+        pod["status"]["ephemeralContainerStatuses"] = json!([{
+            "name": "debugger-x1",
+            "image": "busybox",
+            "restartCount": 0,
+            "ready": false,
+            "state": {"running": {"startedAt": "2018-01-01T01:00:00Z"}},
+        }]);
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let ephemerals = pod.status.ephemeral_container_statuses.as_ref().unwrap();
+        assert_eq!(ephemerals[0].image, "busybox");
+        assert_eq!(pod.log_container_names(), vec!["app", "debugger-x1"]);
+    }
+
+    #[test]
+    fn log_url_and_query() {
+        let kluster = Kluster::new(
+            "prod",
+            None,
+            "https://k8s.example.com:6443",
+            KlusterAuth::with_token("t"),
+        ).unwrap();
+        let params = LogParams {
+            container: Some("app".to_owned()),
+            follow: true,
+            tail_lines: Some(100),
+            since_seconds: Some(3600),
+            timestamps: true,
+            ..LogParams::default()
+        };
+        let url = kluster.log_url("ns", "web-1", &params).unwrap();
+        assert_eq!(url.scheme(), "https");
+        assert_eq!(url.host_str(), Some("k8s.example.com"));
+        assert_eq!(url.port(), Some(6443));
+        assert_eq!(url.path(), "/api/v1/namespaces/ns/pods/web-1/log");
+        assert_eq!(
+            url.query(),
+            Some("container=app&follow=true&tailLines=100&sinceSeconds=3600&timestamps=true")
+        );
+
+        let previous = LogParams {
+            previous: true,
+            ..LogParams::default()
+        };
+        assert_eq!(
+            previous.path("ns", "web-1"),
+            "/api/v1/namespaces/ns/pods/web-1/log?previous=true"
+        );
+        assert_eq!(
+            LogParams::default().path("ns", "web-1"),
+            "/api/v1/namespaces/ns/pods/web-1/log"
+        );
+    }
+
+    fn with_resource_version(mut obj: Value, rv: &str) -> Value {
+        obj["metadata"]["resourceVersion"] = json!(rv);
+        obj
+    }
+
+    #[test]
+    fn watch_forever_reconnects_from_last_version() {
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=10&watch=true",
+                watch_stream(vec![
+                    ("MODIFIED", with_resource_version(pod_json("a"), "11")),
+                    ("ADDED", with_resource_version(pod_json("b"), "12")),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=12&watch=true",
+                watch_stream(vec![("DELETED", with_resource_version(pod_json("a"), "13"))]),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "10"},
+                    "items": [with_resource_version(pod_json("a"), "9")],
+                })),
+            ),
+        ]);
+        let mut seen = Vec::new();
+        kluster
+            .watch_forever("/api/v1/pods", |event: WatchEvent<Pod>| {
+                let desc = match event {
+                    WatchEvent::Added(p) => format!("added {}", p.metadata.name),
+                    WatchEvent::Modified(p) => format!("modified {}", p.metadata.name),
+                    WatchEvent::Deleted(p) => format!("deleted {}", p.metadata.name),
+                    WatchEvent::Error(e) => format!("error {}", e),
+                };
+                seen.push(desc);
+                seen.len() < 4
+            })
+            .unwrap();
+        assert_eq!(seen, vec!["added a", "modified a", "added b", "deleted a"]);
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/api/v1/pods",
+                "/api/v1/pods?resourceVersion=10&watch=true",
+                "/api/v1/pods?resourceVersion=12&watch=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_forever_gives_up_on_rejected_requests() {
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=1&watch=true",
+                MockResponse::json(401, status_body(401, "token expired")),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({"metadata": {"resourceVersion": "1"}, "items": []})),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/secret/pods",
+                MockResponse::json(403, status_body(403, "pods is forbidden")),
+            ),
+        ]);
+        match kluster.watch_forever("/api/v1/pods", |_: WatchEvent<Pod>| true) {
+            Err(KubeError::Kube(KubeErrNo::Unauthorized(Some(msg)))) => {
+                assert_eq!(msg, "token expired")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match kluster.watch_forever("/api/v1/namespaces/secret/pods", |_: WatchEvent<Pod>| true) {
+            Err(KubeError::KubeServerError(msg)) => assert_eq!(msg, "pods is forbidden"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // no retries
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn empty_bodies() {
+        let (kluster, _) = mock_kluster(vec![
+            route("GET", "/api/v1/empty", MockResponse::raw(200, Vec::new())),
+            route("GET", "/api/v1/blank", MockResponse::raw(200, b"  \n".to_vec())),
+            route("GET", "/api/v1/broken", MockResponse::raw(500, Vec::new())),
+        ]);
+        match kluster.get::<Value>("/api/v1/empty") {
+            Err(KubeError::Kube(KubeErrNo::EmptyResponse)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match kluster.get_value("/api/v1/blank") {
+            Err(KubeError::Kube(KubeErrNo::EmptyResponse)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // an error with no body to explain it
+        match kluster.get_value("/api/v1/broken") {
+            Err(KubeError::Kube(KubeErrNo::Unknown)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    fn with_phase(mut pod: Value, phase: &str) -> Value {
+        pod["status"]["phase"] = json!(phase);
+        pod
+    }
+
+    #[test]
+    fn prune_failed_pods_skips_running() {
+        let mut evicted = with_phase(pod_json("evicted"), "Failed");
+        evicted["status"]["reason"] = json!("Evicted");
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods?fieldSelector=status.phase=Failed&limit=250",
+                MockResponse::json(200, json!({
+                    "metadata": {"continue": "page2"},
+                    "items": [with_phase(pod_json("crashed"), "Failed"), pod_json("running")],
+                })),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods?fieldSelector=status.phase=Failed&limit=250\
+                 &continue=page2",
+                MockResponse::json(200, json!({"metadata": {"continue": ""}, "items": [evicted]})),
+            ),
+            route(
+                "DELETE",
+                "/api/v1/namespaces/ns/pods/crashed",
+                MockResponse::json(200, json!({})),
+            ),
+            route(
+                "DELETE",
+                "/api/v1/namespaces/ns/pods/evicted",
+                MockResponse::json(403, status_body(403, "forbidden")),
+            ),
+        ]);
+        let results = kluster.prune_failed_pods(Some("ns")).unwrap();
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["crashed", "evicted"]);
+        assert!(results[0].result.is_ok());
+        assert!(results[1].result.is_err());
+        let deletes: Vec<String> = log.lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.method == "DELETE")
+            .map(|r| r.path.clone())
+            .collect();
+        assert_eq!(
+            deletes,
+            vec!["/api/v1/namespaces/ns/pods/crashed", "/api/v1/namespaces/ns/pods/evicted"]
+        );
+    }
+
+    #[test]
+    fn session_cache_is_shared() {
+        // each connection's tls config gets its own clone of the cache, so a session stored by one
+        // connection's handshake is there for the next to resume
+        let cache = SharedSessionCache::new();
+        let mut first_conn = cache.clone();
+        let mut second_conn = cache.clone();
+        assert_eq!(second_conn.get(b"session-key"), None);
+        assert!(first_conn.put(b"session-key".to_vec(), b"ticket".to_vec()));
+        assert_eq!(second_conn.get(b"session-key"), Some(b"ticket".to_vec()));
+    }
+
+    #[test]
+    fn request_token_fixture() {
+        let (kluster, log) = mock_kluster(vec![route(
+            "POST",
+            "/api/v1/namespaces/ns/serviceaccounts/builder/token",
+            MockResponse::json(201, json!({
+                "kind": "TokenRequest",
+                "apiVersion": "authentication.k8s.io/v1",
+                "spec": {"audiences": ["vault"], "expirationSeconds": 600},
+                "status": {"token": "eyJhbGciOi", "expirationTimestamp": "2018-03-01T10:10:00Z"},
+            })),
+        )]);
+        let status = kluster.request_token("ns", "builder", &["vault"], 600).unwrap();
+        assert_eq!(status.token, "eyJhbGciOi");
+        assert_eq!(status.expiration_timestamp.to_rfc3339(), "2018-03-01T10:10:00+00:00");
+
+        let log = log.lock().unwrap();
+        assert_eq!(log[0].method, "POST");
+        assert_eq!(log[0].header("content-type"), Some("application/json"));
+        assert_eq!(
+            log[0].body_json(),
+            json!({
+                "kind": "TokenRequest",
+                "apiVersion": "authentication.k8s.io/v1",
+                "spec": {"audiences": ["vault"], "expirationSeconds": 600},
+            })
+        );
+    }
+
+    /// A node with the given Ready status and allocatable cpu and memory
+    fn node_json(name: &str, ready: &str, cpu: &str, memory: &str) -> Value {
+        json!({
+            "metadata": {"name": name},
+            "spec": {},
+            "status": {
+                "conditions": [{"type": "Ready", "status": ready}],
+                "allocatable": {"cpu": cpu, "memory": memory, "pods": "110"},
+                "capacity": {"cpu": cpu, "memory": memory, "pods": "110"},
+            },
+        })
+    }
+
+    #[test]
+    fn capacity_of_ready_nodes() {
+        let nodes: NodeList = serde_json::from_value(json!({"items": [
+            node_json("n1", "True", "3920m", "8Gi"),
+            node_json("n2", "True", "4", "16393216Ki"),
+            node_json("n3", "False", "16", "64Gi"),
+        ]})).unwrap();
+        assert!(!nodes.items[2].is_ready());
+        assert_eq!(nodes.items[2].allocatable("pods"), Some(Quantity::from_units(110)));
+        let summary = nodes.capacity_summary();
+        assert_eq!(summary.ready_nodes, 2);
+        assert_eq!(summary.total_nodes, 3);
+        assert_eq!(summary.cpu.to_string(), "7920m");
+        assert_eq!(summary.memory.to_string(), "24201Mi");
+    }
+
+    #[test]
+    fn limit_ratios_bounded_and_unbounded() {
+        let mut pod = pod_json("mixed");
+        pod["spec"]["containers"] = json!([
+            {
+                "name": "bounded",
+                "image": "app:1",
+                "resources": {
+                    "requests": {"cpu": "250m", "memory": "256Mi"},
+                    "limits": {"cpu": "1", "memory": "256Mi", "ephemeral-storage": "1Gi"},
+                },
+            },
+            {"name": "unbounded", "image": "sidecar:1", "resources": {"requests": {"cpu": "100m"}}},
+            {"name": "bare", "image": "sidecar:1"},
+        ]);
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let limits = pod.limit_ratios();
+        assert_eq!(limits.len(), 3);
+
+        let bounded = &limits[0];
+        assert_eq!(bounded.container, "bounded");
+        assert!(!bounded.unbounded);
+        let ratios: Vec<(&str, Option<f64>)> = bounded
+            .ratios
+            .iter()
+            .map(|r| (r.resource.as_str(), r.ratio))
+            .collect();
+        // a limit without a request defaults the request to the limit
+        assert_eq!(
+            ratios,
+            vec![("cpu", Some(4.0)), ("ephemeral-storage", Some(1.0)), ("memory", Some(1.0))]
+        );
+
+        let unbounded = &limits[1];
+        assert!(unbounded.unbounded);
+        assert_eq!(unbounded.ratios.len(), 1);
+        assert_eq!(unbounded.ratios[0].request, Some("100m".parse().unwrap()));
+        assert_eq!(unbounded.ratios[0].limit, None);
+        assert_eq!(unbounded.ratios[0].ratio, None);
+
+        assert!(limits[2].unbounded);
+        assert!(limits[2].ratios.is_empty());
+    }
+
+    #[test]
+    fn consumers_of_config_map() {
+        let mut mounts = pod_json("mounts");
+        mounts["spec"]["volumes"] = json!([
+            {"name": "config", "configMap": {"name": "app-config"}},
+            {"name": "creds", "secret": {"secretName": "app-config"}},
+        ]);
+        let mut projected = pod_json("projected");
+        projected["spec"]["volumes"] = json!([{"name": "all", "projected": {"sources": [
+            {"secret": {"name": "other"}},
+            {"configMap": {"name": "app-config"}},
+        ]}}]);
+        let mut env = pod_json("env");
+        env["spec"]["initContainers"] = json!([{
+            "name": "init",
+            "image": "init:1",
+            "envFrom": [{"configMapRef": {"name": "app-config"}}],
+        }]);
+        let mut secret_only = pod_json("secret-only");
+        secret_only["spec"]["containers"][0]["env"] = json!([
+            {"name": "PLAIN", "value": "app-config"},
+            {"name": "PASS", "valueFrom": {"secretKeyRef": {"name": "app-config", "key": "p"}}},
+        ]);
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods",
+            MockResponse::json(200, json!({
+                "items": [mounts, projected, env, secret_only, pod_json("unrelated")],
+            })),
+        )]);
+
+        let names = |pods: Vec<Pod>| -> Vec<String> {
+            pods.into_iter().map(|p| p.metadata.name).collect()
+        };
+        let config_map = kluster.consumers_of("ns", ConfigRefKind::ConfigMap, "app-config");
+        assert_eq!(names(config_map.unwrap()), vec!["mounts", "projected", "env"]);
+        let secret = kluster.consumers_of("ns", ConfigRefKind::Secret, "app-config");
+        assert_eq!(names(secret.unwrap()), vec!["mounts", "secret-only"]);
+    }
+
+    #[test]
+    fn selector_match_labels_only() {
+        let dep: Deployment = serde_json::from_value(json!({
+            "metadata": {"name": "web"},
+            "spec": {"selector": {"matchLabels": {"tier": "frontend", "app": "web"}}},
+            "status": {},
+        })).unwrap();
+        // sorted, so the string is stable
+        assert_eq!(dep.selector_string(), Some("app=web,tier=frontend".to_owned()));
+
+        let ds: DaemonSet = serde_json::from_value(json!({
+            "metadata": {"name": "agent"},
+            "spec": {"selector": {"matchLabels": {"name": "agent"}}},
+        })).unwrap();
+        assert_eq!(ds.selector_string(), Some("name=agent".to_owned()));
+    }
+
+    #[test]
+    fn selector_match_expressions() {
+        let rs: ReplicaSet = serde_json::from_value(json!({
+            "metadata": {"name": "web-5d9f"},
+            "spec": {"selector": {
+                "matchLabels": {"app": "web"},
+                "matchExpressions": [
+                    {"key": "tier", "operator": "In", "values": ["frontend", "edge"]},
+                    {"key": "env", "operator": "NotIn", "values": ["dev"]},
+                    {"key": "release", "operator": "Exists"},
+                    {"key": "canary", "operator": "DoesNotExist"},
+                ],
+            }},
+        })).unwrap();
+        let selector = rs.selector_string().unwrap();
+        assert_eq!(
+            selector,
+            "app=web,tier in (frontend,edge),env notin (dev),release,!canary"
+        );
+
+        let sts: StatefulSet = serde_json::from_value(json!({
+            "metadata": {"name": "db"},
+            "spec": {"selector": {"matchExpressions": [
+                {"key": "app", "operator": "Matches", "values": ["d.*"]},
+            ]}},
+        })).unwrap();
+        assert_eq!(sts.selector_string(), None);
+
+        let (kluster, log) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods",
+            MockResponse::json(200, json!({"items": []})),
+        )]);
+        let pods: PodList = kluster
+            .list_with_selector("/api/v1/namespaces/ns/pods", selector.as_str())
+            .unwrap();
+        assert!(pods.items.is_empty());
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/api/v1/namespaces/ns/pods?labelSelector=app%3Dweb%2Ctier%20in%20%28frontend\
+                 %2Cedge%29%2Cenv%20notin%20%28dev%29%2Crelease%2C%21canary",
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_nodes_reports_ready_flip() {
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/nodes?resourceVersion=5&watch=true",
+                watch_stream(vec![
+                    ("MODIFIED", with_resource_version(node_json("n1", "True", "4", "8Gi"), "6")),
+                    ("ADDED", with_resource_version(node_json("n2", "False", "4", "8Gi"), "7")),
+                    ("MODIFIED", with_resource_version(node_json("n1", "False", "4", "8Gi"), "8")),
+                    ("MODIFIED", with_resource_version(node_json("n2", "True", "4", "8Gi"), "9")),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/nodes",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "5"},
+                    "items": [node_json("n1", "True", "4", "8Gi")],
+                })),
+            ),
+        ]);
+        let mut flips = Vec::new();
+        kluster
+            .watch_nodes(|node| {
+                flips.push((node.metadata.name.clone(), node.is_ready()));
+                flips.len() < 2
+            })
+            .unwrap();
+        assert_eq!(flips, vec![("n1".to_owned(), false), ("n2".to_owned(), true)]);
+    }
+
+    #[test]
+    fn collection_paths() {
+        for path in [
+            "/api/v1/pods",
+            "/api/v1/namespaces",
+            "/api/v1/namespaces/ns/pods",
+            "/api/v1/namespaces/ns/pods?labelSelector=app%3Dweb",
+            "/apis/apps/v1/namespaces/ns/deployments",
+            "/apis/apps/v1/deployments?limit=500",
+        ].iter()
+        {
+            assert!(is_collection_path(path), "{} is a collection", path);
+        }
+        for path in [
+            "/api/v1/namespaces/ns",
+            "/api/v1/namespaces/ns/pods/web-1",
+            "/api/v1/namespaces/ns/pods/web-1/log",
+            "/api/v1/nodes/n1",
+            "/apis/apps/v1/namespaces/ns/deployments/web/scale",
+            "/api/v1/namespaces/ns/pods?watch=true",
+            "/api/v1/pods?resourceVersion=5&watch=1",
+            "/version",
+            "/apis",
+        ].iter()
+        {
+            assert!(!is_collection_path(path), "{} is not a collection", path);
+        }
+    }
+
+    #[test]
+    fn gzip_requested_for_lists_only() {
+        // {"items":[]} gzipped, by python's gzip module
+        let hex = "1f8b08000000000002ffab56ca2c49cd2d56b28a8ead05001d1f43ea0c000000";
+        let gzipped: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods",
+                MockResponse::raw(200, gzipped).header("Content-Encoding", "gzip"),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods/web-1",
+                MockResponse::json(200, pod_json("web-1")),
+            ),
+        ]);
+        let list: PodList = kluster.get("/api/v1/namespaces/ns/pods").unwrap();
+        assert!(list.items.is_empty());
+        let pod: Pod = kluster.get("/api/v1/namespaces/ns/pods/web-1").unwrap();
+        assert_eq!(pod.metadata.name, "web-1");
+        let log = log.lock().unwrap();
+        assert_eq!(log[0].header("accept-encoding"), Some("gzip"));
+        assert_eq!(log[1].header("accept-encoding"), None);
+    }
+
+    #[test]
+    fn explain_request_paths() {
+        let (kluster, log) = mock_kluster(vec![]);
+        assert_eq!(
+            kluster.explain_request("pods -n kube-system -l app=dns,tier!=web").unwrap(),
+            "/api/v1/namespaces/kube-system/pods?labelSelector=app%3Ddns%2Ctier%21%3Dweb"
+        );
+        assert_eq!(
+            kluster.explain_request("deploy -l app=web").unwrap(),
+            "/apis/apps/v1/deployments?labelSelector=app%3Dweb"
+        );
+        // cluster scoped kinds ignore the namespace
+        assert_eq!(kluster.explain_request("no/worker-1").unwrap(), "/api/v1/nodes/worker-1");
+        assert_eq!(
+            kluster.explain_request("Node worker-1 -n prod").unwrap(),
+            "/api/v1/nodes/worker-1"
+        );
+        assert_eq!(
+            kluster.explain_request("cm settings").unwrap(),
+            "/api/v1/namespaces/default/configmaps/settings"
+        );
+        assert!(kluster.explain_request("").is_err());
+        assert!(kluster.explain_request("pods a b").is_err());
+        assert!(log.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn explain_request_discovers_custom_kinds() {
+        let groups = json!({"kind": "APIGroupList", "groups": [
+            {"name": "example.com",
+             "versions": [{"groupVersion": "example.com/v1", "version": "v1"},
+                          {"groupVersion": "example.com/v1beta1", "version": "v1beta1"}],
+             "preferredVersion": {"groupVersion": "example.com/v1", "version": "v1"}},
+            {"name": "metrics.k8s.io",
+             "versions": [{"groupVersion": "metrics.k8s.io/v1beta1", "version": "v1beta1"}]},
+        ]});
+        let core = json!({"kind": "APIResourceList", "resources": [
+            {"name": "pods", "singularName": "pod", "namespaced": true, "kind": "Pod"},
+        ]});
+        let example = json!({"kind": "APIResourceList", "resources": [
+            {"name": "widgets/status", "singularName": "", "namespaced": true, "kind": "Widget"},
+            {"name": "widgets", "singularName": "widget", "namespaced": true, "kind": "Widget",
+             "shortNames": ["wd"]},
+            {"name": "gizmos", "singularName": "gizmo", "namespaced": false, "kind": "Gizmo"},
+        ]});
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/apis", MockResponse::json(200, groups)),
+            route("GET", "/api/v1", MockResponse::json(200, core)),
+            route("GET", "/apis/example.com/v1", MockResponse::json(200, example)),
+            // metrics.k8s.io is down, which shouldn't stop other kinds resolving
+            route(
+                "GET",
+                "/apis/metrics.k8s.io/v1beta1",
+                MockResponse::json(503, status_body(503, "unavailable")),
+            ),
+        ]);
+        assert_eq!(
+            kluster.explain_request("wd/big -n prod").unwrap(),
+            "/apis/example.com/v1/namespaces/prod/widgets/big"
+        );
+        assert_eq!(
+            kluster.explain_request("Widget -l size=big").unwrap(),
+            "/apis/example.com/v1/widgets?labelSelector=size%3Dbig"
+        );
+        assert_eq!(
+            kluster.explain_request("gizmo g1 -n prod").unwrap(),
+            "/apis/example.com/v1/gizmos/g1"
+        );
+        match kluster.explain_request("sprockets") {
+            Err(KubeError::ParseErr(msg)) => assert_eq!(msg, "Unknown kind: sprockets"),
+            other => panic!("expected an unknown kind, got {:?}", other),
+        }
+        let paths = paths(&log);
+        assert!(paths.contains(&"/apis/example.com/v1".to_owned()));
+        assert!(!paths.contains(&"/apis/example.com/v1beta1".to_owned()));
+    }
+
+    #[test]
+    fn control_plane_toleration_and_affinity() {
+        let mut pod = pod_json("etcd-backup");
+        pod["spec"]["tolerations"] = json!([
+            {"key": "node-role.kubernetes.io/control-plane", "operator": "Exists",
+             "effect": "NoSchedule"},
+            {"key": "node.kubernetes.io/not-ready", "operator": "Exists", "effect": "NoExecute",
+             "tolerationSeconds": 300},
+            {"key": "dedicated", "value": "etcd", "effect": "NoSchedule"},
+        ]);
+        pod["spec"]["affinity"] = json!({"nodeAffinity": {
+            "requiredDuringSchedulingIgnoredDuringExecution": {"nodeSelectorTerms": [
+                {"matchExpressions": [
+                    {"key": "node-role.kubernetes.io/control-plane", "operator": "Exists"},
+                    {"key": "zone", "operator": "In", "values": ["a", "b"]},
+                ]},
+            ]},
+            "preferredDuringSchedulingIgnoredDuringExecution": [
+                {"weight": 10, "preference": {"matchExpressions": [
+                    {"key": "cpu-count", "operator": "Gt", "values": ["8"]},
+                ]}},
+            ],
+        }});
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let tolerations: Vec<String> = pod.spec
+            .tolerations
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(
+            tolerations,
+            vec![
+                "node-role.kubernetes.io/control-plane:NoSchedule op=Exists",
+                "node.kubernetes.io/not-ready:NoExecute op=Exists for 300s",
+                "dedicated=etcd:NoSchedule",
+            ]
+        );
+        let affinity = pod.spec.affinity.as_ref().unwrap().node_affinity.as_ref().unwrap();
+        assert_eq!(
+            affinity.summary(),
+            vec![
+                "required: node-role.kubernetes.io/control-plane, zone in (a,b)",
+                "preferred (weight 10): cpu-count>8",
+            ]
+        );
+        // both are optional
+        let pod: Pod = serde_json::from_value(pod_json("plain")).unwrap();
+        assert!(pod.spec.tolerations.is_none() && pod.spec.affinity.is_none());
+    }
+
+    #[test]
+    fn orphaned_pods_skips_controlled_and_mirror() {
+        let mut controlled = pod_json("web-5d9c-x2");
+        controlled["metadata"]["ownerReferences"] = json!([
+            {"kind": "ReplicaSet", "name": "web-5d9c", "uid": "rs-uid", "controller": true},
+        ]);
+        let mut mirror = pod_json("kube-apiserver-master");
+        mirror["metadata"]["annotations"] = json!({"kubernetes.io/config.mirror": "abc123"});
+        mirror["metadata"]["ownerReferences"] = json!([
+            {"kind": "Node", "name": "master", "uid": "node-uid", "controller": true},
+        ]);
+        // an owner that isn't the controller doesn't keep the pod alive
+        let mut bare = pod_json("debug");
+        bare["metadata"]["ownerReferences"] = json!([
+            {"kind": "ConfigMap", "name": "settings", "uid": "cm-uid"},
+        ]);
+
+        let pods: Vec<Pod> = vec![&controlled, &mirror, &bare]
+            .into_iter()
+            .map(|p| serde_json::from_value(p.clone()).unwrap())
+            .collect();
+        assert_eq!(
+            pods[0].ownership(),
+            PodOwnership::Controlled("ReplicaSet", "web-5d9c")
+        );
+        assert_eq!(pods[1].ownership(), PodOwnership::Mirror);
+        assert_eq!(pods[2].ownership(), PodOwnership::Bare);
+
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods",
+            MockResponse::json(200, json!({"items": [controlled, mirror, bare]})),
+        )]);
+        let orphans = kluster.orphaned_pods("ns").unwrap();
+        let names: Vec<&str> = orphans.iter().map(|p| p.metadata.name.as_str()).collect();
+        assert_eq!(names, vec!["debug"]);
+    }
+
+    #[test]
+    fn oom_killed_last_state() {
+        let mut pod = pod_json("hungry");
+        pod["status"]["containerStatuses"][0]["restartCount"] = json!(4);
+        pod["status"]["containerStatuses"][0]["lastState"] = json!({"terminated": {
+            "exitCode": 137,
+            "reason": "OOMKilled",
+            "startedAt": "2018-01-01T00:00:00Z",
+            "finishedAt": "2018-01-01T00:05:00Z",
+        }});
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let status = &pod.status.container_statuses.as_ref().unwrap()[0];
+        let last = status.last_terminated().unwrap();
+        assert_eq!(last.exit_code, 137);
+        assert_eq!(last.reason.as_ref().map(|r| r.as_str()), Some("OOMKilled"));
+        assert_eq!(
+            status.last_termination_summary().unwrap(),
+            "exited with code 137 (OOMKilled) at 2018-01-01 00:05:00 UTC"
+        );
+
+        // a container that has never restarted has an empty lastState
+        let mut pod = pod_json("fresh");
+        pod["status"]["containerStatuses"][0]["lastState"] = json!({});
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let status = &pod.status.container_statuses.as_ref().unwrap()[0];
+        assert!(status.last_terminated().is_none());
+        assert!(status.last_termination_summary().is_none());
+    }
+
+    #[test]
+    fn containers_merge_key_from_openapi() {
+        let doc = json!({"definitions": {
+            "io.k8s.api.core.v1.Pod": {
+                "x-kubernetes-group-version-kind": [{"group": "", "kind": "Pod", "version": "v1"}],
+                "properties": {"spec": {"$ref": "#/definitions/io.k8s.api.core.v1.PodSpec"}},
+            },
+            "io.k8s.api.apps.v1.Deployment": {
+                "x-kubernetes-group-version-kind": [
+                    {"group": "apps", "kind": "Deployment", "version": "v1"},
+                ],
+                "properties": {"spec": {"$ref": "#/definitions/io.k8s.api.apps.v1.DeploymentSpec"}},
+            },
+            "io.k8s.api.apps.v1.DeploymentSpec": {"properties": {
+                "template": {"$ref": "#/definitions/io.k8s.api.core.v1.PodTemplateSpec"},
+            }},
+            "io.k8s.api.core.v1.PodTemplateSpec": {"properties": {
+                "spec": {"$ref": "#/definitions/io.k8s.api.core.v1.PodSpec"},
+            }},
+            "io.k8s.api.core.v1.PodSpec": {"properties": {
+                "containers": {
+                    "type": "array",
+                    "items": {"$ref": "#/definitions/io.k8s.api.core.v1.Container"},
+                    "x-kubernetes-patch-merge-key": "name",
+                    "x-kubernetes-patch-strategy": "merge",
+                },
+                "nodeName": {"type": "string"},
+            }},
+            "io.k8s.api.core.v1.Container": {"properties": {
+                "ports": {
+                    "type": "array",
+                    "x-kubernetes-patch-merge-key": "containerPort",
+                    "x-kubernetes-patch-strategy": "merge",
+                },
+            }},
+        }});
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/openapi/v2", MockResponse::json(200, doc)),
+        ]);
+        let by_name = Some(PatchStrategy {
+            strategy: Some("merge".to_owned()),
+            merge_key: Some("name".to_owned()),
+        });
+        let pod = GroupVersionKind::new("", "v1", "Pod");
+        assert_eq!(kluster.patch_strategy(&pod, "spec.containers").unwrap(), by_name);
+        let deployment = GroupVersionKind::new("apps", "v1", "Deployment");
+        assert_eq!(
+            kluster.patch_strategy(&deployment, "spec.template.spec.containers").unwrap(),
+            by_name
+        );
+        // through the containers list to a field of each container
+        assert_eq!(
+            kluster.patch_strategy(&pod, "spec.containers.ports").unwrap(),
+            Some(PatchStrategy {
+                strategy: Some("merge".to_owned()),
+                merge_key: Some("containerPort".to_owned()),
+            })
+        );
+        assert_eq!(
+            kluster.patch_strategy(&pod, "spec.nodeName").unwrap(),
+            Some(PatchStrategy { strategy: None, merge_key: None })
+        );
+        assert_eq!(kluster.patch_strategy(&pod, "spec.missing").unwrap(), None);
+        let unknown = GroupVersionKind::new("apps", "v1", "Pod");
+        assert_eq!(kluster.patch_strategy(&unknown, "spec.containers").unwrap(), None);
+        // the document is only fetched once
+        assert_eq!(paths(&log), vec!["/openapi/v2"]);
+    }
+
+    #[test]
+    fn unauthorized_keeps_server_message() {
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces",
+                MockResponse::json(401, status_body(401, "token has expired")),
+            ),
+            route("GET", "/api/v1/nodes", MockResponse::raw(401, b"Unauthorized\n".to_vec())),
+        ]);
+        match kluster.get::<NamespaceList>("/api/v1/namespaces") {
+            Err(KubeError::Kube(KubeErrNo::Unauthorized(Some(ref msg)))) => {
+                assert_eq!(msg, "token has expired")
+            }
+            other => panic!("expected Unauthorized with a message, got {:?}", other),
+        }
+        let err = kluster.get::<NamespaceList>("/api/v1/namespaces").unwrap_err();
+        assert!(err.to_string().ends_with("(server said: token has expired)"));
+        // a body that isn't a Status just means there's no message
+        match kluster.get::<NodeList>("/api/v1/nodes") {
+            Err(KubeError::Kube(KubeErrNo::Unauthorized(None))) => {}
+            other => panic!("expected Unauthorized without a message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn dump_two_key_configmap() {
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/configmaps/settings",
+            MockResponse::json(
+                200,
+                json!({
+                    "metadata": {"name": "settings", "namespace": "ns"},
+                    "data": {"app.properties": "color=blue\n", "../escape": "nope"},
+                    "binaryData": {"logo.png": "iVBORw0KGgo="},
+                }),
+            ),
+        )]);
+        let tmp = TempDir::new("click-dump").unwrap();
+        let dir = tmp.path().join("settings");
+        let written = kluster.dump_configmap("ns", "settings", dir.as_path()).unwrap();
+        assert_eq!(
+            written,
+            vec![dir.join(".._escape"), dir.join("app.properties"), dir.join("logo.png")]
+        );
+        let read = |name: &str| fs::read(dir.join(name)).unwrap();
+        assert_eq!(read("app.properties"), b"color=blue\n".to_vec());
+        assert_eq!(read(".._escape"), b"nope".to_vec());
+        assert_eq!(read("logo.png"), b"\x89PNG\r\n\x1a\n".to_vec());
+        // nothing escaped the directory
+        assert!(!tmp.path().join("escape").exists());
+
+        assert_eq!(key_to_filename(".."), "_..");
+        assert_eq!(key_to_filename(""), "_");
+        assert_eq!(key_to_filename("a\\b"), "a_b");
+    }
+
+    #[test]
+    fn delete_dry_run_query() {
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "DELETE",
+                "/api/v1/namespaces/ns/pods/web-1?dryRun=All",
+                MockResponse::json(200, pod_json("web-1")),
+            ),
+            route(
+                "DELETE",
+                "/api/v1/namespaces/ns/pods/web-2?gracePeriodSeconds=0&dryRun=All",
+                MockResponse::json(200, pod_json("web-2")),
+            ),
+        ]);
+        let body = json!({"kind": "DeleteOptions", "propagationPolicy": "Foreground"});
+        let resp = kluster
+            .delete_dry_run("/api/v1/namespaces/ns/pods/web-1", Some(body.to_string()))
+            .unwrap();
+        assert!(resp.status.is_success());
+        let pod: Pod = kluster.parse_body(resp).unwrap();
+        assert_eq!(pod.metadata.name, "web-1");
+        let resp = kluster
+            .delete_dry_run("/api/v1/namespaces/ns/pods/web-2?gracePeriodSeconds=0", None)
+            .unwrap();
+        assert!(resp.status.is_success());
+
+        // only the dry runs were sent, so nothing was deleted
+        let requests = log.lock().unwrap();
+        assert!(requests.iter().all(|r| r.method == "DELETE" && r.path.ends_with("dryRun=All")));
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].body_json(), body);
+    }
+
+    fn workload_json(name: &str, desired: u32, ready: u32) -> Value {
+        json!({
+            "metadata": {"name": name, "namespace": "ns", "generation": 4},
+            "spec": {"replicas": desired},
+            "status": {
+                "observedGeneration": 4,
+                "replicas": desired,
+                "readyReplicas": ready,
+                "availableReplicas": ready,
+            },
+        })
+    }
+
+    #[test]
+    fn scaling_report_converged_and_under_scaled() {
+        let mut owned = workload_json("web-5d9c", 3, 3);
+        owned["metadata"]["ownerReferences"] = json!([
+            {"kind": "Deployment", "name": "web", "uid": "web-uid", "controller": true},
+        ]);
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/deployments",
+                MockResponse::json(200, json!({"items": [workload_json("web", 3, 3)]})),
+            ),
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/statefulsets",
+                MockResponse::json(200, json!({"items": [workload_json("db", 3, 1)]})),
+            ),
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/replicasets",
+                MockResponse::json(200, json!({"items": [owned, workload_json("cache", 2, 2)]})),
+            ),
+        ]);
+        let report = kluster.scaling_report(Some("ns")).unwrap();
+        let summary: Vec<(&str, &str, u32, u32, bool)> = report
+            .iter()
+            .map(|w| (w.kind, w.name.as_str(), w.desired, w.ready, w.converged))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Deployment", "web", 3, 3, true),
+                ("StatefulSet", "db", 3, 1, false),
+                ("ReplicaSet", "cache", 2, 2, true),
+            ]
+        );
+        assert!(report.iter().all(|w| w.namespace == "ns"));
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn http_get_liveness_probe() {
+        let mut pod = pod_json("web");
+        pod["spec"]["containers"][0]["livenessProbe"] = json!({
+            "httpGet": {"path": "/healthz", "port": 8080, "scheme": "HTTPS"},
+            "initialDelaySeconds": 15,
+            "failureThreshold": 5,
+        });
+        pod["spec"]["containers"][0]["readinessProbe"] = json!({
+            "tcpSocket": {"port": "http"},
+            "periodSeconds": 5,
+        });
+        pod["spec"]["containers"][0]["startupProbe"] = json!({
+            "exec": {"command": ["cat", "/tmp/started"]},
+        });
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let container = &pod.spec.containers[0];
+        let liveness = container.liveness_probe.as_ref().unwrap();
+        let http = liveness.http_get.as_ref().unwrap();
+        assert_eq!(http.path.as_ref().map(|p| p.as_str()), Some("/healthz"));
+        match http.port {
+            IntOrString::Int(8080) => {}
+            ref port => panic!("unexpected port {:?}", port),
+        }
+        // thresholds that weren't given take the api server's defaults
+        assert_eq!(
+            (liveness.timeout_seconds, liveness.period_seconds, liveness.success_threshold),
+            (1, 10, 1)
+        );
+        assert_eq!(
+            liveness.to_string(),
+            "http-get https://:8080/healthz delay=15s timeout=1s period=10s #success=1 #failure=5"
+        );
+        assert_eq!(
+            container.readiness_probe.as_ref().unwrap().to_string(),
+            "tcp-socket :http delay=0s timeout=1s period=5s #success=1 #failure=3"
+        );
+        assert_eq!(
+            container.startup_probe.as_ref().unwrap().to_string(),
+            "exec [cat /tmp/started] delay=0s timeout=1s period=10s #success=1 #failure=3"
+        );
+    }
+
+    fn ready_pod(name: &str, ready: bool) -> Value {
+        let mut pod = pod_json(name);
+        pod["status"]["conditions"] =
+            json!([{"type": "Ready", "status": if ready { "True" } else { "False" }}]);
+        pod
+    }
+
+    #[test]
+    fn rolling_restart_two_pods() {
+        let mut deleting = ready_pod("web-a", true);
+        deleting["metadata"]["deletionTimestamp"] = json!("2018-01-01T00:00:00Z");
+        let lists = vec![
+            vec![ready_pod("web-a", true), ready_pod("web-b", true)],
+            // web-a is on its way out, which doesn't count as live
+            vec![deleting, ready_pod("web-b", true)],
+            vec![ready_pod("web-b", true), ready_pod("web-c", false)],
+            vec![ready_pod("web-b", true), ready_pod("web-c", true)],
+            vec![ready_pod("web-c", true), ready_pod("web-d", true)],
+        ];
+        let (kluster, log) = mock_kluster(vec![
+            (
+                "GET",
+                "/api/v1/namespaces/ns/pods?labelSelector=app%3Dweb".to_owned(),
+                lists
+                    .into_iter()
+                    .map(|items| MockResponse::json(200, json!({"items": items})))
+                    .collect(),
+            ),
+            route(
+                "POST",
+                "/api/v1/namespaces/ns/pods/web-a/eviction",
+                MockResponse::json(201, json!({"kind": "Status", "status": "Success"})),
+            ),
+            (
+                "POST",
+                "/api/v1/namespaces/ns/pods/web-b/eviction".to_owned(),
+                vec![
+                    MockResponse::json(429, status_body(429, "Cannot evict pod, budget")),
+                    MockResponse::json(201, json!({"kind": "Status", "status": "Success"})),
+                ],
+            ),
+        ]);
+        let evicted = kluster
+            .rolling_restart_pods_polling("ns", "app=web", Duration::from_millis(1))
+            .unwrap();
+        assert_eq!(evicted, vec!["web-a", "web-b"]);
+
+        // web-b is only evicted once web-a's replacement is ready, and retried past the budget
+        let requests: Vec<String> = log.lock()
+            .unwrap()
+            .iter()
+            .map(|r| {
+                format!("{} {}", r.method, r.path.trim_start_matches("/api/v1/namespaces/ns/pods"))
+            })
+            .collect();
+        assert_eq!(
+            requests,
+            vec![
+                "GET ?labelSelector=app%3Dweb",
+                "POST /web-a/eviction",
+                "GET ?labelSelector=app%3Dweb",
+                "GET ?labelSelector=app%3Dweb",
+                "GET ?labelSelector=app%3Dweb",
+                "POST /web-b/eviction",
+                "POST /web-b/eviction",
+                "GET ?labelSelector=app%3Dweb",
+            ]
+        );
+        let eviction = log.lock().unwrap()[1].body_json();
+        assert_eq!(eviction["kind"], "Eviction");
+        assert_eq!(eviction["metadata"]["name"], "web-a");
+    }
+
+    #[test]
+    fn watch_diff_one_field() {
+        let mut updated = with_resource_version(pod_json("web"), "12");
+        updated["spec"]["containers"][0]["image"] = json!("app:2");
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=10&watch=true",
+                watch_stream(vec![
+                    // only the resourceVersion changes, so there's nothing to report
+                    ("MODIFIED", with_resource_version(pod_json("web"), "11")),
+                    ("MODIFIED", updated),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "10"},
+                    "items": [with_resource_version(pod_json("web"), "10")],
+                })),
+            ),
+        ]);
+        let mut seen = Vec::new();
+        kluster
+            .watch_diff("/api/v1/pods", |key, diffs| {
+                for d in diffs.iter() {
+                    seen.push((key.to_owned(), d.path.clone(), d.old.clone(), d.new.clone()));
+                }
+                false
+            })
+            .unwrap();
+        assert_eq!(
+            seen,
+            vec![(
+                "ns/web".to_owned(),
+                "/spec/containers/0/image".to_owned(),
+                Some(json!("app:1")),
+                Some(json!("app:2")),
+            )]
+        );
+    }
+
+    #[test]
+    fn watch_diff_returns_out_of_order_events() {
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=10&watch=true",
+                watch_stream(vec![(
+                    "MODIFIED",
+                    with_resource_version(with_phase(pod_json("web"), "Pending"), "9"),
+                )]),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "10"},
+                    "items": [with_resource_version(pod_json("web"), "10")],
+                })),
+            ),
+        ]);
+        let mut calls = 0;
+        let result = kluster.watch_diff("/api/v1/pods", |_, _| {
+            calls += 1;
+            true
+        });
+        match result {
+            Err(KubeError::Kube(KubeErrNo::WatchInconsistency(_))) => {}
+            other => panic!("expected a WatchInconsistency, got {:?}", other),
+        }
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn stale_and_current_status() {
+        assert!(has_stale_status(Some(3), Some(2)));
+        assert!(!has_stale_status(Some(3), Some(3)));
+        assert!(!has_stale_status(Some(3), Some(4)));
+        // unknown isn't stale
+        assert!(!has_stale_status(Some(3), None));
+        assert!(!has_stale_status(None, Some(3)));
+        assert!(!has_stale_status(None, None));
+
+        let dep: Deployment = serde_json::from_value(deployment_json(2, 1, 3, 3, 3)).unwrap();
+        assert!(dep.has_stale_status());
+        assert_eq!(dep.rollout_status(), RolloutStatus::Pending);
+        let dep: Deployment = serde_json::from_value(deployment_json(2, 2, 3, 3, 3)).unwrap();
+        assert!(!dep.has_stale_status());
+        assert_eq!(dep.rollout_status(), RolloutStatus::Complete);
+        // a status without observedGeneration is taken at its word
+        let mut unobserved = deployment_json(2, 2, 3, 3, 3);
+        unobserved["status"].as_object_mut().unwrap().remove("observedGeneration");
+        let dep: Deployment = serde_json::from_value(unobserved).unwrap();
+        assert_eq!(dep.rollout_status(), RolloutStatus::Complete);
+
+        let mut set = workload_json("db", 3, 3);
+        set["status"]["observedGeneration"] = json!(3);
+        let set: StatefulSet = serde_json::from_value(set).unwrap();
+        assert!(set.has_stale_status());
+        let mut set = workload_json("db", 3, 3);
+        set["metadata"].as_object_mut().unwrap().remove("generation");
+        let set: ReplicaSet = serde_json::from_value(set).unwrap();
+        assert!(!set.has_stale_status());
+        let mut set = workload_json("db", 3, 0);
+        set.as_object_mut().unwrap().remove("status");
+        let set: StatefulSet = serde_json::from_value(set).unwrap();
+        assert!(!set.has_stale_status());
+    }
+
+    #[test]
+    fn get_yaml_round_trips() {
+        let obj = json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "settings", "namespace": "ns", "labels": {"app": "web"}},
+            "data": {
+                "replicas": "3",
+                "enabled": "true",
+                "script": "#!/bin/sh\necho 'hi: there'\n",
+                "empty": "",
+            },
+        });
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/configmaps/settings",
+            MockResponse::json(200, obj.clone()),
+        )]);
+        let yaml = kluster.get_yaml("/api/v1/namespaces/ns/configmaps/settings").unwrap();
+        assert!(yaml.contains("kind: ConfigMap"));
+        // strings that look like other types stay strings
+        let parsed: Value = serde_yaml::from_str(yaml.as_str()).unwrap();
+        assert_eq!(parsed, obj);
+    }
+
+    /// An editor that runs the shell commands in script on the file ($1)
+    fn script_editor(dir: &TempDir, script: &str) -> String {
+        let path = dir.path().join("editor.sh");
+        File::create(&path).unwrap().write_all(script.as_bytes()).unwrap();
+        format!("sh {}", path.display())
+    }
+
+    #[test]
+    fn edit_without_changes_sends_nothing() {
+        let path = "/apis/apps/v1/namespaces/ns/deployments/web";
+        let mut obj = deployment_json(1, 1, 3, 3, 3);
+        obj["metadata"]["managedFields"] = json!([{"manager": "kubectl"}]);
+        let (kluster, log) = mock_kluster(vec![route("GET", path, MockResponse::json(200, obj))]);
+        let dir = TempDir::new("click-editor").unwrap();
+
+        match kluster.edit(path, "true").unwrap() {
+            EditOutcome::Unchanged => {}
+            other => panic!("expected no change, got {:?}", other),
+        }
+        // stripping managedFields for editing isn't a change either
+        assert_eq!(paths(&log), vec![path]);
+
+        let empty = script_editor(&dir, ": > \"$1\"\n");
+        match kluster.edit(path, empty.as_str()).unwrap() {
+            EditOutcome::Cancelled => {}
+            other => panic!("expected a cancel, got {:?}", other),
+        }
+        assert!(log.lock().unwrap().iter().all(|r| r.method == "GET"));
+    }
+
+    #[test]
+    fn edit_applies_a_merge_patch() {
+        let path = "/apis/apps/v1/namespaces/ns/deployments/web";
+        let mut updated = deployment_json(2, 1, 3, 3, 3);
+        updated["spec"]["replicas"] = json!(5);
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", path, MockResponse::json(200, deployment_json(1, 1, 3, 3, 3))),
+            (
+                "PATCH",
+                path.to_owned(),
+                vec![
+                    MockResponse::json(422, status_body(422, "spec.replicas: Invalid value")),
+                    MockResponse::json(200, updated),
+                ],
+            ),
+        ]);
+        let dir = TempDir::new("click-editor").unwrap();
+        // spec has just the one field, replicas
+        let scale = script_editor(&dir, "sed -i '/^spec:/{n;s/replicas: 3/replicas: 5/}' \"$1\"\n");
+
+        // the first patch is rejected, and the editor makes no further changes
+        match kluster.edit(path, scale.as_str()) {
+            Err(KubeError::ParseErr(ref msg)) => assert!(msg.contains("giving up")),
+            other => panic!("expected to give up, got {:?}", other),
+        }
+        match kluster.edit(path, scale.as_str()).unwrap() {
+            EditOutcome::Applied(obj) => assert_eq!(obj["spec"]["replicas"], 5),
+            other => panic!("expected the edit to apply, got {:?}", other),
+        }
+        let requests = log.lock().unwrap();
+        let patch = requests.iter().find(|r| r.method == "PATCH").unwrap();
+        assert_eq!(patch.header("Content-Type"), Some("application/merge-patch+json"));
+        assert_eq!(patch.body_json(), json!({"spec": {"replicas": 5}}));
+    }
+
+    #[test]
+    fn image_pull_failure_of_one_container() {
+        let mut web = pod_json("web");
+        web["spec"]["containers"] = json!([
+            {"name": "app", "image": "app:1"},
+            {"name": "proxy", "image": "registry.example.com/proxy:9"},
+        ]);
+        web["status"]["containerStatuses"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!({
+                "name": "proxy",
+                "image": "registry.example.com/proxy:9",
+                "restartCount": 0,
+                "ready": false,
+                "state": {"waiting": {
+                    "reason": "ImagePullBackOff",
+                    "message": "Back-off pulling image \"registry.example.com/proxy:9\"",
+                }},
+            }));
+        let mut job = pod_json("migrate");
+        job["metadata"]["namespace"] = json!("jobs");
+        job["status"]["initContainerStatuses"] = json!([{
+            "name": "fetch",
+            "image": "private/fetch:2",
+            "restartCount": 0,
+            "ready": false,
+            "state": {"waiting": {"reason": "ErrImagePull"}},
+        }]);
+        // waiting for some other reason isn't a pull failure
+        let mut crashing = pod_json("crashing");
+        crashing["status"]["containerStatuses"][0]["state"] =
+            json!({"waiting": {"reason": "CrashLoopBackOff"}});
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/pods",
+            MockResponse::json(200, json!({"items": [web, job, crashing]})),
+        )]);
+        let failures = kluster.image_pull_failures(None).unwrap();
+        let summary: Vec<(&str, &str, &str, &str, &str)> = failures
+            .iter()
+            .map(|f| {
+                (
+                    f.namespace.as_str(),
+                    f.pod.as_str(),
+                    f.container.as_str(),
+                    f.image.as_str(),
+                    f.reason.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("ns", "web", "proxy", "registry.example.com/proxy:9", "ImagePullBackOff"),
+                ("jobs", "migrate", "fetch", "private/fetch:2", "ErrImagePull"),
+            ]
+        );
+        assert_eq!(
+            failures[0].message,
+            "Back-off pulling image \"registry.example.com/proxy:9\""
+        );
+        assert_eq!(failures[1].message, "");
+    }
+
+    #[test]
+    fn subresource_paths() {
+        let dep = "/apis/apps/v1/namespaces/ns/deployments/web";
+        assert_eq!(subresource_path(dep, "scale"), format!("{}/scale", dep));
+        assert_eq!(subresource_path(&format!("{}/", dep), "/status/"), format!("{}/status", dep));
+        assert_eq!(
+            subresource_path("/api/v1/namespaces/ns/pods/web-1?pretty=true", "status"),
+            "/api/v1/namespaces/ns/pods/web-1/status?pretty=true"
+        );
+
+        let (kluster, log) = mock_kluster(vec![route(
+            "GET",
+            "/apis/apps/v1/namespaces/ns/deployments/web/scale",
+            MockResponse::json(200, json!({"kind": "Scale", "spec": {"replicas": 3}})),
+        )]);
+        let scale = kluster.get_value_subresource(dep, "scale").unwrap();
+        assert_eq!(scale["spec"]["replicas"], 3);
+        assert_eq!(paths(&log), vec![format!("{}/scale", dep)]);
+    }
+
+    #[test]
+    fn discovery_health_reports_failing_group() {
+        let group = |name: &str, versions: &[&str]| {
+            let versions: Vec<Value> = versions
+                .iter()
+                .map(|v| json!({"groupVersion": format!("{}/{}", name, v), "version": v}))
+                .collect();
+            json!({"name": name, "versions": versions})
+        };
+        let resources = json!({"kind": "APIResourceList", "resources": []});
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis",
+                MockResponse::json(200, json!({"groups": [
+                    group("apps", &["v1"]),
+                    group("metrics.k8s.io", &["v1beta1"]),
+                    group("batch", &["v1", "v1beta1"]),
+                ]})),
+            ),
+            route("GET", "/api/v1", MockResponse::json(200, resources.clone())),
+            route("GET", "/apis/apps/v1", MockResponse::json(200, resources.clone())),
+            route("GET", "/apis/batch/v1", MockResponse::json(200, resources.clone())),
+            route("GET", "/apis/batch/v1beta1", MockResponse::json(200, resources)),
+            route(
+                "GET",
+                "/apis/metrics.k8s.io/v1beta1",
+                MockResponse::json(503, status_body(503, "service unavailable")),
+            ),
+        ]);
+        let failing = kluster.discovery_health().unwrap();
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].0, "metrics.k8s.io/v1beta1");
+        match failing[0].1 {
+            KubeError::KubeServerError(ref msg) => assert_eq!(msg, "service unavailable"),
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        let mut fetched = paths(&log);
+        fetched.sort();
+        assert_eq!(
+            fetched,
+            vec![
+                "/api/v1",
+                "/apis",
+                "/apis/apps/v1",
+                "/apis/batch/v1",
+                "/apis/batch/v1beta1",
+                "/apis/metrics.k8s.io/v1beta1",
+            ]
+        );
+    }
+
+    fn event_json(
+        typ: &str,
+        reason: &str,
+        message: &str,
+        pod: &str,
+        field_path: Option<&str>,
+    ) -> Value {
+        let mut obj = json!({"kind": "Pod", "namespace": "ns", "name": pod});
+        if let Some(fp) = field_path {
+            obj["fieldPath"] = json!(fp);
+        }
+        json!({
+            "count": 1,
+            "type": typ,
+            "reason": reason,
+            "message": message,
+            "lastTimestamp": "2018-01-01T00:05:00Z",
+            "involvedObject": obj,
+        })
+    }
+
+    #[test]
+    fn oom_report_matches_status_and_events() {
+        let mut hungry = pod_json("hungry");
+        hungry["status"]["containerStatuses"][0]["restartCount"] = json!(3);
+        hungry["status"]["containerStatuses"][0]["lastState"] = json!({"terminated": {
+            "exitCode": 137,
+            "reason": "OOMKilled",
+            "finishedAt": "2018-01-01T00:05:00Z",
+        }});
+        let events = json!({"items": [
+            event_json(
+                "Warning",
+                "OOMKilling",
+                "Memory cgroup out of memory: Killed process 1234 (app)",
+                "hungry",
+                Some("spec.containers{app}"),
+            ),
+            // about the pod but not a container, so attached to its one OOM killed container
+            event_json("Warning", "Evicted", "Pod was OOM killed", "hungry", None),
+            event_json("Warning", "BackOff", "Back-off restarting", "hungry", None),
+            event_json("Warning", "OOMKilling", "Killed process 99", "gone", None),
+        ]});
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods",
+                MockResponse::json(200, json!({"items": [hungry, pod_json("fine")]})),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/events?fieldSelector=type%3DWarning",
+                MockResponse::json(200, events),
+            ),
+        ]);
+        let report = kluster.oom_report("ns").unwrap();
+        assert_eq!(report.len(), 1);
+        let kill = &report[0];
+        assert_eq!(kill.pod, "hungry");
+        assert_eq!(kill.container, Some("app".to_owned()));
+        assert_eq!(kill.restart_count, 3);
+        assert_eq!(
+            kill.killed_at.map(|t| t.to_rfc3339()),
+            Some("2018-01-01T00:05:00+00:00".to_owned())
+        );
+        assert_eq!(
+            kill.events,
+            vec![
+                "Memory cgroup out of memory: Killed process 1234 (app)",
+                "Pod was OOM killed",
+            ]
+        );
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/api/v1/namespaces/ns/pods",
+                "/api/v1/namespaces/ns/events?fieldSelector=type%3DWarning",
+            ]
+        );
+    }
+
+    #[test]
+    fn node_proxy_metrics_path_and_forbidden() {
+        assert_eq!(
+            node_proxy_path("worker-1", "/metrics/resource"),
+            "/api/v1/nodes/worker-1/proxy/metrics/resource"
+        );
+        assert_eq!(
+            node_proxy_path("worker-1", "stats/summary"),
+            "/api/v1/nodes/worker-1/proxy/stats/summary"
+        );
+
+        let metrics = "# TYPE node_cpu_usage_seconds_total counter\n\
+                       node_cpu_usage_seconds_total 42.5\n";
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/nodes/worker-1/proxy/metrics/resource",
+                MockResponse::raw(200, metrics.as_bytes().to_vec())
+                    .header("Content-Type", "text/plain; version=0.0.4"),
+            ),
+            route(
+                "GET",
+                "/api/v1/nodes/worker-2/proxy/metrics/resource",
+                MockResponse::json(
+                    403,
+                    status_body(403, "nodes \"worker-2\" is forbidden: cannot get nodes/proxy"),
+                ),
+            ),
+        ]);
+        assert_eq!(kluster.node_proxy_metrics("worker-1").unwrap(), metrics);
+        match kluster.node_proxy_metrics("worker-2") {
+            Err(KubeError::KubeServerError(ref msg)) => assert_eq!(
+                msg,
+                "nodes \"worker-2\" is forbidden: cannot get nodes/proxy (reading node metrics \
+                 needs get permission on nodes/proxy)"
+            ),
+            other => panic!("expected a forbidden error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn proxy_paths_for_services_and_pods() {
+        let svc = ProxyTarget::new("prod", "svc", "web", "https:metrics").unwrap();
+        assert_eq!(svc.resource, "services");
+        assert_eq!(
+            svc.path("/healthz"),
+            "/api/v1/namespaces/prod/services/web:https:metrics/proxy/healthz"
+        );
+        let pod = ProxyTarget::new("prod", "Pod", "web-1", "8080").unwrap();
+        assert_eq!(pod.path("status"), "/api/v1/namespaces/prod/pods/web-1:8080/proxy/status");
+        // no port means the default one
+        let pod = ProxyTarget::new("prod", "po", "web-1", "").unwrap();
+        assert_eq!(pod.port, None);
+        assert_eq!(pod.path("/"), "/api/v1/namespaces/prod/pods/web-1/proxy/");
+
+        assert!(ProxyTarget::new("prod", "deploy", "web", "80").is_err());
+        assert!(ProxyTarget::new("prod", "svc", "web", "80/../x").is_err());
+        assert!(ProxyTarget::new("prod", "svc", "web", "http:").is_err());
+
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/prod/pods/web-1:http/proxy/healthz",
+            MockResponse::raw(200, b"ok".to_vec()),
+        )]);
+        assert_eq!(kluster.proxy_get("prod", "pod", "web-1", "http", "healthz").unwrap(), "ok");
+    }
+
+    #[test]
+    fn fan_out_bounds_requests_in_flight() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        for &limit in [1, 3].iter() {
+            let (kluster, log) = mock_kluster(vec![]);
+            let kluster = kluster.with_max_concurrency(limit);
+            let in_flight = AtomicUsize::new(0);
+            let most = AtomicUsize::new(0);
+            let items: Vec<usize> = (0..12).collect();
+            let results = kluster.fan_out(items, |kluster, i| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                let found = kluster.get_value(format!("/api/v1/pods/p{}", i).as_str()).is_ok();
+                thread::sleep(Duration::from_millis(20));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                (i, found)
+            });
+            assert_eq!(results, (0..12).map(|i| (i, false)).collect::<Vec<(usize, bool)>>());
+            assert!(most.load(Ordering::SeqCst) <= limit);
+            assert!(most.load(Ordering::SeqCst) >= cmp::min(limit, 2));
+            assert_eq!(log.lock().unwrap().len(), 12);
+        }
+        // zero still makes progress
+        let (kluster, _) = mock_kluster(vec![]);
+        assert_eq!(kluster.with_max_concurrency(0).fan_out(vec![1, 2], |_, i| i), vec![1, 2]);
+    }
+
+    fn csr_json(name: &str, conditions: Value) -> Value {
+        json!({
+            "metadata": {"name": name},
+            "spec": {
+                "username": format!("system:node:{}", name),
+                "groups": ["system:nodes"],
+                "signerName": "kubernetes.io/kubelet-serving",
+                "usages": ["digital signature", "server auth"],
+            },
+            "status": {"conditions": conditions},
+        })
+    }
+
+    #[test]
+    fn csr_fixture_and_approval_patch() {
+        let csrs = "/apis/certificates.k8s.io/v1/certificatesigningrequests";
+        let denied = json!([{"type": "Denied", "status": "True", "reason": "NotANode"}]);
+        let failed = json!([{"type": "Failed", "status": "True", "message": "bad key"}]);
+        let mut approved = csr_json("node-1", json!([]));
+        approved["status"]["conditions"] = json!([{"type": "Approved", "status": "True"}]);
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                csrs,
+                MockResponse::json(200, json!({"items": [
+                    csr_json("node-1", json!([])),
+                    csr_json("node-2", denied.clone()),
+                    csr_json("node-3", failed.clone()),
+                ]})),
+            ),
+            route(
+                "GET",
+                &format!("{}/node-1", csrs),
+                MockResponse::json(200, csr_json("node-1", json!([]))),
+            ),
+            route(
+                "GET",
+                &format!("{}/node-2", csrs),
+                MockResponse::json(200, csr_json("node-2", denied)),
+            ),
+            route(
+                "GET",
+                &format!("{}/node-3", csrs),
+                MockResponse::json(200, csr_json("node-3", failed.clone())),
+            ),
+            route("PATCH", &format!("{}/node-1/approval", csrs), MockResponse::json(200, approved)),
+            route(
+                "PATCH",
+                &format!("{}/node-3/approval", csrs),
+                MockResponse::json(200, csr_json("node-3", json!([]))),
+            ),
+        ]);
+        let pending: Vec<String> = kluster
+            .csrs()
+            .unwrap()
+            .into_iter()
+            .filter(|csr| csr.is_pending())
+            .map(|csr| csr.metadata.name)
+            .collect();
+        assert_eq!(pending, vec!["node-1", "node-3"]);
+
+        let csr = kluster.approve_csr("node-1").unwrap();
+        assert!(csr.is_approved() && !csr.is_pending());
+        assert!(kluster.approve_csr("node-2").is_err());
+        kluster.approve_csr("node-3").unwrap();
+
+        let requests = log.lock().unwrap();
+        let patches: Vec<&MockRequest> = requests.iter().filter(|r| r.method == "PATCH").collect();
+        assert_eq!(patches.len(), 2, "a denied request isn't patched");
+        let conditions = patches[0].body_json()["status"]["conditions"].clone();
+        assert_eq!(conditions.as_array().unwrap().len(), 1);
+        assert_eq!(conditions[0]["type"], "Approved");
+        assert_eq!(conditions[0]["status"], "True");
+        assert_eq!(conditions[0]["reason"], "ClickApprove");
+        // a merge patch replaces the list, so the existing conditions are kept in it
+        let conditions = patches[1].body_json()["status"]["conditions"].clone();
+        assert_eq!(conditions[0], failed[0]);
+        assert_eq!(conditions[1]["type"], "Approved");
+
+        let now: DateTime<Utc> = "2018-01-02T03:04:05Z".parse().unwrap();
+        assert_eq!(
+            csr_approval_patch(&[], "ok", now),
+            json!({"status": {"conditions": [{
+                "type": "Approved",
+                "status": "True",
+                "reason": "ClickApprove",
+                "message": "ok",
+                "lastUpdateTime": "2018-01-02T03:04:05Z",
+            }]}})
+        );
+    }
+
+    #[test]
+    fn image_matching_exact_and_prefix() {
+        assert_eq!(split_image("registry:5000/app:1.0"), ("registry:5000/app", Some("1.0"), None));
+        assert_eq!(split_image("registry:5000/app"), ("registry:5000/app", None, None));
+        assert_eq!(
+            split_image("app:1.0@sha256:abc"),
+            ("app", Some("1.0"), Some("sha256:abc"))
+        );
+
+        // no tag in the search matches any tag
+        assert!(image_matches("nginx:1.25", "nginx", false));
+        assert!(image_matches("nginx", "nginx:latest", false));
+        assert!(image_matches("nginx:1.25", "nginx:1.25", false));
+        assert!(!image_matches("nginx:1.25", "nginx:1.2", false));
+        assert!(!image_matches("nginx-exporter:1", "nginx", false));
+        assert!(!image_matches("registry:5000/nginx:1", "nginx", false));
+        assert!(image_matches("app@sha256:abc", "app@sha256:abc", false));
+        assert!(!image_matches("app@sha256:abc", "app@sha256:def", false));
+        // pinned by digest, so it's not known to be :latest
+        assert!(!image_matches("app@sha256:abc", "app:latest", false));
+
+        assert!(image_matches("gcr.io/proj/app:1", "gcr.io/proj/", true));
+        assert!(image_matches("nginx-exporter:1", "nginx", true));
+        assert!(!image_matches("docker.io/nginx", "nginx", true));
+
+        let mut web = pod_json("web");
+        web["spec"]["containers"][0]["image"] = json!("nginx:1.25");
+        let mut job = pod_json("job");
+        job["spec"]["initContainers"] = json!([{"name": "setup", "image": "gcr.io/proj/tool:2"}]);
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({"items": [web.clone(), job]})),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods",
+                MockResponse::json(200, json!({"items": [web]})),
+            ),
+        ]);
+        let names = |pods: Vec<Pod>| -> Vec<String> {
+            pods.into_iter().map(|p| p.metadata.name).collect()
+        };
+        assert_eq!(names(kluster.pods_using_image(None, "nginx:1.25", false).unwrap()), ["web"]);
+        assert_eq!(names(kluster.pods_using_image(None, "gcr.io/proj/", true).unwrap()), ["job"]);
+        assert_eq!(names(kluster.pods_using_image(None, "app", false).unwrap()), ["job"]);
+        assert!(kluster.pods_using_image(Some("ns"), "nginx:1.24", false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decode_service_account_jwt() {
+        let header = "eyJhbGciOiJSUzI1NiIsImtpZCI6ImFiYyJ9";
+        let payload = "eyJhdWQiOlsiaHR0cHM6Ly9rdWJlcm5ldGVzLmRlZmF1bHQuc3ZjIl0sImV4cCI6MTg5Mz\
+                       Q1NjAwMCwiaWF0IjoxNTE0NzY0ODAwLCJpc3MiOiJodHRwczovL2t1YmVybmV0ZXMuZGVm\
+                       YXVsdC5zdmMiLCJzdWIiOiJzeXN0ZW06c2VydmljZWFjY291bnQ6bnM6ZGVwbG95ZXIifQ";
+        let token = format!("{}.{}.c2lnbmF0dXJl", header, payload);
+        let info = KlusterAuth::with_token(token.as_str()).token_info().unwrap();
+        let time = |s: &str| s.parse::<DateTime<Utc>>().ok();
+        assert_eq!(
+            info,
+            TokenInfo {
+                issuer: Some("https://kubernetes.default.svc".to_owned()),
+                subject: Some("system:serviceaccount:ns:deployer".to_owned()),
+                audiences: vec!["https://kubernetes.default.svc".to_owned()],
+                issued_at: time("2018-01-01T00:00:00Z"),
+                expires: time("2030-01-01T00:00:00Z"),
+            }
+        );
+        assert!(!info.is_expired());
+
+        // a single audience, and already expired
+        let payload = "eyJpc3MiOiJvbGQiLCJhdWQiOiJhcGkiLCJleHAiOjE1MTQ3NjQ4MDB9";
+        let info = TokenInfo::from_jwt(format!("{}.{}.sig", header, payload).as_str()).unwrap();
+        assert_eq!(info.audiences, vec!["api"]);
+        assert_eq!(info.subject, None);
+        assert!(info.is_expired());
+
+        // times too far off to represent are left out, rather than failing the whole token
+        let payload = ::base64::encode_config(
+            br#"{"iss":"far","exp":9223372036854775807,"iat":-9223372036854775808}"#,
+            ::base64::URL_SAFE_NO_PAD,
+        );
+        let info = TokenInfo::from_jwt(format!("{}.{}.sig", header, payload).as_str()).unwrap();
+        assert_eq!(info.issuer, Some("far".to_owned()));
+        assert_eq!(info.expires, None);
+        assert_eq!(info.issued_at, None);
+
+        // opaque tokens and other auth aren't JWTs
+        assert_eq!(KlusterAuth::with_token("abcdef0123456789").token_info(), None);
+        assert_eq!(TokenInfo::from_jwt(format!("{}.{}", header, payload).as_str()), None);
+        assert_eq!(TokenInfo::from_jwt("a.!!!.c"), None);
+        assert_eq!(TokenInfo::from_jwt(format!("{}.{}.c", header, "WzFd").as_str()), None);
+    }
+
+    fn job_json(active: u32, succeeded: u32, failed: u32, finished: Option<(&str, &str)>) -> Value {
+        let conditions: Vec<Value> = finished
+            .iter()
+            .map(|&(typ, reason)| {
+                json!({"type": typ, "status": "True", "reason": reason, "message": "done"})
+            })
+            .collect();
+        json!({
+            "metadata": {"name": "migrate", "namespace": "ns"},
+            "status": {
+                "active": active,
+                "succeeded": succeeded,
+                "failed": failed,
+                "conditions": conditions,
+            },
+        })
+    }
+
+    #[test]
+    fn wait_for_job_success_and_failure() {
+        let jobs = "/apis/batch/v1/namespaces/ns/jobs";
+        let (kluster, log) = mock_kluster(vec![route(
+            "GET",
+            jobs,
+            watch_stream(vec![
+                ("ADDED", job_json(1, 0, 0, None)),
+                ("MODIFIED", job_json(1, 2, 0, None)),
+                ("MODIFIED", job_json(0, 3, 0, Some(("Complete", "Completed")))),
+            ]),
+        )]);
+        let outcome = kluster.wait_for_job("ns", "migrate", Duration::from_secs(60)).unwrap();
+        assert_eq!(
+            outcome,
+            JobOutcome {
+                complete: true,
+                succeeded: 3,
+                failed: 0,
+                reason: Some("Completed".to_owned()),
+                message: Some("done".to_owned()),
+            }
+        );
+        let path = paths(&log).remove(0);
+        assert!(path.starts_with(
+            "/apis/batch/v1/namespaces/ns/jobs?fieldSelector=metadata.name=migrate&timeoutSeconds="
+        ));
+        assert!(path.ends_with("&watch=true"));
+
+        // the first watch ends while the job is still running, so it watches again
+        let (kluster, log) = mock_kluster(vec![(
+            "GET",
+            jobs.to_owned(),
+            vec![
+                watch_stream(vec![("ADDED", job_json(1, 0, 1, None))]),
+                watch_stream(vec![
+                    ("ADDED", job_json(1, 0, 1, None)),
+                    ("MODIFIED", job_json(0, 0, 2, Some(("Failed", "BackoffLimitExceeded")))),
+                ]),
+            ],
+        )]);
+        let outcome = kluster.wait_for_job("ns", "migrate", Duration::from_secs(60)).unwrap();
+        assert!(!outcome.complete);
+        assert_eq!((outcome.succeeded, outcome.failed), (0, 2));
+        assert_eq!(outcome.reason, Some("BackoffLimitExceeded".to_owned()));
+        assert_eq!(log.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn wait_for_job_timeout_and_delete() {
+        let jobs = "/apis/batch/v1/namespaces/ns/jobs";
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            jobs,
+            watch_stream(vec![("ADDED", job_json(1, 0, 0, None))]),
+        )]);
+        match kluster.wait_for_job("ns", "migrate", Duration::from_millis(50)) {
+            Err(KubeError::Kube(KubeErrNo::Timeout)) => {}
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            jobs,
+            watch_stream(vec![
+                ("ADDED", job_json(1, 0, 0, None)),
+                ("DELETED", job_json(1, 0, 0, None)),
+            ]),
+        )]);
+        match kluster.wait_for_job("ns", "migrate", Duration::from_secs(60)) {
+            Err(KubeError::KubeServerError(ref msg)) => assert_eq!(msg, "Job migrate was deleted"),
+            other => panic!("expected an error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn volumes_for_pvc_and_config_map_mounts() {
+        let mut pod = pod_json("db-0");
+        pod["spec"]["volumes"] = json!([
+            {"name": "data", "persistentVolumeClaim": {"claimName": "data-db-0"}},
+            {"name": "config", "configMap": {"name": "db-config"}},
+            {"name": "scratch", "emptyDir": {"medium": "Memory", "sizeLimit": "64Mi"}},
+            {"name": "logs", "hostPath": {"path": "/var/log"}},
+        ]);
+        pod["spec"]["containers"][0]["volumeMounts"] = json!([
+            {"name": "data", "mountPath": "/var/lib/db"},
+            {"name": "config", "mountPath": "/etc/db/db.conf", "subPath": "db.conf",
+             "readOnly": true},
+            {"name": "missing", "mountPath": "/missing"},
+        ]);
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let mounts = pod.spec.containers[0].volume_mounts.as_ref().unwrap();
+        let sources: Vec<Option<String>> = mounts
+            .iter()
+            .map(|m| pod.spec.volume_for_mount(m).map(|v| v.source_summary()))
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                Some("PersistentVolumeClaim data-db-0".to_owned()),
+                Some("ConfigMap db-config".to_owned()),
+                None,
+            ]
+        );
+        assert_eq!(mounts[1].sub_path, Some("db.conf".to_owned()));
+        assert_eq!(mounts[1].read_only, Some(true));
+
+        let volumes = pod.spec.volumes.as_ref().unwrap();
+        assert_eq!(volumes[2].source(), ("EmptyDir (memory)", None));
+        let limit = volumes[2].empty_dir.as_ref().unwrap().size_limit;
+        assert_eq!(limit, Some("64Mi".parse().unwrap()));
+        assert_eq!(volumes[3].source(), ("HostPath", Some("/var/log")));
+    }
+
+    fn with_requests(mut pod: Value, cpu: &str, memory: &str) -> Value {
+        pod["spec"]["containers"][0]["resources"] =
+            json!({"requests": {"cpu": cpu, "memory": memory}});
+        pod
+    }
+
+    #[test]
+    fn namespace_summary_from_several_lists() {
+        let pods = json!({"items": [
+            with_requests(pod_json("web-1"), "250m", "128Mi"),
+            with_requests(pod_json("web-2"), "1", "1Gi"),
+            // finished pods don't hold on to their requests
+            with_requests(with_phase(pod_json("job-1"), "Succeeded"), "4", "8Gi"),
+            with_phase(pod_json("new"), "Pending"),
+        ]});
+        let two = json!({"items": [{"metadata": {"name": "a"}}, {"metadata": {"name": "b"}}]});
+        let (kluster, _) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/ns/pods", MockResponse::json(200, pods)),
+            route("GET", "/api/v1/namespaces/ns/services", MockResponse::json(200, two)),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/persistentvolumeclaims",
+                MockResponse::json(200, json!({"items": [{"metadata": {"name": "data"}}]})),
+            ),
+            // the apps group isn't served, so deployments gets the mock's 404
+        ]);
+        let summary = kluster.with_max_concurrency(2).namespace_summary("ns").unwrap();
+        assert_eq!(summary.namespace, "ns");
+        assert_eq!(summary.pod_count(), 4);
+        assert_eq!(summary.pods_by_phase["Running"], 2);
+        assert_eq!(summary.pods_by_phase["Succeeded"], 1);
+        assert_eq!(summary.pods_by_phase["Pending"], 1);
+        assert_eq!(summary.deployments, None);
+        assert_eq!(summary.services, Some(2));
+        assert_eq!(summary.persistent_volume_claims, Some(1));
+        assert_eq!(summary.cpu_requests, "1250m".parse().unwrap());
+        assert_eq!(summary.memory_requests, "1152Mi".parse().unwrap());
+
+        // without pods there's no summary
+        let (kluster, _) = mock_kluster(vec![]);
+        assert!(kluster.namespace_summary("ns").is_err());
+    }
+
+    #[test]
+    fn upsert_updates_or_creates() {
+        let cm = json!({
+            "apiVersion": "v1",
+            "kind": "ConfigMap",
+            "metadata": {"name": "settings", "namespace": "ns"},
+            "data": {"color": "blue"},
+        });
+        let body = cm.to_string();
+        let path = "/api/v1/namespaces/ns/configmaps/settings";
+        let mut stored = cm.clone();
+        stored["metadata"]["resourceVersion"] = json!("7");
+
+        // update: the put succeeds
+        let (kluster, log) = mock_kluster(vec![
+            route("PUT", path, MockResponse::json(200, stored.clone())),
+        ]);
+        let result: ConfigMap = kluster.upsert(path, body.as_str()).unwrap();
+        assert_eq!(result.metadata.name, "settings");
+        assert_eq!(
+            log.lock().unwrap().iter().map(|r| r.method.clone()).collect::<Vec<String>>(),
+            vec!["PUT"]
+        );
+        assert_eq!(log.lock().unwrap()[0].body_json(), cm);
+
+        // create: the put finds nothing, so post to the collection
+        let (kluster, log) = mock_kluster(vec![
+            route("PUT", path, MockResponse::json(404, status_body(404, "not found"))),
+            route(
+                "POST",
+                "/api/v1/namespaces/ns/configmaps",
+                MockResponse::json(201, stored),
+            ),
+        ]);
+        let result: Value = kluster.upsert(path, body.as_str()).unwrap();
+        assert_eq!(result["metadata"]["resourceVersion"], "7");
+        let requests = log.lock().unwrap();
+        let sent: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|r| (r.method.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(sent, vec![("PUT", path), ("POST", "/api/v1/namespaces/ns/configmaps")]);
+        assert_eq!(requests[1].body_json(), cm);
+
+        // other failures aren't retried as a create
+        let (kluster, log) = mock_kluster(vec![
+            route("PUT", path, MockResponse::json(409, status_body(409, "conflict"))),
+        ]);
+        assert!(kluster.upsert::<Value>(path, body.as_str()).is_err());
+        assert_eq!(log.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn evicted_pod_status() {
+        let pod: Pod = serde_json::from_value(json!({
+            "metadata": {"name": "web-1", "namespace": "ns"},
+            "spec": {"containers": [{"name": "app", "image": "app:1"}]},
+            "status": {
+                "phase": "Failed",
+                "reason": "Evicted",
+                "message": "The node was low on resource: memory. Container app was using \
+                            1Gi, which exceeds its request of 256Mi.",
+                "startTime": "2018-01-01T00:00:00Z",
+            },
+        })).unwrap();
+        assert_eq!(pod.status.phase, "Failed");
+        assert_eq!(pod.status.reason, Some("Evicted".to_owned()));
+        assert!(pod.status.message.as_ref().unwrap().starts_with("The node was low on resource"));
+        // evicted pods have had their containers' statuses cleared
+        assert!(pod.status.container_statuses.is_none());
+
+        let pod: Pod = serde_json::from_value(pod_json("web")).unwrap();
+        assert_eq!((pod.status.reason, pod.status.message), (None, None));
+    }
+
+    #[test]
+    fn get_many_found_and_missing() {
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/a/pods/web", MockResponse::json(200, pod_json("web"))),
+            route("GET", "/api/v1/namespaces/c/pods/db", MockResponse::json(200, pod_json("db"))),
+        ]);
+        let paths: Vec<String> = vec![
+            "/api/v1/namespaces/a/pods/web".to_owned(),
+            "/api/v1/namespaces/b/pods/web".to_owned(),
+            "/api/v1/namespaces/c/pods/db".to_owned(),
+        ];
+        let results = kluster.with_max_concurrency(2).get_many::<Pod>(&paths);
+        assert_eq!(
+            results.iter().map(|&(ref p, _)| p.clone()).collect::<Vec<String>>(),
+            paths
+        );
+        assert_eq!(results[0].1.as_ref().unwrap().metadata.name, "web");
+        match results[1].1 {
+            Err(KubeError::KubeServerError(_)) => {}
+            ref other => panic!("expected a not found error, got {:?}", other),
+        }
+        assert_eq!(results[2].1.as_ref().unwrap().metadata.name, "db");
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn over_limit_bodies_are_too_large() {
+        let big = json!({"metadata": {"name": "big"}, "data": {"a": "x".repeat(2000)}});
+        // {"a":"xxx...x"} with 2000 x's, which gzips to 42 bytes
+        let bomb = unhex(
+            "1f8b0800000000000203ab564a54b252aa1805a360148c8251300a46c190074ab5005d7225c1d8\
+             070000",
+        );
+        let path = "/api/v1/namespaces/ns/configmaps/big";
+        let (kluster, _) = mock_kluster(vec![
+            route("GET", path, MockResponse::json(200, big.clone())),
+            route("PUT", path, MockResponse::json(200, big.clone())),
+            (
+                "GET",
+                "/api/v1/namespaces/ns/configmaps".to_owned(),
+                vec![MockResponse::raw(200, bomb).header("Content-Encoding", "gzip")],
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods/web-1",
+                MockResponse::json(500, status_body(500, &"e".repeat(2000))),
+            ),
+        ]);
+        let kluster = kluster.with_max_response_bytes(1000);
+        let too_large = |result: Result<(), KubeError>| match result {
+            Err(KubeError::Kube(KubeErrNo::ResponseTooLarge)) => {}
+            other => panic!("expected ResponseTooLarge, got {:?}", other),
+        };
+        too_large(kluster.get::<ConfigMap>(path).map(|_| ()));
+        too_large(kluster.get_value(path).map(|_| ()));
+        too_large(kluster.get_bytes(path).map(|_| ()));
+        too_large(kluster.get_text(path).map(|_| ()));
+        // bodies read after a write are limited too
+        too_large(kluster.upsert::<Value>(path, "{}").map(|_| ()));
+        // as is the decompressed size of a small gzipped body, and error bodies
+        too_large(kluster.get_value("/api/v1/namespaces/ns/configmaps").map(|_| ()));
+        too_large(kluster.get_value("/api/v1/namespaces/ns/pods/web-1").map(|_| ()));
+
+        // within the limit is fine
+        let kluster = kluster.with_max_response_bytes(3000);
+        assert_eq!(kluster.get_value(path).unwrap(), big);
+        let list = kluster.get_value("/api/v1/namespaces/ns/configmaps").unwrap();
+        assert_eq!(list["a"].as_str().map(|a| a.len()), Some(2000));
+    }
+
+    #[test]
+    fn pod_health_variants() {
+        let health = |pod: Value| serde_json::from_value::<Pod>(pod).unwrap().health();
+        assert_eq!(health(pod_json("web")), PodHealth::Healthy);
+        assert_eq!(health(with_phase(pod_json("job"), "Succeeded")), PodHealth::Healthy);
+
+        let mut pending = with_phase(pod_json("new"), "Pending");
+        pending["status"]["containerStatuses"][0]["ready"] = json!(false);
+        pending["status"]["containerStatuses"][0]["state"] =
+            json!({"waiting": {"reason": "ContainerCreating"}});
+        assert_eq!(health(pending), PodHealth::Pending);
+
+        let mut crashing = pod_json("crashing");
+        crashing["status"]["containerStatuses"][0]["state"] =
+            json!({"waiting": {"reason": "CrashLoopBackOff"}});
+        assert_eq!(health(crashing), PodHealth::Degraded("app: CrashLoopBackOff".to_owned()));
+        let mut unready = pod_json("unready");
+        unready["status"]["containerStatuses"][0]["ready"] = json!(false);
+        assert_eq!(health(unready), PodHealth::Degraded("0/1 containers ready".to_owned()));
+        let mut restarting = pod_json("restarting");
+        restarting["status"]["containerStatuses"][0]["restartCount"] = json!(9);
+        assert_eq!(health(restarting), PodHealth::Degraded("9 restarts".to_owned()));
+        assert_eq!(
+            health(with_phase(pod_json("odd"), "Unknown")),
+            PodHealth::Degraded("phase is Unknown".to_owned())
+        );
+        // a reason doesn't mean the pod failed unless its phase says so
+        let mut lost = pod_json("lost");
+        lost["status"]["reason"] = json!("NodeLost");
+        assert_eq!(health(lost), PodHealth::Degraded("NodeLost".to_owned()));
+
+        let mut evicted = with_phase(pod_json("evicted"), "Failed");
+        evicted["status"]["reason"] = json!("Evicted");
+        evicted["status"]["message"] = json!("The node was low on resource: memory.");
+        assert_eq!(health(evicted), PodHealth::Failed("Evicted".to_owned()));
+        let mut failed = with_phase(pod_json("failed"), "Failed");
+        failed["status"]["message"] = json!("Pod was active too long");
+        assert_eq!(health(failed), PodHealth::Failed("Pod was active too long".to_owned()));
+        assert_eq!(
+            health(with_phase(pod_json("failed"), "Failed")),
+            PodHealth::Failed("Failed".to_owned())
+        );
+    }
+
+    #[test]
+    fn trace_ingress_without_ready_endpoints() {
+        let ingress = json!({
+            "metadata": {"name": "web", "namespace": "ns"},
+            "spec": {"rules": [{
+                "host": "example.com",
+                "http": {"paths": [{
+                    "path": "/",
+                    "backend": {"service": {"name": "web", "port": {"number": 80}}}
+                }]}
+            }]}
+        });
+        let slices = json!({"items": [{
+            "metadata": {"name": "web-abcde"},
+            "endpoints": [{
+                "addresses": ["10.0.0.7"],
+                "conditions": {"ready": false},
+                "targetRef": {"kind": "Pod", "name": "web-1"}
+            }]
+        }]});
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis/networking.k8s.io/v1/namespaces/ns/ingresses/web",
+                MockResponse::json(200, ingress),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/services/web",
+                MockResponse::json(
+                    200,
+                    json!({"metadata": {"name": "web"}, "spec": {}, "status": {}}),
+                ),
+            ),
+            route(
+                "GET",
+                "/apis/discovery.k8s.io/v1/namespaces/ns/endpointslices",
+                MockResponse::json(200, slices),
+            ),
+        ]);
+        let routes = kluster.trace_ingress("ns", "web").unwrap();
+        assert_eq!(routes.len(), 1);
+        let route = &routes[0];
+        assert_eq!(route.host, Some("example.com".to_owned()));
+        assert_eq!(route.path, Some("/".to_owned()));
+        assert_eq!(route.service, Some("web".to_owned()));
+        assert_eq!(route.port, Some("80".to_owned()));
+        assert!(route.service_found);
+        assert_eq!(route.endpoints.len(), 1);
+        assert_eq!(route.endpoints[0].address, "10.0.0.7");
+        assert_eq!(route.endpoints[0].pod, Some("web-1".to_owned()));
+        assert!(!route.endpoints[0].ready);
+        assert!(!route.is_routable());
+        assert_eq!(
+            paths(&log)[2],
+            "/apis/discovery.k8s.io/v1/namespaces/ns/endpointslices\
+             ?labelSelector=kubernetes.io%2Fservice-name%3Dweb"
+        );
+    }
+
+    #[test]
+    fn combined_logs_are_time_ordered() {
+        let web = "2024-01-01T00:00:01Z web starting\n\
+                   2024-01-01T00:00:03Z web ready\n";
+        let db = "2024-01-01T00:00:02Z db starting\n\
+                  2024-01-01T00:00:04Z db ready\n";
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods/web/log",
+                MockResponse::raw(200, web.as_bytes().to_vec()),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods/db/log",
+                MockResponse::raw(200, db.as_bytes().to_vec()),
+            ),
+        ]);
+        let sources = vec![
+            ("ns".to_owned(), "web".to_owned()),
+            ("ns".to_owned(), "db".to_owned()),
+        ];
+        let mut out = Vec::new();
+        kluster
+            .stream_combined_logs(sources, &LogParams::default(), &mut out)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[web] web starting\n[db] db starting\n[web] web ready\n[db] db ready\n"
+        );
+        // timestamps are always fetched, so the lines can be ordered
+        for path in paths(&log) {
+            assert!(path.ends_with("/log?timestamps=true"), "{}", path);
+        }
+    }
+
+    #[test]
+    fn event_ring_drops_oldest_past_capacity() {
+        let mut unparseable = event_json("Normal", "Pulled", "no time", "web", None);
+        unparseable.as_object_mut().unwrap().remove("lastTimestamp");
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/events?resourceVersion=5&watch=true",
+                watch_stream(vec![
+                    ("MODIFIED", event_json("Normal", "Pulled", "second", "web", None)),
+                    ("ADDED", event_json("Warning", "BackOff", "third", "web", None)),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/events",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "5"},
+                    "items": [event_json("Normal", "Scheduled", "first", "web", None), unparseable],
+                })),
+            ),
+        ]);
+        let ring = EventRing::new(2);
+        let mut seen = 0;
+        kluster
+            .watch_events(Some("ns"), |event| {
+                ring.push(event.clone());
+                seen += 1;
+                seen < 3
+            })
+            .unwrap();
+        assert_eq!(seen, 3);
+        assert_eq!(ring.len(), 2);
+        let messages: Vec<String> = ring.snapshot().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["third", "second"]);
+
+        let empty = EventRing::new(0);
+        empty.push(ring.snapshot().remove(0));
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn warning_header_with_agent() {
+        let header = "299 kube-apiserver:6443 \"extensions/v1beta1 Ingress is \\\"deprecated\\\"\" \
+                      \"Sat, 01 Jan 2022 00:00:00 GMT\", 110 - \"Response is stale\", \
+                      299 - \"use networking.k8s.io/v1\"";
+        assert_eq!(
+            parse_warning_header(header),
+            vec![
+                DeprecationWarning {
+                    text: "extensions/v1beta1 Ingress is \"deprecated\"".to_owned(),
+                    agent: Some("kube-apiserver:6443".to_owned()),
+                },
+                DeprecationWarning {
+                    text: "use networking.k8s.io/v1".to_owned(),
+                    agent: None,
+                },
+            ]
+        );
+        assert!(parse_warning_header("299 - unquoted").is_empty());
+
+        let warning = "299 - \"v1beta1 is deprecated\"";
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/pods",
+            MockResponse::json(200, json!({"items": []})).header("Warning", warning),
+        )]);
+        kluster.get_value("/api/v1/pods").unwrap();
+        kluster.get_value("/api/v1/pods").unwrap();
+        let warnings = kluster.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].text, "v1beta1 is deprecated");
+        kluster.get_value("/api/v1/pods").unwrap();
+        assert!(kluster.take_warnings().is_empty());
+    }
+
+    #[test]
+    fn reflector_rejects_regressing_resource_version() {
+        let mut reflector = Reflector::new();
+        let pod = |rv: &str| with_resource_version(pod_json("web"), rv);
+        assert!(reflector.apply(WatchEvent::Added(pod("5"))).unwrap().is_some());
+        reflector.apply(WatchEvent::Modified(pod("7"))).unwrap();
+        for rv in ["6", "7"].iter() {
+            match reflector.apply(WatchEvent::Modified(pod(rv))) {
+                Err(KubeError::Kube(KubeErrNo::WatchInconsistency(msg))) => {
+                    assert_eq!(msg, format!("ns/web modified at resourceVersion {} after 7", rv))
+                }
+                other => panic!("expected an inconsistency, got {:?}", other),
+            }
+        }
+        // the stale events weren't applied
+        assert_eq!(
+            ::values::val_str_opt("/metadata/resourceVersion", reflector.get("ns/web").unwrap()),
+            Some("7".to_owned())
+        );
+        // a relist re-adds objects, which isn't out of order
+        reflector.apply(WatchEvent::Added(pod("7"))).unwrap();
+        assert_eq!(reflector.len(), 1);
+    }
+
+    #[test]
+    fn pull_secrets_from_pod_and_service_account() {
+        let mut pod = pod_json("web");
+        pod["spec"]["serviceAccountName"] = json!("builder");
+        pod["spec"]["imagePullSecrets"] = json!([{"name": "regcred"}]);
+        let sa = json!({
+            "metadata": {"name": "builder", "namespace": "ns"},
+            "imagePullSecrets": [{"name": "regcred"}, {"name": "mirror"}, {"name": "gone"}],
+        });
+        let (kluster, _) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/ns/pods/web", MockResponse::json(200, pod)),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/serviceaccounts/builder",
+                MockResponse::json(200, sa),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/secrets/regcred",
+                MockResponse::json(200, json!({"metadata": {"name": "regcred"}})),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/secrets/mirror",
+                MockResponse::json(403, status_body(403, "secrets \"mirror\" is forbidden")),
+            ),
+        ]);
+        let secret = |name: &str, service_account: Option<&str>, exists: Option<bool>| PullSecret {
+            name: name.to_owned(),
+            service_account: service_account.map(|s| s.to_owned()),
+            exists: exists,
+        };
+        assert_eq!(
+            kluster.effective_pull_secrets("ns", "web").unwrap(),
+            vec![
+                secret("regcred", None, Some(true)),
+                secret("mirror", Some("builder"), None),
+                secret("gone", Some("builder"), Some(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn deletion_blocked_by_two_finalizers() {
+        let path = "/api/v1/namespaces/ns/persistentvolumeclaims/data";
+        let mut pvc = json!({"metadata": {
+            "name": "data",
+            "namespace": "ns",
+            "deletionTimestamp": "2018-01-01T00:05:00Z",
+            "finalizers": ["kubernetes.io/pvc-protection", "example.com/backup"],
+            "ownerReferences": [{
+                "kind": "StatefulSet",
+                "name": "db",
+                "uid": "1234",
+                "blockOwnerDeletion": true,
+            }],
+        }});
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", path, MockResponse::json(200, pvc.clone())),
+            route("PATCH", path, MockResponse::json(200, json!({"metadata": {"name": "data"}}))),
+        ]);
+        let blockers = kluster.deletion_blockers(path).unwrap().unwrap();
+        assert_eq!(blockers.deletion_timestamp.to_rfc3339(), "2018-01-01T00:05:00+00:00");
+        assert_eq!(
+            blockers.finalizers,
+            vec!["kubernetes.io/pvc-protection", "example.com/backup"]
+        );
+        assert_eq!(blockers.owners.len(), 1);
+        assert_eq!(blockers.owners[0].name, "db");
+        assert!(blockers.owners[0].block_owner_deletion);
+
+        kluster.remove_finalizers(path).unwrap();
+        let patch = log.lock().unwrap()[1].body_json();
+        assert_eq!(patch, json!({"metadata": {"finalizers": null}}));
+
+        // an object that isn't being deleted isn't blocked
+        pvc["metadata"].as_object_mut().unwrap().remove("deletionTimestamp");
+        let (kluster, _) = mock_kluster(vec![route("GET", path, MockResponse::json(200, pvc))]);
+        assert!(kluster.deletion_blockers(path).unwrap().is_none());
+    }
+
+    #[test]
+    fn priority_class_and_qos() {
+        let pod = |v: Value| serde_json::from_value::<Pod>(v).unwrap();
+        let mut critical = pod_json("critical");
+        critical["spec"]["priorityClassName"] = json!("system-node-critical");
+        critical["spec"]["priority"] = json!(2000001000);
+        critical["status"]["qosClass"] = json!("Burstable");
+        let critical = pod(critical);
+        assert_eq!(critical.priority_summary(), "system-node-critical (2000001000)");
+        assert_eq!(critical.qos_class(), QosClass::Burstable);
+
+        let plain = pod(pod_json("plain"));
+        assert_eq!(plain.priority_summary(), "<default>");
+        assert_eq!(plain.qos_class(), QosClass::BestEffort);
+        assert_eq!(
+            pod(with_requests(pod_json("requests"), "250m", "128Mi")).qos_class(),
+            QosClass::Burstable
+        );
+        let mut limited = pod_json("limited");
+        limited["spec"]["containers"][0]["resources"] =
+            json!({"limits": {"cpu": "1", "memory": "1Gi"}});
+        assert_eq!(pod(limited).qos_class(), QosClass::Guaranteed);
+    }
+
+    #[test]
+    fn selector_operators() {
+        let labels: BTreeMap<String, String> = vec![("app", "web"), ("tier", "frontend")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        let matches = |selector: &str| selector_matches(selector, &labels);
+        assert!(matches(""));
+        assert!(matches("app=web"));
+        assert!(matches("app==web"));
+        assert!(!matches("app=db"));
+        assert!(matches("app!=db"));
+        assert!(!matches("app!=web"));
+        assert!(matches("track!=canary")); // missing labels aren't equal to anything
+        assert!(matches("tier in (backend, frontend)"));
+        assert!(!matches("tier in (backend)"));
+        assert!(!matches("track in (stable)"));
+        assert!(matches("tier notin (backend)"));
+        assert!(!matches("tier notin (backend,frontend)"));
+        assert!(matches("track notin (stable)"));
+        assert!(matches("app"));
+        assert!(!matches("track"));
+        assert!(matches("!track"));
+        assert!(!matches("!app"));
+        assert!(matches("app=web,tier in (frontend,backend),!canary"));
+        assert!(!matches("app=web,tier in (frontend,backend),canary"));
+        // unparseable selectors match nothing
+        assert!(!matches("tier within (frontend)"));
+        assert!(!matches("tier in (frontend"));
+        assert!(!matches("=web"));
+        assert!(!matches("!"));
+    }
+
+    #[test]
+    fn watch_table_debounces_a_burst_of_changes() {
+        let table = |rv: &str, names: Vec<&str>| {
+            let rows: Vec<Value> = names.iter().map(|n| json!({"cells": [n]})).collect();
+            MockResponse::json(200, json!({
+                "metadata": {"resourceVersion": rv},
+                "columnDefinitions": [{"name": "Name", "type": "string", "format": "name"}],
+                "rows": rows,
+            }))
+        };
+        let pod = |name: &str, rv: &str| with_resource_version(pod_json(name), rv);
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods?resourceVersion=10&timeoutSeconds=5&watch=true",
+                watch_stream(vec![
+                    ("ADDED", pod("c", "11")),
+                    // only the resourceVersion changed, so this isn't worth a refresh
+                    ("MODIFIED", pod("c", "12")),
+                    ("DELETED", pod("b", "13")),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods?resourceVersion=13&timeoutSeconds=5&watch=true",
+                MockResponse::json(500, status_body(500, "watch closed")),
+            ),
+            (
+                "GET",
+                "/api/v1/namespaces/ns/pods".to_owned(),
+                vec![table("10", vec!["a", "b"]), table("13", vec!["a", "c"])],
+            ),
+        ]);
+        let mut updates = Vec::new();
+        let start = Instant::now();
+        kluster
+            .watch_table("/api/v1/namespaces/ns/pods", |table| {
+                let names: Vec<String> = table
+                    .rows
+                    .iter()
+                    .map(|r| r.cells[0].as_str().unwrap().to_owned())
+                    .collect();
+                updates.push(names);
+                updates.len() < 2
+            })
+            .unwrap();
+        // the watcher is backing off after the 500 by then, but doesn't make us wait for it
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(updates, vec![vec!["a", "b"], vec!["a", "c"]]);
+        let log = log.lock().unwrap();
+        let table_fetches: Vec<&MockRequest> = log
+            .iter()
+            .filter(|r| r.path == "/api/v1/namespaces/ns/pods")
+            .collect();
+        assert_eq!(table_fetches.len(), 2);
+        assert_eq!(
+            table_fetches[0].header("Accept"),
+            Some("application/json; as=Table; v=v1; g=meta.k8s.io")
+        );
+    }
+
+    #[test]
+    fn container_env_masks_secrets() {
+        let mut pod = pod_json("web");
+        pod["spec"]["containers"][0]["env"] = json!([
+            {"name": "MODE", "value": "production"},
+            {"name": "PASSWORD", "valueFrom": {"secretKeyRef": {"name": "db", "key": "password"}}},
+            {"name": "LEVEL", "valueFrom": {"configMapKeyRef": {"name": "conf", "key": "level"}}},
+            {"name": "POD_IP", "valueFrom": {"fieldRef": {"fieldPath": "status.podIP"}}},
+        ]);
+        let pod: Pod = serde_json::from_value(pod).unwrap();
+        let container = &pod.spec.containers[0];
+        let env = container.env.as_ref().unwrap();
+        assert_eq!(env[0].source(), EnvValue::Literal("production"));
+        assert_eq!(env[1].source(), EnvValue::SecretKey("db", "password"));
+
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/secrets/db",
+                MockResponse::json(200, json!({
+                    "metadata": {"name": "db"},
+                    "data": {"password": "aHVudGVyMg=="},
+                })),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/configmaps/conf",
+                MockResponse::json(200, json!({
+                    "metadata": {"name": "conf"},
+                    "data": {"level": "debug"},
+                })),
+            ),
+        ]);
+        let pairs = |values: Vec<&str>| -> Vec<(String, String)> {
+            ["MODE", "PASSWORD", "LEVEL", "POD_IP"]
+                .iter()
+                .zip(values.into_iter())
+                .map(|(n, v)| (n.to_string(), v.to_owned()))
+                .collect()
+        };
+        assert_eq!(
+            kluster.container_env("ns", container, false).unwrap(),
+            pairs(vec![
+                "production",
+                "<set to the key 'password' in secret 'db'>",
+                "debug",
+                "(status.podIP)",
+            ])
+        );
+        // the secret wasn't even fetched
+        assert_eq!(paths(&log), vec!["/api/v1/namespaces/ns/configmaps/conf"]);
+        assert_eq!(
+            kluster.container_env("ns", container, true).unwrap(),
+            pairs(vec!["production", "hunter2", "debug", "(status.podIP)"])
+        );
+    }
+
+    #[test]
+    fn compare_inventory_of_two_clusters() {
+        let deploy = |name: &str, replicas: u32, uid: &str| {
+            json!({
+                "metadata": {"name": name, "namespace": "ns", "uid": uid},
+                "spec": {"replicas": replicas},
+                "status": {"readyReplicas": replicas},
+            })
+        };
+        let list = |items: Vec<Value>| {
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/deployments",
+                MockResponse::json(200, json!({ "items": items })),
+            )
+        };
+        let (mut east, _) = mock_kluster(vec![list(vec![
+            deploy("web", 3, "1"),
+            deploy("api", 2, "2"),
+            deploy("batch", 1, "3"),
+        ])]);
+        east.name = "east".to_owned();
+        let (mut west, _) = mock_kluster(vec![list(vec![
+            deploy("web", 3, "4"),
+            deploy("api", 4, "5"),
+            deploy("cache", 1, "6"),
+        ])]);
+        west.name = "west".to_owned();
+        let multi = MultiKluster::new(vec![east, west]);
+        let diff = multi.compare_inventory("deploy", Some("ns")).unwrap();
+        assert!(!diff.is_empty());
+        assert_eq!((diff.left.as_str(), diff.right.as_str()), ("east", "west"));
+        assert_eq!(diff.only_left, vec!["ns/batch"]);
+        assert_eq!(diff.only_right, vec!["ns/cache"]);
+        // only the spec counts, the uids and statuses always differ
+        assert_eq!(diff.changed.len(), 1);
+        let (ref key, ref fields) = diff.changed[0];
+        assert_eq!(key, "ns/api");
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].path, "/spec/replicas");
+        assert_eq!(fields[0].old, Some(json!(2)));
+        assert_eq!(fields[0].new, Some(json!(4)));
+
+        let single = MultiKluster::new(vec![mock_kluster(Vec::new()).0]);
+        assert!(single.compare_inventory("deploy", Some("ns")).is_err());
+        let (a, _) = mock_kluster(Vec::new());
+        let (b, _) = mock_kluster(Vec::new());
+        assert!(MultiKluster::new(vec![a, b]).compare_inventory("widget", None).is_err());
+    }
+
+    #[test]
+    fn list_kind_and_api_version() {
+        let list: PodList = serde_json::from_value(json!({
+            "kind": "PodList",
+            "apiVersion": "v1",
+            "metadata": {"resourceVersion": "10"},
+            "items": [pod_json("web")],
+        })).unwrap();
+        assert_eq!(list.type_meta.kind, Some("PodList".to_owned()));
+        assert_eq!(list.type_meta.api_version, Some("v1".to_owned()));
+        assert_eq!(list.items.len(), 1);
+
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/nodes",
+                MockResponse::json(200, json!({"kind": "PodList", "items": []})),
+            ),
+            route("GET", "/api/v1/pods", MockResponse::json(200, json!({"items": []}))),
+        ]);
+        match kluster.get_list::<NodeList>("/api/v1/nodes") {
+            Err(KubeError::ParseErr(msg)) => {
+                assert_eq!(msg, "Expected a NodeList but got a PodList")
+            }
+            other => panic!("expected a kind mismatch, got {:?}", other),
+        }
+        // lists without a kind are taken as what was asked for
+        assert!(kluster.get_list::<PodList>("/api/v1/pods").unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn pause_and_resume_patches() {
+        assert_eq!(paused_patch(true), json!({"spec": {"paused": true}}));
+        assert_eq!(paused_patch(false), json!({"spec": {"paused": false}}));
+
+        let path = "/apis/apps/v1/namespaces/ns/deployments/web";
+        let mut paused = deployment_json(2, 2, 3, 3, 3);
+        paused["spec"]["paused"] = json!(true);
+        let mut resumed = deployment_json(3, 2, 3, 3, 3);
+        resumed["spec"]["paused"] = json!(false);
+        let (kluster, log) = mock_kluster(vec![(
+            "PATCH",
+            path.to_owned(),
+            vec![MockResponse::json(200, paused), MockResponse::json(200, resumed)],
+        )]);
+        assert!(kluster.set_deployment_paused(path, true).unwrap().is_paused());
+        assert!(!kluster.set_deployment_paused(path, false).unwrap().is_paused());
+        let log = log.lock().unwrap();
+        assert_eq!(log[0].header("Content-Type"), Some(MERGE_PATCH_TYPE));
+        assert_eq!(log[0].body_json(), json!({"spec": {"paused": true}}));
+        assert_eq!(log[1].body_json(), json!({"spec": {"paused": false}}));
+        // deployments that have never been paused don't say so
+        let never: Deployment = serde_json::from_value(deployment_json(1, 1, 3, 3, 3)).unwrap();
+        assert!(!never.is_paused());
+    }
+
+    #[test]
+    fn node_pod_pressure_at_and_below_cap() {
+        let mut small = node_json("small", "True", "2", "4Gi");
+        small["status"]["allocatable"]["pods"] = json!("3");
+        let pods = |names: Vec<&str>| {
+            let items: Vec<Value> = names.into_iter().map(pod_json).collect();
+            MockResponse::json(200, json!({ "items": items }))
+        };
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/api/v1/nodes/small", MockResponse::json(200, small)),
+            (
+                "GET",
+                "/api/v1/pods".to_owned(),
+                vec![pods(vec!["a", "b"]), pods(vec!["a", "b", "c"])],
+            ),
+        ]);
+        let below = kluster.node_pod_pressure("small").unwrap();
+        assert_eq!(below.node, "small");
+        assert_eq!(below.pods, 2);
+        assert_eq!(below.allocatable, 3);
+        assert_eq!(below.remaining(), 1);
+        assert!(!below.at_capacity());
+        assert!((below.utilization() - 2.0 / 3.0).abs() < 1e-9);
+
+        let at = kluster.node_pod_pressure("small").unwrap();
+        assert_eq!(at.remaining(), 0);
+        assert!(at.at_capacity());
+        assert_eq!(at.utilization(), 1.0);
+        assert_eq!(
+            paths(&log)[1],
+            "/api/v1/pods?fieldSelector=spec.nodeName%3Dsmall%2Cstatus.phase%21%3DSucceeded\
+             %2Cstatus.phase%21%3DFailed"
+        );
+    }
+
+    #[test]
+    fn export_small_namespace() {
+        let list = |items: Vec<Value>| MockResponse::json(200, json!({ "items": items }));
+        let mut routes = Vec::new();
+        for &(resource, _) in EXPORT_KINDS.iter() {
+            let path = builtin_kind(resource).unwrap().0.path(Some("ns"), None);
+            let response = match resource {
+                "configmaps" => list(vec![json!({
+                    "metadata": {
+                        "name": "conf",
+                        "namespace": "ns",
+                        "uid": "1234",
+                        "resourceVersion": "10",
+                    },
+                    "data": {"level": "debug"},
+                })]),
+                "services" => list(vec![json!({
+                    "metadata": {"name": "web", "namespace": "ns"},
+                    "spec": {"clusterIP": "10.0.0.1", "ports": [{"port": 80}]},
+                    "status": {"loadBalancer": {}},
+                })]),
+                "serviceaccounts" => list(vec![json!({
+                    "metadata": {"name": "default", "namespace": "ns"},
+                })]),
+                "jobs" => list(vec![json!({
+                    "metadata": {
+                        "name": "nightly-1",
+                        "namespace": "ns",
+                        "ownerReferences": [{
+                            "kind": "CronJob",
+                            "name": "nightly",
+                            "uid": "5678",
+                            "controller": true,
+                        }],
+                    },
+                })]),
+                "secrets" => MockResponse::json(403, status_body(403, "secrets is forbidden")),
+                _ => list(Vec::new()),
+            };
+            routes.push(route("GET", path.as_str(), response));
+        }
+        let (kluster, _) = mock_kluster(routes);
+        let dir = TempDir::new("click-export").unwrap();
+        let written = kluster.export_namespace("ns", dir.path()).unwrap();
+        assert_eq!(
+            written,
+            vec![
+                dir.path().join("configmaps/conf.yaml"),
+                dir.path().join("serviceaccounts/default.yaml"),
+                dir.path().join("services/web.yaml"),
+            ]
+        );
+        let read = |path: &PathBuf| -> Value {
+            serde_yaml::from_str(fs::read_to_string(path).unwrap().as_str()).unwrap()
+        };
+        assert_eq!(
+            read(&written[0]),
+            json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": {"name": "conf", "namespace": "ns"},
+                "data": {"level": "debug"},
+            })
+        );
+        assert_eq!(
+            read(&written[2]),
+            json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": {"name": "web", "namespace": "ns"},
+                "spec": {"ports": [{"port": 80}]},
+            })
+        );
+        assert!(!dir.path().join("jobs").exists());
+        assert!(!dir.path().join("secrets").exists());
+    }
+
+    #[test]
+    fn scheduling_diagnosis_of_pending_pod() {
+        let mut pod = with_phase(pod_json("web"), "Pending");
+        pod["status"]["conditions"] = json!([{
+            "type": "PodScheduled",
+            "status": "False",
+            "reason": "Unschedulable",
+            "message": "0/1 nodes are available: 1 Insufficient memory.",
+        }]);
+        let mut old = event_json(
+            "Warning",
+            "FailedScheduling",
+            "0/4 nodes are available: 4 Insufficient cpu.",
+            "web",
+            None,
+        );
+        old["lastTimestamp"] = json!("2018-01-01T00:01:00Z");
+        let latest = event_json(
+            "Warning",
+            "FailedScheduling",
+            "0/5 nodes are available: 3 Insufficient cpu, 2 node(s) didn't match pod \
+             topology spread constraints. preemption: 0/5 nodes are available: 5 No preemption \
+             victims found for incoming pod.",
+            "web",
+            None,
+        );
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/ns/pods/web", MockResponse::json(200, pod)),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/events",
+                MockResponse::json(200, json!({"items": [latest, old]})),
+            ),
+        ]);
+        let diagnosis = kluster.scheduling_diagnosis("ns", "web").unwrap();
+        assert_eq!(diagnosis.pod, "web");
+        assert!(diagnosis.pending);
+        assert!(diagnosis.message.unwrap().starts_with("0/5 nodes are available"));
+        assert_eq!(diagnosis.total_nodes, Some(5));
+        assert_eq!(
+            diagnosis.reasons,
+            vec![
+                (3, "Insufficient cpu".to_owned()),
+                (2, "node(s) didn't match pod topology spread constraints".to_owned()),
+            ]
+        );
+        assert_eq!(
+            paths(&log)[1],
+            "/api/v1/namespaces/ns/events\
+             ?fieldSelector=involvedObject.name%3Dweb%2Creason%3DFailedScheduling"
+        );
+
+        // once the events expire, the PodScheduled condition still says why
+        let pod = kluster.get_value("/api/v1/namespaces/ns/pods/web").unwrap();
+        let (kluster, _) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/ns/pods/web", MockResponse::json(200, pod)),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/events",
+                MockResponse::json(200, json!({"items": []})),
+            ),
+        ]);
+        let diagnosis = kluster.scheduling_diagnosis("ns", "web").unwrap();
+        assert_eq!(diagnosis.total_nodes, Some(1));
+        assert_eq!(diagnosis.reasons, vec![(1, "Insufficient memory".to_owned())]);
+    }
+
+    #[test]
+    fn scheduling_health_of_each_state() {
+        let mut cordoned = node_json("cordoned", "True", "4", "16Gi");
+        cordoned["spec"]["unschedulable"] = json!(true);
+        let mut lost = node_json("lost", "Unknown", "4", "16Gi");
+        lost["spec"]["unschedulable"] = json!(true);
+        let nodes: NodeList = serde_json::from_value(json!({"items": [
+            node_json("ok", "True", "4", "16Gi"),
+            cordoned,
+            node_json("broken", "False", "4", "16Gi"),
+            lost,
+        ]})).unwrap();
+        let health = nodes.scheduling_health();
+        assert_eq!(health.schedulable, vec!["ok"]);
+        assert_eq!(health.cordoned, vec!["cordoned"]);
+        // not ready trumps cordoned
+        assert_eq!(health.not_ready, vec!["broken", "lost"]);
+        assert!(!health.all_schedulable());
+
+        let nodes: NodeList = serde_json::from_value(json!({"items": [
+            node_json("a", "True", "4", "16Gi"),
+            node_json("b", "True", "4", "16Gi"),
+        ]})).unwrap();
+        assert!(nodes.scheduling_health().all_schedulable());
+    }
+
+    /// Records which of its methods were called, with the pod's name, and stops after a delete
+    struct RecordingWatcher {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Watcher<Pod> for RecordingWatcher {
+        fn on_added(&mut self, pod: &Pod) -> bool {
+            self.calls.lock().unwrap().push(format!("added {}", pod.metadata.name));
+            true
+        }
+
+        fn on_modified(&mut self, pod: &Pod) -> bool {
+            self.calls.lock().unwrap().push(format!("modified {}", pod.metadata.name));
+            true
+        }
+
+        fn on_deleted(&mut self, pod: &Pod) -> bool {
+            self.calls.lock().unwrap().push(format!("deleted {}", pod.metadata.name));
+            false
+        }
+    }
+
+    #[test]
+    fn watcher_callback_per_event_type() {
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=10&watch=true",
+                watch_stream(vec![
+                    ("MODIFIED", with_resource_version(pod_json("a"), "11")),
+                    ("ADDED", with_resource_version(pod_json("b"), "12")),
+                    ("DELETED", with_resource_version(pod_json("a"), "13")),
+                    ("MODIFIED", with_resource_version(pod_json("b"), "14")),
+                ]),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "10"},
+                    "items": [with_resource_version(pod_json("a"), "9")],
+                })),
+            ),
+        ]);
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let watcher = RecordingWatcher {
+            calls: calls.clone(),
+        };
+        kluster
+            .register_watcher("/api/v1/pods", Box::new(watcher))
+            .unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["added a", "modified a", "added b", "deleted a"]
+        );
+
+        // methods that aren't implemented ignore their events
+        let mut watcher = RecordingWatcher {
+            calls: calls.clone(),
+        };
+        let error = WatchEvent::Error(status_body(500, "internal error"));
+        assert!(dispatch_event(&mut watcher, &error));
+        assert_eq!(calls.lock().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn zero_one_and_multiple_default_storage_classes() {
+        let class = |name: &str, annotation: Option<&str>| {
+            let mut sc = json!({
+                "metadata": {"name": name},
+                "provisioner": "ebs.csi.aws.com",
+            });
+            if let Some(key) = annotation {
+                sc["metadata"]["annotations"] = json!({ key: "true" });
+            }
+            sc
+        };
+        let current = "storageclass.kubernetes.io/is-default-class";
+        let beta = "storageclass.beta.kubernetes.io/is-default-class";
+        let lists = vec![
+            json!({"items": [class("slow", None)]}),
+            json!({"items": [class("slow", None), class("gp3", Some(current))]}),
+            json!({"items": [class("gp2", Some(beta)), class("gp3", Some(current))]}),
+        ];
+        let (kluster, _) = mock_kluster(vec![(
+            "GET",
+            "/apis/storage.k8s.io/v1/storageclasses".to_owned(),
+            lists.into_iter().map(|l| MockResponse::json(200, l)).collect(),
+        )]);
+        match kluster.default_storage_class().unwrap() {
+            DefaultStorageClass::None => {}
+            other => panic!("expected no default, got {:?}", other),
+        }
+        match kluster.default_storage_class().unwrap() {
+            DefaultStorageClass::One(sc) => assert_eq!(sc.metadata.name, "gp3"),
+            other => panic!("expected one default, got {:?}", other),
+        }
+        match kluster.default_storage_class().unwrap() {
+            DefaultStorageClass::Multiple(names) => assert_eq!(names, vec!["gp2", "gp3"]),
+            other => panic!("expected several defaults, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn grep_mock_log_stream() {
+        let logs = "start\nconnecting\nERROR timeout\nretrying\nok\n\
+                    idle\nidle\nERROR refused\ndone\n";
+        let (kluster, log) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods/web/log",
+            MockResponse::raw(200, logs.as_bytes().to_vec()),
+        )]);
+        let params = LogParams {
+            follow: true,
+            ..LogParams::default()
+        };
+        let mut grep = LogGrep::new(Regex::new("ERROR").unwrap());
+        grep.before = 1;
+        grep.after = 1;
+        let mut out = Vec::new();
+        assert_eq!(kluster.grep_logs("ns", "web", &params, &grep, &mut out).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "connecting\nERROR timeout\nretrying\n--\nidle\nERROR refused\ndone\n"
+        );
+        assert_eq!(paths(&log), vec!["/api/v1/namespaces/ns/pods/web/log?follow=true"]);
+
+        let mut invert = LogGrep::new(Regex::new("idle|ERROR").unwrap());
+        invert.invert = true;
+        let mut out = Vec::new();
+        assert_eq!(invert.filter(logs.as_bytes(), &mut out).unwrap(), 5);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "start\nconnecting\nretrying\nok\ndone\n"
+        );
+    }
+
+    #[test]
+    fn kubelet_version_skew_of_two_versions() {
+        let node = |name: &str, version: Option<&str>| {
+            let mut node = node_json(name, "True", "4", "16Gi");
+            if let Some(v) = version {
+                node["status"]["nodeInfo"] = json!({"kubeletVersion": v, "architecture": "amd64"});
+            }
+            node
+        };
+        let nodes: NodeList = serde_json::from_value(json!({"items": [
+            node("a", Some("v1.27.9-eks-5e0fdde")),
+            node("b", Some("v1.28.3-eks-4f4795d")),
+            node("c", Some("v1.27.9-eks-5e0fdde")),
+            node("d", None),
+        ]})).unwrap();
+        let skew = nodes.kubelet_version_skew();
+        assert!(skew.skewed);
+        let versions: Vec<(&str, Vec<&str>)> = skew
+            .versions
+            .iter()
+            .map(|(v, nodes)| (v.as_str(), nodes.iter().map(|n| n.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("unknown", vec!["d"]),
+                ("v1.27.9-eks-5e0fdde", vec!["a", "c"]),
+                ("v1.28.3-eks-4f4795d", vec!["b"]),
+            ]
+        );
+
+        // patch versions don't count as skew
+        let nodes: NodeList = serde_json::from_value(json!({"items": [
+            node("a", Some("v1.28.1")),
+            node("b", Some("v1.28.3")),
+        ]})).unwrap();
+        assert!(!nodes.kubelet_version_skew().skewed);
+    }
+
+    #[test]
+    fn init_status_on_second_of_three() {
+        let status = |name: &str, state: Value| {
+            json!({
+                "name": name,
+                "image": "init:1",
+                "restartCount": 0,
+                "ready": false,
+                "state": state,
+            })
+        };
+        let done = json!({"terminated": {"exitCode": 0, "reason": "Completed"}});
+        let running = json!({"running": {"startedAt": "2018-01-01T00:00:00Z"}});
+        let waiting = json!({"waiting": {"reason": "PodInitializing"}});
+        let init_pod = |states: Vec<Value>| {
+            let mut pod = with_phase(pod_json("web"), "Pending");
+            pod["spec"]["initContainers"] = json!([
+                {"name": "migrate", "image": "init:1"},
+                {"name": "seed", "image": "init:1"},
+                {"name": "warm", "image": "init:1"},
+            ]);
+            let statuses: Vec<Value> = ["migrate", "seed", "warm"]
+                .iter()
+                .zip(states.into_iter())
+                .map(|(name, state)| status(name, state))
+                .collect();
+            pod["status"]["initContainerStatuses"] = json!(statuses);
+            serde_json::from_value::<Pod>(pod).unwrap()
+        };
+        let pod = init_pod(vec![done.clone(), running, waiting.clone()]);
+        assert_eq!(pod.init_status(), Some("Init:1/3".to_owned()));
+
+        let crashing = json!({"waiting": {"reason": "CrashLoopBackOff"}});
+        let pod = init_pod(vec![done.clone(), crashing, waiting.clone()]);
+        assert_eq!(pod.init_status(), Some("Init:CrashLoopBackOff".to_owned()));
+        let failed = json!({"terminated": {"exitCode": 3}});
+        let pod = init_pod(vec![failed, waiting.clone(), waiting]);
+        assert_eq!(pod.init_status(), Some("Init:ExitCode:3".to_owned()));
+
+        let pod = init_pod(vec![done.clone(), done.clone(), done]);
+        assert_eq!(pod.init_status(), None);
+        let plain: Pod = serde_json::from_value(pod_json("plain")).unwrap();
+        assert_eq!(plain.init_status(), None);
+    }
+
+    #[test]
+    fn validating_webhook_that_fails_closed() {
+        let validating = json!({
+            "kind": "ValidatingWebhookConfiguration",
+            "items": [{
+                "metadata": {"name": "policy"},
+                "webhooks": [{
+                    "name": "validate.policy.example.com",
+                    "clientConfig": {
+                        "service": {"namespace": "policy", "name": "webhook", "path": "/validate"}
+                    },
+                    "rules": [{
+                        "operations": ["CREATE", "UPDATE"],
+                        "apiGroups": ["apps"],
+                        "apiVersions": ["v1"],
+                        "resources": ["deployments"]
+                    }],
+                    "failurePolicy": "Fail",
+                    "timeoutSeconds": 5
+                }]
+            }]
+        });
+        let mutating = json!({
+            "items": [{
+                "metadata": {"name": "sidecars"},
+                "webhooks": [{
+                    "name": "inject.sidecars.example.com",
+                    "clientConfig": {"url": "https://inject.example.com/mutate"},
+                    "rules": [{
+                        "operations": ["CREATE"],
+                        "apiGroups": [""],
+                        "apiVersions": ["v1"],
+                        "resources": ["pods"]
+                    }],
+                    "failurePolicy": "Ignore"
+                }]
+            }]
+        });
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis/admissionregistration.k8s.io/v1/mutatingwebhookconfigurations",
+                MockResponse::json(200, mutating),
+            ),
+            route(
+                "GET",
+                "/apis/admissionregistration.k8s.io/v1/validatingwebhookconfigurations",
+                MockResponse::json(200, validating),
+            ),
+        ]);
+        let webhooks = kluster.webhooks().unwrap();
+        assert_eq!(webhooks.len(), 2);
+
+        let inject = &webhooks[0];
+        assert!(inject.mutating);
+        assert_eq!(inject.configuration, "sidecars");
+        assert!(!inject.webhook.fails_closed());
+        assert_eq!(inject.webhook.target(), "https://inject.example.com/mutate");
+        assert_eq!(inject.webhook.rules[0].to_string(), "CREATE core/v1 pods");
+
+        let validate = &webhooks[1];
+        assert!(!validate.mutating);
+        assert_eq!(validate.configuration, "policy");
+        assert_eq!(validate.webhook.name, "validate.policy.example.com");
+        assert!(validate.webhook.fails_closed());
+        assert_eq!(validate.webhook.timeout_seconds, Some(5));
+        assert_eq!(validate.webhook.target(), "svc/policy/webhook:443/validate");
+        assert_eq!(
+            validate.webhook.rules[0].to_string(),
+            "CREATE,UPDATE apps/v1 deployments"
+        );
+    }
+
+    #[test]
+    fn status_summary_of_deployment_and_node() {
+        let deployment = json!({
+            "metadata": {"name": "web", "namespace": "ns"},
+            "status": {"conditions": [
+                {
+                    "type": "Available",
+                    "status": "True",
+                    "lastTransitionTime": "2018-01-01T00:01:00Z",
+                    "reason": "MinimumReplicasAvailable"
+                },
+                {
+                    "type": "Progressing",
+                    "status": "True",
+                    "lastTransitionTime": "2018-01-01T00:03:00Z",
+                    "reason": "NewReplicaSetAvailable"
+                }
+            ]}
+        });
+        let mut node = node_json("node-1", "False", "4", "8Gi");
+        node["status"]["conditions"] = json!([
+            {
+                "type": "MemoryPressure",
+                "status": "False",
+                "lastTransitionTime": "2018-01-01T00:00:00Z"
+            },
+            {
+                "type": "Ready",
+                "status": "False",
+                "lastTransitionTime": "2018-01-01T00:04:00Z",
+                "reason": "KubeletNotReady"
+            }
+        ]);
+        let namespace = json!({"metadata": {"name": "ns"}, "status": {"phase": "Active"}});
+        let (kluster, _) = mock_kluster(vec![
+            route(
+                "GET",
+                "/apis/apps/v1/namespaces/ns/deployments/web",
+                MockResponse::json(200, deployment),
+            ),
+            route("GET", "/api/v1/nodes/node-1", MockResponse::json(200, node)),
+            route("GET", "/api/v1/namespaces/ns", MockResponse::json(200, namespace)),
+        ]);
+
+        let summary = kluster
+            .status_summary("/apis/apps/v1/namespaces/ns/deployments/web")
+            .unwrap();
+        assert_eq!(summary.healthy, Some(true));
+        assert_eq!(summary.phase, None);
+        let latest = summary.latest.unwrap();
+        assert_eq!(latest.typ, "Progressing");
+        assert_eq!(latest.reason.as_ref().unwrap(), "NewReplicaSetAvailable");
+
+        let summary = kluster.status_summary("/api/v1/nodes/node-1").unwrap();
+        assert_eq!(summary.healthy, Some(false));
+        assert_eq!(summary.latest.unwrap().reason.unwrap(), "KubeletNotReady");
+
+        let summary = kluster.status_summary("/api/v1/namespaces/ns").unwrap();
+        assert_eq!(summary.healthy, Some(true));
+        assert_eq!(summary.phase.unwrap(), "Active");
+        assert!(summary.latest.is_none());
+    }
+
+    #[test]
+    fn force_delete_namespace_finalizes_then_deletes() {
+        let stuck = json!({
+            "kind": "Namespace",
+            "metadata": {
+                "name": "stuck",
+                "deletionTimestamp": "2018-01-01T00:00:00Z"
+            },
+            "spec": {"finalizers": ["kubernetes"]},
+            "status": {"phase": "Terminating"}
+        });
+        let (kluster, log) = mock_kluster(vec![
+            route("GET", "/api/v1/namespaces/stuck", MockResponse::json(200, stuck.clone())),
+            route("PUT", "/api/v1/namespaces/stuck/finalize", MockResponse::json(200, stuck)),
+            route(
+                "DELETE",
+                "/api/v1/namespaces/stuck",
+                MockResponse::json(404, status_body(404, "namespaces \"stuck\" not found")),
+            ),
+        ]);
+        kluster.force_delete_namespace("stuck").unwrap();
+
+        let log = log.lock().unwrap();
+        let calls: Vec<(&str, &str)> = log.iter()
+            .map(|r| (r.method.as_str(), r.path.as_str()))
+            .collect();
+        assert_eq!(
+            calls,
+            vec![
+                ("GET", "/api/v1/namespaces/stuck"),
+                ("PUT", "/api/v1/namespaces/stuck/finalize"),
+                ("DELETE", "/api/v1/namespaces/stuck"),
+            ]
+        );
+        let body = log[1].body_json();
+        assert_eq!(body["spec"], json!({"finalizers": []}));
+        assert_eq!(body["metadata"]["name"], "stuck");
+        assert_eq!(body["status"]["phase"], "Terminating");
+    }
+
+    #[test]
+    fn startup_breakdown_intervals() {
+        let condition = |typ: &str, time: &str| {
+            json!({"type": typ, "status": "True", "lastTransitionTime": time})
+        };
+        let running = |name: &str, started: &str| {
+            json!({
+                "name": name,
+                "image": "app:1",
+                "restartCount": 0,
+                "ready": true,
+                "state": {"running": {"startedAt": started}},
+            })
+        };
+        let mut pod = pod_json("slow");
+        pod["metadata"]["creationTimestamp"] = json!("2018-01-01T00:00:00Z");
+        pod["spec"]["containers"] = json!([
+            {"name": "app", "image": "app:1"},
+            {"name": "proxy", "image": "proxy:1"},
+        ]);
+        pod["status"]["conditions"] = json!([
+            condition("PodScheduled", "2018-01-01T00:00:02Z"),
+            condition("Initialized", "2018-01-01T00:00:12Z"),
+            condition("Ready", "2018-01-01T00:01:45Z"),
+        ]);
+        pod["status"]["containerStatuses"] = json!([
+            running("app", "2018-01-01T00:01:00Z"),
+            running("proxy", "2018-01-01T00:01:30Z"),
+        ]);
+        let (kluster, _) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods/slow",
+            MockResponse::json(200, pod),
+        )]);
+        let breakdown = kluster.pod_startup_breakdown("ns", "slow").unwrap();
+        let seconds = ::chrono::Duration::seconds;
+        assert_eq!(breakdown.scheduling, Some(seconds(2)));
+        assert_eq!(breakdown.initializing, Some(seconds(10)));
+        assert_eq!(breakdown.starting, Some(seconds(78)));
+        assert_eq!(breakdown.readiness, Some(seconds(15)));
+        assert_eq!(breakdown.total, Some(seconds(105)));
+
+        // still waiting for a node: only how long it's been created is known
+        let mut pending = with_phase(pod_json("pending"), "Pending");
+        pending["metadata"]["creationTimestamp"] = json!("2018-01-01T00:00:00Z");
+        pending["status"]["containerStatuses"] = json!([]);
+        let pending: Pod = serde_json::from_value(pending).unwrap();
+        let breakdown = pending.startup_breakdown();
+        assert!(breakdown.scheduling.is_none());
+        assert!(breakdown.starting.is_none());
+        assert!(breakdown.total.is_none());
+    }
+
+    #[test]
+    fn deletion_preview_of_deployment() {
+        let owned = |mut obj: Value, uid: &str, owner_uid: &str| {
+            obj["metadata"]["uid"] = json!(uid);
+            obj["metadata"]["ownerReferences"] = json!([{"uid": owner_uid, "controller": true}]);
+            obj
+        };
+        let path = "/apis/apps/v1/namespaces/ns/deployments/web";
+        let deployment = json!({"metadata": {"name": "web", "namespace": "ns", "uid": "web-uid"}});
+        let replicaset = json!({"metadata": {"name": "web-5d4f", "namespace": "ns"}});
+        let replicaset = owned(replicaset, "rs-uid", "web-uid");
+        let pods = vec![
+            owned(pod_json("web-5d4f-a"), "pod-a", "rs-uid"),
+            owned(pod_json("web-5d4f-b"), "pod-b", "rs-uid"),
+            owned(pod_json("other-1"), "other", "other-rs-uid"),
+        ];
+        let mut routes = vec![
+            route("GET", path, MockResponse::json(200, deployment)),
+            route("DELETE", path, MockResponse::json(200, status_body(200, "dry run"))),
+        ];
+        for &(group, version, resource, _) in DEPENDENT_KINDS.iter() {
+            let gvr = GroupVersionResource::new(group, version, resource);
+            let list_path = gvr.path(Some("ns"), None);
+            let response = match resource {
+                "replicasets" => MockResponse::json(200, json!({"items": [replicaset.clone()]})),
+                "pods" => MockResponse::json(200, json!({"items": pods.clone()})),
+                "jobs" => MockResponse::json(403, status_body(403, "jobs is forbidden")),
+                _ => MockResponse::json(200, json!({"items": []})),
+            };
+            routes.push(route("GET", list_path.as_str(), response));
+        }
+        let (kluster, log) = mock_kluster(routes);
+        let preview = kluster.deletion_preview(path).unwrap();
+
+        let summary: Vec<(&str, &str, &str, usize)> = preview
+            .dependents
+            .iter()
+            .map(|d| (d.kind.as_str(), d.name.as_str(), d.owner.as_str(), d.depth))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("ReplicaSet", "web-5d4f", "web", 1),
+                ("Pod", "web-5d4f-a", "web-5d4f", 2),
+                ("Pod", "web-5d4f-b", "web-5d4f", 2),
+            ]
+        );
+        assert_eq!(preview.dependents[1].namespace, Some("ns".to_owned()));
+        assert_eq!(preview.skipped, vec!["jobs".to_owned()]);
+
+        let log = log.lock().unwrap();
+        let delete = log.iter().find(|r| r.method == "DELETE").unwrap();
+        assert!(delete.path.contains("dryRun=All"));
+        assert_eq!(delete.body_json()["propagationPolicy"], "Foreground");
+    }
+
+    #[test]
+    fn rbac_for_service_account_bound_to_role() {
+        let rbac = "/apis/rbac.authorization.k8s.io/v1";
+        let binding = |namespace: Option<&str>, name: &str, role: (&str, &str), subject: &Value| {
+            let (role_kind, role) = role;
+            json!({
+                "metadata": {"name": name, "namespace": namespace},
+                "roleRef": {"kind": role_kind, "name": role},
+                "subjects": [subject],
+            })
+        };
+        let deployer = json!({"kind": "ServiceAccount", "name": "deployer"});
+        let service_accounts = json!({"kind": "Group", "name": "system:serviceaccounts"});
+        let alice = json!({"kind": "User", "name": "alice"});
+        let cluster_bindings = json!({"items": [
+            binding(None, "view-all", ("ClusterRole", "view"), &service_accounts),
+            binding(None, "admins", ("ClusterRole", "cluster-admin"), &alice),
+        ]});
+        let bindings = json!({"items": [
+            binding(Some("ns"), "deployer", ("Role", "deploy"), &deployer),
+            binding(Some("ns"), "stale", ("Role", "gone"), &deployer),
+            // the same name in another namespace is a different service account
+            binding(Some("other"), "deployer", ("Role", "deploy"), &deployer),
+        ]});
+        let cluster_roles = json!({"items": [
+            {"metadata": {"name": "view"}, "rules": [
+                {"verbs": ["get", "list"], "apiGroups": [""], "resources": ["pods"]}
+            ]},
+            {"metadata": {"name": "cluster-admin"}, "rules": [
+                {"verbs": ["*"], "apiGroups": ["*"], "resources": ["*"]}
+            ]},
+        ]});
+        let roles = json!({"items": [
+            {
+                "metadata": {"name": "deploy", "namespace": "ns"},
+                "rules": [{
+                    "verbs": ["get", "patch"],
+                    "apiGroups": ["apps"],
+                    "resources": ["deployments"],
+                    "resourceNames": ["web"]
+                }]
+            },
+            // a role of the same name in another namespace doesn't apply
+            {
+                "metadata": {"name": "gone", "namespace": "other"},
+                "rules": [{"verbs": ["delete"], "apiGroups": [""], "resources": ["pods"]}]
+            },
+        ]});
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                format!("{}/clusterrolebindings", rbac).as_str(),
+                MockResponse::json(200, cluster_bindings),
+            ),
+            route(
+                "GET",
+                format!("{}/rolebindings", rbac).as_str(),
+                MockResponse::json(200, bindings),
+            ),
+            route(
+                "GET",
+                format!("{}/clusterroles", rbac).as_str(),
+                MockResponse::json(200, cluster_roles),
+            ),
+            route("GET", format!("{}/roles", rbac).as_str(), MockResponse::json(200, roles)),
+        ]);
+        let result = kluster.rbac_for("ServiceAccount", "ns/deployer").unwrap();
+        let permissions: Vec<(Option<&str>, &str, &str, String)> = result
+            .permissions
+            .iter()
+            .map(|p| {
+                (
+                    p.namespace.as_ref().map(|ns| ns.as_str()),
+                    p.binding.as_str(),
+                    p.role.as_str(),
+                    p.rule.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            permissions,
+            vec![
+                (
+                    None,
+                    "ClusterRoleBinding view-all",
+                    "ClusterRole view",
+                    "get,list core/pods".to_owned(),
+                ),
+                (
+                    Some("ns"),
+                    "RoleBinding ns/deployer",
+                    "Role ns/deploy",
+                    "get,patch apps/deployments named web".to_owned(),
+                ),
+            ]
+        );
+        assert_eq!(
+            result.skipped,
+            vec!["RoleBinding ns/stale refers to missing Role ns/gone".to_owned()]
+        );
+        // each kind of role and binding is listed once
+        assert_eq!(log.lock().unwrap().len(), 4);
+
+        assert!(kluster.rbac_for("ServiceAccount", "deployer").is_err());
+    }
+
+    #[test]
+    fn rolling_restart_with_nothing_matching() {
+        let (kluster, log) = mock_kluster(vec![route(
+            "GET",
+            "/api/v1/namespaces/ns/pods?labelSelector=app%3Dnone",
+            MockResponse::json(200, json!({"items": []})),
+        )]);
+        let evicted = kluster.rolling_restart_pods("ns", "app=none").unwrap();
+        assert!(evicted.is_empty());
+        assert_eq!(paths(&log), vec!["/api/v1/namespaces/ns/pods?labelSelector=app%3Dnone"]);
+    }
+
+    #[test]
+    fn reflector_empties_on_delete() {
+        let mut reflector = Reflector::new();
+        assert!(reflector.is_empty());
+        let pod = with_resource_version(pod_json("web"), "10");
+        reflector.apply(WatchEvent::Added(pod.clone())).unwrap();
+        assert!(!reflector.is_empty());
+        assert_eq!(reflector.get("ns/web"), Some(&pod));
+        reflector
+            .apply(WatchEvent::Deleted(with_resource_version(pod_json("web"), "11")))
+            .unwrap();
+        assert!(reflector.is_empty());
+        assert_eq!(reflector.get("ns/web"), None);
+    }
+
+    #[test]
+    fn fill_event_ring_from_another_thread() {
+        let (kluster, _) = mock_kluster(vec![
+            (
+                "GET",
+                "/api/v1/events?resourceVersion=5&watch=true".to_owned(),
+                vec![
+                    watch_stream(vec![(
+                        "ADDED",
+                        event_json("Warning", "BackOff", "second", "web", None),
+                    )]),
+                    // keeps the watch backing off rather than replaying the stream
+                    MockResponse::json(500, status_body(500, "unavailable")),
+                ],
+            ),
+            route(
+                "GET",
+                "/api/v1/events",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "5"},
+                    "items": [event_json("Normal", "Scheduled", "first", "web", None)],
+                })),
+            ),
+        ]);
+        let ring = EventRing::new(10);
+        let filling = ring.clone();
+        // the watch never returns, so it's left running when the test finishes
+        thread::spawn(move || kluster.fill_event_ring(None, &filling));
+        let start = Instant::now();
+        while ring.len() < 2 && start.elapsed() < Duration::from_secs(10) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let messages: Vec<String> = ring.snapshot().into_iter().map(|e| e.message).collect();
+        assert_eq!(messages, vec!["second", "first"]);
+    }
+
+    #[test]
+    fn watch_forever_relists_when_watch_is_gone() {
+        let list = |rv: &str, name: &str| {
+            MockResponse::json(200, json!({
+                "metadata": {"resourceVersion": rv},
+                "items": [pod_json(name)],
+            }))
+        };
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=1&watch=true",
+                MockResponse::json(410, status_body(410, "too old resource version: 1 (5)")),
+            ),
+            route(
+                "GET",
+                "/api/v1/pods?resourceVersion=5&watch=true",
+                watch_stream(vec![("MODIFIED", with_resource_version(pod_json("web"), "6"))]),
+            ),
+            ("GET", "/api/v1/pods".to_owned(), vec![list("1", "old"), list("5", "web")]),
+        ]);
+        let mut seen = Vec::new();
+        kluster
+            .watch_forever("/api/v1/pods", |event: WatchEvent<Pod>| {
+                seen.push(match event {
+                    WatchEvent::Added(p) => format!("added {}", p.metadata.name),
+                    WatchEvent::Modified(p) => format!("modified {}", p.metadata.name),
+                    other => panic!("unexpected event: {:?}", other),
+                });
+                seen.len() < 3
+            })
+            .unwrap();
+        assert_eq!(seen, vec!["added old", "added web", "modified web"]);
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/api/v1/pods",
+                "/api/v1/pods?resourceVersion=1&watch=true",
+                "/api/v1/pods",
+                "/api/v1/pods?resourceVersion=5&watch=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_forever_retries_unavailable_server() {
+        let (kluster, log) = mock_kluster(vec![
+            (
+                "GET",
+                "/api/v1/pods?resourceVersion=1&watch=true".to_owned(),
+                vec![
+                    MockResponse::json(503, status_body(503, "apiserver is shutting down")),
+                    watch_stream(vec![("ADDED", with_resource_version(pod_json("web"), "2"))]),
+                ],
+            ),
+            (
+                "GET",
+                "/api/v1/pods".to_owned(),
+                vec![
+                    // a proxy in front of the server doesn't send a Status
+                    MockResponse::raw(502, b"<html>Bad Gateway</html>".to_vec()),
+                    MockResponse::json(200, json!({"metadata": {"resourceVersion": "1"}})),
+                ],
+            ),
+        ]);
+        let mut names = Vec::new();
+        kluster
+            .watch_forever("/api/v1/pods", |event: WatchEvent<Pod>| {
+                if let WatchEvent::Added(p) = event {
+                    names.push(p.metadata.name);
+                }
+                false
+            })
+            .unwrap();
+        assert_eq!(names, vec!["web"]);
+        assert_eq!(
+            paths(&log),
+            vec![
+                "/api/v1/pods",
+                "/api/v1/pods",
+                "/api/v1/pods?resourceVersion=1&watch=true",
+                "/api/v1/pods?resourceVersion=1&watch=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn watch_forever_backs_off_from_empty_streams() {
+        let (kluster, log) = mock_kluster(vec![
+            (
+                "GET",
+                "/api/v1/pods?resourceVersion=1&watch=true".to_owned(),
+                vec![
+                    watch_stream(vec![]),
+                    watch_stream(vec![("ADDED", with_resource_version(pod_json("web"), "2"))]),
+                ],
+            ),
+            route(
+                "GET",
+                "/api/v1/pods",
+                MockResponse::json(200, json!({"metadata": {"resourceVersion": "1"}})),
+            ),
+        ]);
+        let start = Instant::now();
+        kluster
+            .watch_forever("/api/v1/pods", |_: WatchEvent<Pod>| false)
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_secs(WATCH_MIN_BACKOFF));
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn watch_table_returns_watch_rejections() {
+        let (kluster, log) = mock_kluster(vec![
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods?resourceVersion=10&timeoutSeconds=5&watch=true",
+                MockResponse::json(403, status_body(403, "pods is forbidden: cannot watch")),
+            ),
+            route(
+                "GET",
+                "/api/v1/namespaces/ns/pods",
+                MockResponse::json(200, json!({
+                    "metadata": {"resourceVersion": "10"},
+                    "columnDefinitions": [{"name": "Name", "type": "string", "format": "name"}],
+                    "rows": [{"cells": ["a"]}],
+                })),
+            ),
+        ]);
+        let mut updates = 0;
+        let result = kluster.watch_table("/api/v1/namespaces/ns/pods", |_| {
+            updates += 1;
+            true
+        });
+        match result {
+            Err(KubeError::KubeServerError(msg)) => {
+                assert_eq!(msg, "pods is forbidden: cannot watch")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(updates, 1);
+        // not retried
+        assert_eq!(log.lock().unwrap().len(), 2);
     }
 }
@@ -54,9 +54,12 @@ mod completer;
 mod connector;
 mod config;
 mod describe;
+mod display;
 mod error;
+mod gzip;
 mod kube;
 mod parser;
+mod quantity;
 mod subjaltnames;
 mod table;
 mod values;
@@ -211,13 +214,27 @@ impl Env {
         match ctx {
             Some(cname) => {
                 self.kluster = match self.config.cluster_for_context(cname) {
-                    Ok(k) => Some(k),
+                    Ok(k) => {
+                        self.config.current_context = Some(cname.to_owned());
+                        if self.namespace.is_none() {
+                            // default to the namespace the context specifies
+                            self.namespace = self.config.current_namespace().map(|n| n.to_owned());
+                        }
+                        Some(k)
+                    }
                     Err(e) => {
                         println!(
                             "[Warning] Couldn't find/load context {}, now no current context.  Error: {}",
                             cname,
                             e
                         );
+                        for problem in self.config
+                            .validate()
+                            .iter()
+                            .filter(|p| p.context() == Some(cname))
+                        {
+                            println!("  {}", problem);
+                        }
                         None
                     }
                 };
@@ -238,6 +255,7 @@ impl Env {
             self.clear_current();
         }
         self.namespace = namespace.map(|n| n.to_owned());
+        self.config.set_current_namespace(namespace);
         self.set_prompt();
     }
 
@@ -265,14 +283,9 @@ impl Env {
             }
             LastList::PodList(ref pl) => {
                 if let Some(pod) = pl.items.get(num) {
-                    let containers = pod.spec
-                        .containers
-                        .iter()
-                        .map(|cspec| cspec.name.clone())
-                        .collect();
                     self.current_object = KObj::Pod {
                         name: pod.metadata.name.clone(),
-                        containers: containers,
+                        containers: pod.log_container_names(),
                     };
                     self.current_object_namespace = pod.metadata.namespace.clone();
                 } else {
@@ -371,13 +384,19 @@ impl Env {
         F: FnOnce(&Kluster) -> Result<R, KubeError>,
     {
         match self.kluster {
-            Some(ref k) => match f(k) {
-                Ok(r) => Some(r),
-                Err(e) => {
-                    println!("{}", e);
-                    None
+            Some(ref k) => {
+                let res = f(k);
+                for warning in k.take_warnings().iter() {
+                    println!("{} {}", Yellow.paint("Warning:"), warning.text);
                 }
-            },
+                match res {
+                    Ok(r) => Some(r),
+                    Err(e) => {
+                        println!("{}", e);
+                        None
+                    }
+                }
+            }
             None => {
                 println!("Need to have an active context");
                 None
@@ -553,15 +572,29 @@ fn main() {
     click_path.push("click.config");
     let click_conf = ClickConfig::from_file(click_path.as_path().to_str().unwrap());
 
-    let mut config_path = conf_dir.clone();
-    config_path.push("config");
+    // like kubectl, use the files in $KUBECONFIG if set (unless a config dir was given)
+    let config_paths: Vec<PathBuf> = match std::env::var("KUBECONFIG") {
+        Ok(ref files) if !files.is_empty() && !matches.is_present("config_dir") => files
+            .split(':')
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        _ => {
+            let mut config_path = conf_dir.clone();
+            config_path.push("config");
+            vec![config_path]
+        }
+    };
 
-    let config = match Config::from_file(config_path.as_path().to_str().
-                                         unwrap_or("[CONFIG_PATH_EMPTY")) {
+    let config = match Config::merge(config_paths.as_slice()) {
         Ok(c) => c,
         Err(e) => {
             println!("Could not load kubernetes config: '{}'. Cannot continue.  Error was: {}",
-                     config_path.as_path().to_str().unwrap_or("[CONFIG_PATH_EMPTY"),
+                     config_paths
+                         .iter()
+                         .map(|p| p.to_string_lossy().into_owned())
+                         .collect::<Vec<String>>()
+                         .join(":"),
                      e.description());
             return;
         }
@@ -597,6 +630,7 @@ fn main() {
     commands.push(Box::new(cmd::Secrets::new()));
     commands.push(Box::new(cmd::PortForward::new()));
     commands.push(Box::new(cmd::PortForwards::new()));
+    commands.push(Box::new(cmd::StorageClassCmd::new()));
 
     let mut rl = Editor::<ClickCompleter>::new();
     rl.load_history(hist_path.as_path()).unwrap_or_default();
@@ -0,0 +1,344 @@
+// Copyright 2017 Databricks, Inc.
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+
+// http://www.apache.org/licenses/LICENSE-2.0
+
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Kubernetes resource quantities, like "100m" of cpu or "1.5Gi" of memory
+
+use serde::{Deserialize, Deserializer};
+use serde::de::Error as DeError;
+use serde_json::Value;
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Sub};
+use std::str::FromStr;
+
+use error::KubeError;
+
+const NANOS_PER_UNIT: i128 = 1_000_000_000;
+
+/// A quantity, stored exactly as a count of billionths of the base unit (cores, bytes, etc.), the
+/// smallest amount kubernetes can express.  Quantities order by their numeric value, so 512Mi <
+/// 1G < 1Gi regardless of suffix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Quantity {
+    nanos: i128,
+}
+
+/// Get the multiplier (in nanos) for a suffix
+fn suffix_multiplier(suffix: &str) -> Option<i128> {
+    let mult = match suffix {
+        "n" => 1,
+        "u" => 1_000,
+        "m" => 1_000_000,
+        "" => NANOS_PER_UNIT,
+        "k" => NANOS_PER_UNIT * 1_000,
+        "M" => NANOS_PER_UNIT * 1_000_000,
+        "G" => NANOS_PER_UNIT * 1_000_000_000,
+        "T" => NANOS_PER_UNIT * 1_000_000_000_000,
+        "P" => NANOS_PER_UNIT * 1_000_000_000_000_000,
+        "E" => NANOS_PER_UNIT * 1_000_000_000_000_000_000,
+        "Ki" => NANOS_PER_UNIT << 10,
+        "Mi" => NANOS_PER_UNIT << 20,
+        "Gi" => NANOS_PER_UNIT << 30,
+        "Ti" => NANOS_PER_UNIT << 40,
+        "Pi" => NANOS_PER_UNIT << 50,
+        "Ei" => NANOS_PER_UNIT << 60,
+        _ => return None,
+    };
+    Some(mult)
+}
+
+/// n / d rounded towards positive infinity, for d > 0
+fn div_ceil(n: i128, d: i128) -> i128 {
+    // division truncates towards zero, so that's already rounded up for negative n
+    if n % d > 0 {
+        n / d + 1
+    } else {
+        n / d
+    }
+}
+
+/// Is suffix a decimal exponent (like e3 or E-2) rather than a unit suffix.  E on its own (or Ei)
+/// is exa (or exbi).
+fn is_exponent(suffix: &str) -> bool {
+    if !(suffix.starts_with('e') || suffix.starts_with('E')) {
+        return false;
+    }
+    let exp = &suffix[1..];
+    let digits = if exp.starts_with('+') || exp.starts_with('-') {
+        &exp[1..]
+    } else {
+        exp
+    };
+    !digits.is_empty() && digits.chars().all(|c| c.is_digit(10))
+}
+
+impl Quantity {
+    pub fn zero() -> Quantity {
+        Quantity { nanos: 0 }
+    }
+
+    /// A quantity of a whole number of base units
+    #[allow(dead_code)]
+    pub fn from_units(units: i64) -> Quantity {
+        Quantity {
+            nanos: i128::from(units) * NANOS_PER_UNIT,
+        }
+    }
+
+    /// The quantity in base units (cores for cpu, bytes for memory, etc), possibly with some loss
+    /// of precision
+    pub fn as_f64(&self) -> f64 {
+        self.nanos as f64 / NANOS_PER_UNIT as f64
+    }
+
+    /// The quantity in thousandths of the base unit, rounded up, which is how cpu is usually
+    /// thought about
+    #[allow(dead_code)]
+    pub fn as_millis(&self) -> i64 {
+        div_ceil(self.nanos, NANOS_PER_UNIT / 1000) as i64
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.nanos == 0
+    }
+}
+
+impl FromStr for Quantity {
+    type Err = KubeError;
+
+    /// Parse a quantity in the kubernetes format: a (possibly fractional, possibly signed) number
+    /// followed by an optional binary (Ki, Mi, ...) or decimal (m, k, M, ...) suffix, or a
+    /// decimal exponent (1e3)
+    fn from_str(s: &str) -> Result<Quantity, KubeError> {
+        let s = s.trim();
+        let bad = || KubeError::ParseErr(format!("Invalid quantity: '{}'", s));
+        let num_end = s.find(|c: char| !(c.is_digit(10) || c == '.' || c == '+' || c == '-'))
+            .unwrap_or(s.len());
+        let (num, suffix) = s.split_at(num_end);
+        if num.is_empty() {
+            return Err(bad());
+        }
+
+        let (negative, num) = if num.starts_with('-') {
+            (true, &num[1..])
+        } else if num.starts_with('+') {
+            (false, &num[1..])
+        } else {
+            (false, num)
+        };
+        let mut parts = num.splitn(2, '.');
+        let whole = parts.next().unwrap_or("");
+        let frac = parts.next().unwrap_or("");
+        if (whole.is_empty() && frac.is_empty())
+            || !whole.chars().all(|c| c.is_digit(10))
+            || !frac.chars().all(|c| c.is_digit(10))
+        {
+            return Err(bad());
+        }
+        // mantissa / 10^scale is the number we were given
+        let mut mantissa: i128 = 0;
+        for c in whole.chars().chain(frac.chars()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(i128::from(c.to_digit(10).unwrap())))
+                .ok_or_else(&bad)?;
+        }
+        let mut scale = frac.len() as u32;
+
+        let mult = if is_exponent(suffix) {
+            let exp: i32 = suffix[1..].parse().map_err(|_| bad())?;
+            if exp >= 0 {
+                NANOS_PER_UNIT
+                    .checked_mul(10i128.checked_pow(exp as u32).ok_or_else(&bad)?)
+                    .ok_or_else(&bad)?
+            } else {
+                scale += (-exp) as u32;
+                NANOS_PER_UNIT
+            }
+        } else {
+            suffix_multiplier(suffix).ok_or_else(&bad)?
+        };
+
+        // like kubernetes, anything smaller than a nano is rounded up (away from zero) to one
+        let divisor = 10i128.checked_pow(scale).ok_or_else(&bad)?;
+        let nanos = div_ceil(mantissa.checked_mul(mult).ok_or_else(&bad)?, divisor);
+        Ok(Quantity {
+            nanos: if negative { -nanos } else { nanos },
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for Quantity {
+    fn deserialize<D>(deserializer: D) -> Result<Quantity, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // quantities are almost always strings, but plain numbers are allowed too
+        match Value::deserialize(deserializer)? {
+            Value::String(s) => s.parse().map_err(|e| D::Error::custom(format!("{}", e))),
+            Value::Number(n) => n.to_string()
+                .parse()
+                .map_err(|e| D::Error::custom(format!("{}", e))),
+            v => Err(D::Error::custom(format!("Invalid quantity: {}", v))),
+        }
+    }
+}
+
+/// The largest of suffixes (which are in decreasing order) that represents nanos exactly, and the
+/// number to go with it
+fn exact_suffix(nanos: i128, suffixes: &[&'static str]) -> Option<(i128, &'static str)> {
+    suffixes.iter().find(|s| nanos % suffix_multiplier(s).unwrap() == 0).map(|s| {
+        (nanos / suffix_multiplier(s).unwrap(), *s)
+    })
+}
+
+impl fmt::Display for Quantity {
+    /// Format using the largest suffix that represents the quantity exactly.  If both a binary and
+    /// a decimal suffix can, whichever gives the smaller number is used (the binary one if it's a
+    /// tie, since that's what memory is usually specified in), so 1Gi stays 1Gi and 3E stays 3E.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.nanos == 0 {
+            return write!(f, "0");
+        }
+        let binary = exact_suffix(self.nanos, &["Ei", "Pi", "Ti", "Gi", "Mi", "Ki"]);
+        // everything is divisible by 1n, so there's always a decimal suffix
+        let decimal = exact_suffix(
+            self.nanos,
+            &["E", "P", "T", "G", "M", "k", "", "m", "u", "n"],
+        ).unwrap();
+        let (num, suffix) = match binary {
+            Some(b) if b.0.abs() <= decimal.0.abs() => b,
+            _ => decimal,
+        };
+        write!(f, "{}{}", num, suffix)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+
+    fn add(self, other: Quantity) -> Quantity {
+        Quantity {
+            nanos: self.nanos + other.nanos,
+        }
+    }
+}
+
+impl AddAssign for Quantity {
+    fn add_assign(&mut self, other: Quantity) {
+        self.nanos += other.nanos;
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+
+    fn sub(self, other: Quantity) -> Quantity {
+        Quantity {
+            nanos: self.nanos - other.nanos,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn q(s: &str) -> Quantity {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_suffixes() {
+        assert_eq!(q("100m").as_millis(), 100);
+        assert_eq!(q("2").as_millis(), 2000);
+        assert_eq!(q("1.5").as_millis(), 1500);
+        assert_eq!(q("250u").as_millis(), 1);
+        assert_eq!(q("1Ki").as_f64(), 1024.0);
+        assert_eq!(q("1.5Gi").as_f64(), 1.5 * 1024.0 * 1024.0 * 1024.0);
+        assert_eq!(q("128974848").as_f64(), 128974848.0);
+        assert_eq!(q("129M").as_f64(), 129e6);
+        assert_eq!(q("-1k").as_f64(), -1000.0);
+        assert_eq!(q("+5"), Quantity::from_units(5));
+        assert!(q("0").is_zero());
+        assert!(q("0Mi").is_zero());
+        assert_eq!(Quantity::zero(), q("0m"));
+    }
+
+    #[test]
+    fn negative_and_sub_nano_rounding() {
+        assert_eq!(q("-1m").as_millis(), -1);
+        assert_eq!(q("-1.5m").as_millis(), -1);
+        assert_eq!(q("-250u").as_millis(), 0);
+        assert_eq!(q("-2").as_millis(), -2000);
+        assert_eq!(q("1.5m").as_millis(), 2);
+        assert_eq!(q("0.1n"), q("1n"));
+        assert_eq!(q("1.01n"), q("2n"));
+        assert_eq!(q("-0.1n"), q("-1n"));
+        assert_eq!(q("1e-10").to_string(), "1n");
+        assert!(!q("0.1n").is_zero());
+    }
+
+    #[test]
+    fn parse_exa_and_exponents() {
+        assert_eq!(q("1E"), Quantity::from_units(1_000_000_000_000_000_000));
+        assert_eq!(q("1Ei"), Quantity::from_units(1 << 60));
+        assert_eq!(q("2Ei").to_string(), "2Ei");
+        assert_eq!(q("1e3"), Quantity::from_units(1000));
+        assert_eq!(q("1E3"), Quantity::from_units(1000));
+        assert_eq!(q("12e+2"), Quantity::from_units(1200));
+        assert_eq!(q("5e-3").as_millis(), 5);
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for bad in ["", "Mi", "1.2.3", "1Q", "1e", "1e+", "1ex", "--1", "1 Gi x"].iter() {
+            assert!(bad.parse::<Quantity>().is_err(), "{} should be invalid", bad);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "0", "1", "100m", "1500m", "1Ki", "1536Ki", "1Gi", "1G", "3E", "1Ei", "7Pi", "250n",
+            "-2Mi",
+        ].iter()
+        {
+            let parsed = q(s);
+            assert_eq!(parsed.to_string(), *s);
+            assert_eq!(q(parsed.to_string().as_str()), parsed);
+        }
+        // the largest exact suffix is used
+        assert_eq!(q("1024Mi").to_string(), "1Gi");
+        assert_eq!(q("1000k").to_string(), "1M");
+        assert_eq!(q("0.5").to_string(), "500m");
+    }
+
+    #[test]
+    fn deserialize_strings_and_numbers() {
+        let parsed: Vec<Quantity> = ::serde_json::from_str(r#"["4", 2, "0.5", 1.5]"#).unwrap();
+        assert_eq!(parsed, vec![q("4"), q("2"), q("500m"), q("1500m")]);
+        assert!(::serde_json::from_str::<Quantity>("true").is_err());
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(q("1Gi") + q("512Mi"), q("1.5Gi"));
+        assert_eq!(q("1") - q("250m"), q("750m"));
+        let mut total = Quantity::zero();
+        total += q("100m");
+        total += q("2");
+        assert_eq!(total.as_millis(), 2100);
+    }
+}
@@ -16,6 +16,7 @@
 /// applying filters and sorting
 
 use output::ClickWriter;
+use quantity::Quantity;
 
 use clap::ArgMatches;
 use prettytable::cell::Cell;
@@ -24,6 +25,8 @@ use prettytable::{format, Table};
 use regex::Regex;
 use term::terminfo::TerminfoTerminal;
 
+use std::cmp::Ordering;
+
 lazy_static! {
     static ref TBLFMT: format::TableFormat = format::FormatBuilder::new()
         .separators(
@@ -118,6 +121,15 @@ impl<'a> CellSpec<'a> {
         }
     }
 
+    /// The text of this cell, or None for an index cell (whose text depends on its position)
+    pub fn text(&self) -> Option<&str> {
+        match self.txt {
+            CellSpecTxt::Index => None,
+            CellSpecTxt::Str(s) => Some(s),
+            CellSpecTxt::String(ref s) => Some(s.as_str()),
+        }
+    }
+
     pub fn matches(&self, regex: &Regex) -> bool {
         match self.txt {
             CellSpecTxt::Index => false,
@@ -161,6 +173,28 @@ where
         .collect()
 }
 
+/// Compare two cells holding quantities (like "512Mi" and "1Gi") by value.  Cells that don't
+/// parse as quantities (like "<none>") sort after those that do, and among themselves by text.
+pub fn cmp_quantity_cells(a: &CellSpec, b: &CellSpec) -> Ordering {
+    let qa = a.text().and_then(|t| t.parse::<Quantity>().ok());
+    let qb = b.text().and_then(|t| t.parse::<Quantity>().ok());
+    match (qa, qb) {
+        (Some(qa), Some(qb)) => qa.cmp(&qb),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.text().cmp(&b.text()),
+    }
+}
+
+/// Sort rows by the quantity in the specified column, smallest first
+#[allow(dead_code)]
+pub fn sort_by_quantity<'a, T>(specs: &mut Vec<(T, Vec<CellSpec<'a>>)>, column: usize) {
+    specs.sort_by(|a, b| match (a.1.get(column), b.1.get(column)) {
+        (Some(ca), Some(cb)) => cmp_quantity_cells(ca, cb),
+        _ => Ordering::Equal,
+    });
+}
+
 fn term_print_table(table: &Table, writer: &mut ClickWriter) -> bool {
     match TerminfoTerminal::new(writer) {
         Some(ref mut term) => {
@@ -185,3 +219,38 @@ pub fn print_table<'a, T>(
         table.print(writer).unwrap_or(());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_mixed_quantities() {
+        let sizes = vec!["1Gi", "<none>", "512Mi", "1G", "1.5Gi", "100M", "2k", "1500m", "n/a"];
+        let mut specs: Vec<(usize, Vec<CellSpec>)> = sizes
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (i, vec![CellSpec::new_index(), CellSpec::new(s)]))
+            .collect();
+        sort_by_quantity(&mut specs, 1);
+        let sorted: Vec<&str> = specs.iter().map(|s| sizes[s.0]).collect();
+        assert_eq!(
+            sorted,
+            vec!["1500m", "2k", "100M", "512Mi", "1G", "1Gi", "1.5Gi", "<none>", "n/a"]
+        );
+
+        // a lexical sort would put "1Gi" first
+        assert_eq!(
+            cmp_quantity_cells(&CellSpec::new("1Gi"), &CellSpec::new("512Mi")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            cmp_quantity_cells(&CellSpec::new("1000"), &CellSpec::new("1k")),
+            Ordering::Equal
+        );
+        // rows without the column keep their place
+        let mut short: Vec<(usize, Vec<CellSpec>)> = vec![(0, vec![]), (1, vec![])];
+        sort_by_quantity(&mut short, 1);
+        assert_eq!(short.iter().map(|s| s.0).collect::<Vec<_>>(), vec![0, 1]);
+    }
+}
@@ -15,6 +15,7 @@
 /// Helper functions to deal with Values
 
 use serde;
+use serde_json::Map;
 use serde_json::value::Value;
 
 use error::KubeError;
@@ -75,3 +76,164 @@ where
         None => Err(KubeError::ParseErr("Can't deserialize".to_owned())),
     }
 }
+
+/// A field that differs between two versions of an object
+#[derive(Debug, PartialEq)]
+pub struct FieldDiff {
+    /// JSON pointer to the field, like /spec/replicas
+    pub path: String,
+    /// None if the field was added
+    pub old: Option<Value>,
+    /// None if the field was removed
+    pub new: Option<Value>,
+}
+
+fn escape_pointer_part(part: &str) -> String {
+    part.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_into(path: &str, old: Option<&Value>, new: Option<&Value>, diffs: &mut Vec<FieldDiff>) {
+    match (old, new) {
+        (Some(&Value::Object(ref o)), Some(&Value::Object(ref n))) => {
+            let mut keys: Vec<&String> = o.keys().chain(n.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys.into_iter() {
+                let child = format!("{}/{}", path, escape_pointer_part(key));
+                diff_into(child.as_str(), o.get(key), n.get(key), diffs);
+            }
+        }
+        // lists of the same length are compared item by item, otherwise the whole list changed
+        (Some(&Value::Array(ref o)), Some(&Value::Array(ref n))) if o.len() == n.len() => {
+            for (i, (oi, ni)) in o.iter().zip(n.iter()).enumerate() {
+                diff_into(format!("{}/{}", path, i).as_str(), Some(oi), Some(ni), diffs);
+            }
+        }
+        (o, n) => {
+            if o != n {
+                diffs.push(FieldDiff {
+                    path: path.to_owned(),
+                    old: o.cloned(),
+                    new: n.cloned(),
+                });
+            }
+        }
+    }
+}
+
+/// Get the leaf fields that differ between old and new, in path order
+pub fn diff_values(old: &Value, new: &Value) -> Vec<FieldDiff> {
+    let mut diffs = Vec::new();
+    diff_into("", Some(old), Some(new), &mut diffs);
+    diffs
+}
+
+/// Remove the parts of an object's metadata that are bookkeeping for kubectl apply and server
+/// side apply (the last-applied-configuration annotation and managedFields).  They're large and
+/// just get in the way when viewing or editing an object.
+pub fn strip_apply_metadata(value: &mut Value) {
+    if let Some(metadata) = value.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+        metadata.remove("managedFields");
+        let now_empty = match metadata.get_mut("annotations").and_then(|a| a.as_object_mut()) {
+            Some(annotations) => {
+                annotations.remove("kubectl.kubernetes.io/last-applied-configuration");
+                annotations.is_empty()
+            }
+            None => false,
+        };
+        if now_empty {
+            metadata.remove("annotations");
+        }
+    }
+}
+
+/// Remove everything the server fills in or manages from an object (its status, and metadata
+/// like uid and resourceVersion), along with the apply bookkeeping strip_apply_metadata removes,
+/// leaving what's needed to create it again, possibly in another cluster.  A service's cluster
+/// ip is removed too, since it's allocated per cluster.
+pub fn strip_server_fields(value: &mut Value) {
+    strip_apply_metadata(value);
+    if let Some(obj) = value.as_object_mut() {
+        obj.remove("status");
+        if let Some(metadata) = obj.get_mut("metadata").and_then(|m| m.as_object_mut()) {
+            for field in [
+                "uid",
+                "resourceVersion",
+                "generation",
+                "creationTimestamp",
+                "deletionTimestamp",
+                "selfLink",
+                "ownerReferences",
+            ].iter()
+            {
+                metadata.remove(*field);
+            }
+        }
+        let is_service = obj.get("kind").and_then(|k| k.as_str()) == Some("Service");
+        if is_service {
+            if let Some(spec) = obj.get_mut("spec").and_then(|s| s.as_object_mut()) {
+                spec.remove("clusterIP");
+                spec.remove("clusterIPs");
+            }
+        }
+    }
+}
+
+/// Build a JSON merge patch (RFC 7386) that turns old into new.  Objects are patched key by key,
+/// with removed keys set to null, while anything else (including lists) that differs is replaced
+/// wholesale.
+pub fn create_merge_patch(old: &Value, new: &Value) -> Value {
+    match (old, new) {
+        (&Value::Object(ref o), &Value::Object(ref n)) => {
+            let mut patch = Map::new();
+            for key in o.keys() {
+                if !n.contains_key(key) {
+                    patch.insert(key.clone(), Value::Null);
+                }
+            }
+            for (key, nv) in n.iter() {
+                match o.get(key) {
+                    Some(ov) if ov == nv => {}
+                    Some(ov) => {
+                        patch.insert(key.clone(), create_merge_patch(ov, nv));
+                    }
+                    None => {
+                        patch.insert(key.clone(), nv.clone());
+                    }
+                }
+            }
+            Value::Object(patch)
+        }
+        _ => new.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_apply_bookkeeping() {
+        let mut obj = json!({
+            "metadata": {
+                "name": "web",
+                "managedFields": [{"manager": "kubectl", "operation": "Apply"}],
+                "annotations": {"kubectl.kubernetes.io/last-applied-configuration": "{}"},
+            },
+            "spec": {"replicas": 2},
+        });
+        strip_apply_metadata(&mut obj);
+        assert_eq!(obj, json!({"metadata": {"name": "web"}, "spec": {"replicas": 2}}));
+
+        // other annotations stay
+        let mut obj = json!({"metadata": {"annotations": {
+            "kubectl.kubernetes.io/last-applied-configuration": "{}",
+            "team": "web",
+        }}});
+        strip_apply_metadata(&mut obj);
+        assert_eq!(obj, json!({"metadata": {"annotations": {"team": "web"}}}));
+        let mut not_an_object = json!("web");
+        strip_apply_metadata(&mut not_an_object);
+        assert_eq!(not_an_object, json!("web"));
+    }
+}